@@ -0,0 +1,341 @@
+use brush_parser::word::WordPiece;
+
+/// A character in a word undergoing brace expansion.
+#[derive(Clone, Copy, Debug)]
+struct WordChar {
+    value: char,
+    /// Whether the character appeared outside of any quoting, escaping, or expansion, and
+    /// is therefore eligible to delimit a brace expression.
+    unquoted: bool,
+}
+
+/// A brace expression found in a word.
+struct BraceExpr {
+    /// Index of the opening brace.
+    open: usize,
+    /// Index of the closing brace.
+    close: usize,
+    /// The words the expression expands to.
+    expansions: Vec<Vec<WordChar>>,
+}
+
+/// Performs brace expansion on the given unexpanded word, yielding the resulting words in
+/// order. Braces are only recognized where they appear unquoted, unescaped, and outside of
+/// any parameter, command, or arithmetic expansion. The resulting words retain their quoting
+/// and are still subject to all other expansions.
+///
+/// # Arguments
+///
+/// * `word` - The word to expand.
+/// * `parser_options` - The options to use when parsing the word.
+pub(crate) fn expand_braces(
+    word: &str,
+    parser_options: &brush_parser::ParserOptions,
+) -> Vec<String> {
+    if !word.contains('{') {
+        return vec![word.to_owned()];
+    }
+
+    // If the word can't be parsed, we leave it as-is; subsequent expansion will report
+    // the problem.
+    let Ok(pieces) = brush_parser::word::parse(word, parser_options) else {
+        return vec![word.to_owned()];
+    };
+
+    let mut chars = vec![];
+    let mut next_index = 0;
+    for piece in pieces {
+        if piece.start_index > next_index {
+            push_chars(&mut chars, &word[next_index..piece.start_index], false);
+        }
+
+        let unquoted = matches!(piece.piece, WordPiece::Text(_) | WordPiece::TildePrefix(_));
+        push_chars(
+            &mut chars,
+            &word[piece.start_index..piece.end_index],
+            unquoted,
+        );
+
+        next_index = piece.end_index;
+    }
+
+    if next_index < word.len() {
+        push_chars(&mut chars, &word[next_index..], false);
+    }
+
+    expand(&chars)
+        .into_iter()
+        .map(|expanded| expanded.into_iter().map(|c| c.value).collect())
+        .collect()
+}
+
+fn push_chars(chars: &mut Vec<WordChar>, s: &str, unquoted: bool) {
+    chars.extend(s.chars().map(|value| WordChar { value, unquoted }));
+}
+
+fn expand(word: &[WordChar]) -> Vec<Vec<WordChar>> {
+    let Some(brace_expr) = find_brace_expr(word) else {
+        return vec![word.to_vec()];
+    };
+
+    let preamble = &word[..brace_expr.open];
+    let expanded_postambles = expand(&word[brace_expr.close + 1..]);
+
+    let mut results = vec![];
+    for expansion in brace_expr.expansions {
+        for postamble in &expanded_postambles {
+            let mut result = preamble.to_vec();
+            result.extend_from_slice(expansion.as_slice());
+            result.extend_from_slice(postamble.as_slice());
+            results.push(result);
+        }
+    }
+
+    results
+}
+
+/// Finds the first valid brace expression in the given word. Braces that don't delimit
+/// a list of alternatives or a sequence expression are treated as literal text.
+fn find_brace_expr(word: &[WordChar]) -> Option<BraceExpr> {
+    let mut search_start = 0;
+
+    while let Some(open) = find_unquoted(word, search_start, '{') {
+        if let Some(close) = find_matching_close_brace(word, open) {
+            let content = &word[open + 1..close];
+            if let Some(expansions) =
+                expand_alternatives(content).or_else(|| expand_sequence(content))
+            {
+                return Some(BraceExpr {
+                    open,
+                    close,
+                    expansions,
+                });
+            }
+        }
+
+        search_start = open + 1;
+    }
+
+    None
+}
+
+fn find_unquoted(word: &[WordChar], start: usize, value: char) -> Option<usize> {
+    word.iter()
+        .skip(start)
+        .position(|c| c.unquoted && c.value == value)
+        .map(|i| i + start)
+}
+
+fn find_matching_close_brace(word: &[WordChar], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in word.iter().enumerate().skip(open + 1) {
+        match c.value {
+            '{' if c.unquoted => depth += 1,
+            '}' if c.unquoted => {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// Expands a comma-separated list of alternatives (e.g., `a,b,c`), each of which may itself
+/// contain brace expressions. Returns `None` if there's no top-level comma.
+fn expand_alternatives(content: &[WordChar]) -> Option<Vec<Vec<WordChar>>> {
+    let mut alternatives = vec![];
+    let mut depth = 0;
+    let mut alternative_start = 0;
+
+    for (i, c) in content.iter().enumerate() {
+        if !c.unquoted {
+            continue;
+        }
+
+        match c.value {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            ',' if depth == 0 => {
+                alternatives.push(&content[alternative_start..i]);
+                alternative_start = i + 1;
+            }
+            _ => (),
+        }
+    }
+
+    if alternatives.is_empty() {
+        return None;
+    }
+
+    alternatives.push(&content[alternative_start..]);
+
+    Some(alternatives.into_iter().flat_map(expand).collect())
+}
+
+/// Expands a sequence expression of the form `x..y[..incr]`, where `x` and `y` are either
+/// both integers or both single letters. Returns `None` if the content isn't a valid
+/// sequence expression.
+fn expand_sequence(content: &[WordChar]) -> Option<Vec<Vec<WordChar>>> {
+    if !content.iter().all(|c| c.unquoted) {
+        return None;
+    }
+
+    let content: String = content.iter().map(|c| c.value).collect();
+    let parts: Vec<_> = content.split("..").collect();
+
+    let (start, end, increment) = match parts.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, increment] => (*start, *end, increment.parse::<i64>().ok()?),
+        _ => return None,
+    };
+
+    let values: Vec<String> = if let (Ok(start_value), Ok(end_value)) = (start.parse(), end.parse())
+    {
+        let width = std::cmp::max(zero_padded_width(start), zero_padded_width(end));
+        integer_sequence(start_value, end_value, increment)?
+            .into_iter()
+            .map(|value| std::format!("{value:0width$}"))
+            .collect()
+    } else {
+        let start_value = single_letter(start)?;
+        let end_value = single_letter(end)?;
+
+        integer_sequence(i64::from(start_value), i64::from(end_value), increment)?
+            .into_iter()
+            .filter_map(|value| u32::try_from(value).ok().and_then(char::from_u32))
+            .map(|c| {
+                // Characters between 'Z' and 'a' need to be escaped, since the word will
+                // be subsequently expanded.
+                if c.is_ascii_alphanumeric() {
+                    c.to_string()
+                } else {
+                    std::format!("\\{c}")
+                }
+            })
+            .collect()
+    };
+
+    Some(
+        values
+            .into_iter()
+            .map(|value| {
+                value
+                    .chars()
+                    .map(|value| WordChar {
+                        value,
+                        unquoted: false,
+                    })
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+/// Generates the values from `start` to `end` (inclusive), stepping by the magnitude of
+/// `increment` in the direction of `end`.
+fn integer_sequence(start: i64, end: i64, increment: i64) -> Option<Vec<i64>> {
+    let magnitude = increment.checked_abs()?.max(1);
+    let step = if start <= end { magnitude } else { -magnitude };
+
+    let mut values = vec![];
+    let mut current = Some(start);
+    while let Some(value) = current {
+        if (step > 0 && value > end) || (step < 0 && value < end) {
+            break;
+        }
+
+        values.push(value);
+        current = value.checked_add(step);
+    }
+
+    Some(values)
+}
+
+/// Returns the width to which values should be zero-padded, if the given sequence bound
+/// was written with leading zeros; otherwise returns 0.
+fn zero_padded_width(bound: &str) -> usize {
+    let digits = bound.strip_prefix('-').unwrap_or(bound);
+    if digits.len() > 1 && digits.starts_with('0') {
+        bound.len()
+    } else {
+        0
+    }
+}
+
+fn single_letter(s: &str) -> Option<u8> {
+    match s.as_bytes() {
+        [c] if c.is_ascii_alphabetic() => Some(*c),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_str(word: &str) -> Vec<String> {
+        expand_braces(word, &brush_parser::ParserOptions::default())
+    }
+
+    #[test]
+    fn test_alternatives() {
+        assert_eq!(expand_str("a{b,c}d"), vec!["abd", "acd"]);
+        assert_eq!(expand_str("{a,b}{1,2}"), vec!["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand_str("{a,{b,c}}"), vec!["a", "b", "c"]);
+        assert_eq!(expand_str("{{a,b}}"), vec!["{a}", "{b}"]);
+        assert_eq!(expand_str("x{a,}"), vec!["xa", "x"]);
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        assert_eq!(expand_str("{}"), vec!["{}"]);
+        assert_eq!(expand_str("{a}"), vec!["{a}"]);
+        assert_eq!(expand_str("{a,b"), vec!["{a,b"]);
+        assert_eq!(expand_str("{a}{b,c}"), vec!["{a}b", "{a}c"]);
+    }
+
+    #[test]
+    fn test_quoted_braces() {
+        assert_eq!(expand_str("\"{a,b}\""), vec!["\"{a,b}\""]);
+        assert_eq!(expand_str("'{a,b}'"), vec!["'{a,b}'"]);
+        assert_eq!(expand_str(r"\{a,b}"), vec![r"\{a,b}"]);
+        assert_eq!(expand_str("${x}"), vec!["${x}"]);
+        assert_eq!(
+            expand_str("\"$dir\"/{a,b}.txt"),
+            vec!["\"$dir\"/a.txt", "\"$dir\"/b.txt"]
+        );
+        assert_eq!(expand_str("{\"a,b\",c}"), vec!["\"a,b\"", "c"]);
+        assert_eq!(expand_str("{$x,y}"), vec!["$x", "y"]);
+    }
+
+    #[test]
+    fn test_sequences() {
+        assert_eq!(expand_str("{1..3}"), vec!["1", "2", "3"]);
+        assert_eq!(expand_str("{3..1}"), vec!["3", "2", "1"]);
+        assert_eq!(expand_str("{-1..1}"), vec!["-1", "0", "1"]);
+        assert_eq!(expand_str("{1..10..4}"), vec!["1", "5", "9"]);
+        assert_eq!(expand_str("{10..1..-4}"), vec!["10", "6", "2"]);
+        assert_eq!(expand_str("{01..10..3}"), vec!["01", "04", "07", "10"]);
+        assert_eq!(expand_str("{a..e..2}"), vec!["a", "c", "e"]);
+        assert_eq!(
+            expand_str("{Z..b}"),
+            vec!["Z", r"\[", r"\\", r"\]", r"\^", r"\_", r"\`", "a", "b"]
+        );
+        assert_eq!(
+            expand_str("pre{1..2}\"$x\""),
+            vec!["pre1\"$x\"", "pre2\"$x\""]
+        );
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        assert_eq!(expand_str("{1..a}"), vec!["{1..a}"]);
+        assert_eq!(expand_str("{1..$n}"), vec!["{1..$n}"]);
+        assert_eq!(expand_str("{ab..c}"), vec!["{ab..c}"]);
+        assert_eq!(expand_str("{1..2..x}"), vec!["{1..2..x}"]);
+    }
+}
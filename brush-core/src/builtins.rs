@@ -38,6 +38,7 @@ mod jobs;
 #[cfg(unix)]
 mod kill;
 mod let_;
+pub mod loadable;
 mod popd;
 mod printf;
 mod pushd;
@@ -275,6 +276,9 @@ pub struct Registration {
 
     /// Is this builtin one that takes specially handled declarations?
     pub declaration_builtin: bool,
+
+    /// The builtin's implementation, if it was loaded from a shared object.
+    pub loaded_builtin: Option<std::sync::Arc<loadable::LoadedBuiltin>>,
 }
 
 fn get_builtin_man_page(_name: &str, _command: &clap::Command) -> Result<String, error::Error> {
@@ -1,19 +1,20 @@
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 
 use clap::Parser;
+use itertools::Itertools;
 
-use crate::{builtins, commands};
+use crate::{builtins, commands, expansion, Shell};
 
 /// Change the current shell working directory.
 #[derive(Parser)]
 pub(crate) struct CdCommand {
     /// Force following symlinks.
-    #[arg(short = 'L')]
+    #[arg(short = 'L', overrides_with = "use_physical_dir")]
     force_follow_symlinks: bool,
 
     /// Use physical dir structure without following symlinks.
-    #[arg(short = 'P')]
+    #[arg(short = 'P', overrides_with = "force_follow_symlinks")]
     use_physical_dir: bool,
 
     /// Exit with non zero exit status if current working directory resolution fails.
@@ -35,15 +36,26 @@ impl builtins::Command for CdCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        // TODO: implement options
-        if self.force_follow_symlinks
-            || self.use_physical_dir
-            || self.exit_on_failed_cwd_resolution
-            || self.file_with_xattr_as_dir
-        {
-            return crate::error::unimp("options to cd");
+        // Extended attributes aren't supported on any platforms we target.
+        if self.file_with_xattr_as_dir {
+            writeln!(
+                context.stderr(),
+                "{}: -@: invalid option",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::InvalidUsage);
         }
 
+        // N.B. The -e option only has an effect when the physical working directory can't
+        // be determined after changing directories; since we determine it as part of the
+        // change, there's nothing more to do for it.
+        let physical = self.use_physical_dir
+            || (!self.force_follow_symlinks
+                && context
+                    .shell
+                    .options
+                    .do_not_resolve_symlinks_when_changing_dir);
+
         let mut should_print = false;
         let target_dir = if let Some(target_dir) = &self.target_dir {
             // `cd -', equivalent to `cd $OLDPWD'
@@ -69,20 +81,182 @@ impl builtins::Command for CdCommand {
             }
         };
 
-        if let Err(e) = context.shell.set_working_dir(&target_dir) {
-            writeln!(context.stderr(), "cd: {e}")?;
-            return Ok(builtins::ExitCode::Custom(1));
-        }
-
         // Bash compatibility
         // https://www.gnu.org/software/bash/manual/bash.html#index-cd
         // If a non-empty directory name from CDPATH is used, or if '-' is the first argument, and
         // the directory change is successful, the absolute pathname of the new working
         // directory is written to the standard output.
+        if self.target_dir.is_some() && !should_print && uses_cdpath(&target_dir) {
+            for (cdpath_entry, candidate) in cdpath_candidates(context.shell, &target_dir) {
+                if context.shell.get_absolute_path(&candidate).is_dir()
+                    && context
+                        .shell
+                        .set_working_dir_and_run_hooks(&candidate, physical)
+                        .await
+                        .is_ok()
+                {
+                    if !cdpath_entry.is_empty() {
+                        writeln!(context.stdout(), "{}", context.shell.working_dir.display())?;
+                    }
+
+                    return Ok(builtins::ExitCode::Success);
+                }
+            }
+        }
+
+        if let Err(e) = context
+            .shell
+            .set_working_dir_and_run_hooks(&target_dir, physical)
+            .await
+        {
+            // With `cdable_vars`, a name that's not a directory may name a variable whose
+            // value is the directory.
+            if context.shell.options.cdable_vars {
+                if let Some(var_value) = cdable_var_value(context.shell, &target_dir) {
+                    if context
+                        .shell
+                        .set_working_dir_and_run_hooks(Path::new(&var_value), physical)
+                        .await
+                        .is_ok()
+                    {
+                        writeln!(context.stdout(), "{var_value}")?;
+                        return Ok(builtins::ExitCode::Success);
+                    }
+                }
+            }
+
+            // With `cdspell`, interactive shells correct minor misspellings.
+            if context.shell.options.interactive && context.shell.options.cd_autocorrect_spelling {
+                if let Some(corrected) = correct_dir_spelling(context.shell, &target_dir) {
+                    if context
+                        .shell
+                        .set_working_dir_and_run_hooks(&corrected, physical)
+                        .await
+                        .is_ok()
+                    {
+                        writeln!(context.stdout(), "{}", corrected.display())?;
+                        return Ok(builtins::ExitCode::Success);
+                    }
+                }
+            }
+
+            writeln!(context.stderr(), "cd: {e}")?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
         if should_print {
-            writeln!(context.stdout(), "{}", target_dir.display())?;
+            writeln!(context.stdout(), "{}", context.shell.working_dir.display())?;
         }
 
         Ok(builtins::ExitCode::Success)
     }
 }
+
+/// Checks whether `CDPATH` should be searched for the given directory; that's only done for
+/// relative paths that don't start with `.` or `..`.
+fn uses_cdpath(target_dir: &Path) -> bool {
+    matches!(target_dir.components().next(), Some(Component::Normal(_)))
+}
+
+/// Returns the candidate paths for the given directory, paired with the `CDPATH` entries
+/// that produced them. An empty entry refers to the current directory.
+fn cdpath_candidates(shell: &Shell, target_dir: &Path) -> Vec<(String, PathBuf)> {
+    let Some(cdpath) = shell.env.get_str("CDPATH") else {
+        return vec![];
+    };
+
+    cdpath
+        .split(':')
+        .map(|entry| {
+            let candidate = if entry.is_empty() {
+                target_dir.to_owned()
+            } else {
+                Path::new(entry).join(target_dir)
+            };
+
+            (entry.to_owned(), candidate)
+        })
+        .collect()
+}
+
+fn cdable_var_value(shell: &Shell, target_dir: &Path) -> Option<String> {
+    let name = target_dir.to_str()?;
+    if !expansion::valid_variable_name(name) {
+        return None;
+    }
+
+    shell.env.get_str(name).map(|value| value.to_string())
+}
+
+/// Tries to correct minor misspellings in each component of the given directory path, by
+/// finding the closest-named entry in the directory containing it.
+fn correct_dir_spelling(shell: &Shell, dir: &Path) -> Option<PathBuf> {
+    let mut corrected = PathBuf::new();
+
+    for component in dir.components() {
+        let Component::Normal(name) = component else {
+            corrected.push(component);
+            continue;
+        };
+
+        let name = name.to_str()?;
+        let containing_dir = shell.get_absolute_path(&corrected);
+        let best_match = std::fs::read_dir(containing_dir)
+            .ok()?
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .sorted()
+            .map(|entry| (spelling_distance(entry.as_str(), name), entry))
+            .filter(|(distance, _)| *distance < 3)
+            .min_by_key(|(distance, _)| *distance)?;
+
+        corrected.push(best_match.1);
+    }
+
+    Some(corrected)
+}
+
+/// Computes the distance between a candidate name and a possibly misspelled one, the way
+/// bash does: 0 for an exact match, 1 for a transposition of two adjacent characters, 2
+/// for a single incorrect, extra, or missing character, and 3 otherwise.
+fn spelling_distance(candidate: &str, guess: &str) -> u8 {
+    let candidate: Vec<_> = candidate.chars().collect();
+    let guess: Vec<_> = guess.chars().collect();
+
+    let common_prefix_len = candidate
+        .iter()
+        .zip(guess.iter())
+        .take_while(|(c, g)| c == g)
+        .count();
+    let candidate = &candidate[common_prefix_len..];
+    let guess = &guess[common_prefix_len..];
+
+    match (candidate, guess) {
+        ([], []) => 0,
+        ([c0, c1, c_rest @ ..], [g0, g1, g_rest @ ..])
+            if c0 == g1 && c1 == g0 && c_rest == g_rest =>
+        {
+            1
+        }
+        ([_, c_rest @ ..], [_, g_rest @ ..]) if c_rest == g_rest => 2,
+        ([_, c_rest @ ..], _) if c_rest == guess => 2,
+        (_, [_, g_rest @ ..]) if candidate == g_rest => 2,
+        _ => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spelling_distance() {
+        assert_eq!(spelling_distance("source", "source"), 0);
+        assert_eq!(spelling_distance("source", "suorce"), 1);
+        assert_eq!(spelling_distance("source", "sourec"), 1);
+        assert_eq!(spelling_distance("source", "sourse"), 2);
+        assert_eq!(spelling_distance("source", "sorce"), 2);
+        assert_eq!(spelling_distance("source", "sourcee"), 2);
+        assert_eq!(spelling_distance("source", "sauce"), 3);
+        assert_eq!(spelling_distance("src", "source"), 3);
+    }
+}
@@ -31,10 +31,6 @@ impl builtins::Command for CommandCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<builtins::ExitCode, error::Error> {
-        if self.use_default_path {
-            return error::unimp("command -p");
-        }
-
         if self.print_description || self.print_verbose_description {
             if let Some(found_cmd) = self.try_find_command(context.shell) {
                 if self.print_description {
@@ -104,12 +100,24 @@ impl CommandCommand {
                 }
             }
 
-            shell
-                .find_first_executable_in_path_using_cache(&self.command_name)
-                .map(|path| FoundCommand::External(path.to_string_lossy().to_string()))
+            let found_path = if self.use_default_path {
+                shell.find_first_executable_in_default_path(&self.command_name)
+            } else {
+                shell.find_first_executable_in_path_using_cache(&self.command_name)
+            };
+
+            found_path.map(|path| FoundCommand::External(path.to_string_lossy().to_string()))
         }
     }
 
+    fn is_enabled_builtin(&self, context: &commands::ExecutionContext<'_>) -> bool {
+        context
+            .shell
+            .builtins
+            .get(self.command_name.as_str())
+            .is_some_and(|builtin| !builtin.disabled)
+    }
+
     async fn execute_command(
         &self,
         mut context: commands::ExecutionContext<'_>,
@@ -125,9 +133,38 @@ impl CommandCommand {
         // We do not have an existing process group to place this into.
         let mut pgid = None;
 
+        // With -p, external commands are looked up in the system's default search path
+        // rather than the user's PATH.
+        let default_path_lookup = if self.use_default_path
+            && !self.command_name.contains(std::path::MAIN_SEPARATOR)
+            && !self.is_enabled_builtin(&context)
+        {
+            Some(
+                context
+                    .shell
+                    .find_first_executable_in_default_path(&self.command_name),
+            )
+        } else {
+            None
+        };
+
+        let spawn_result = match default_path_lookup {
+            Some(Some(path)) => commands::execute_external_command(
+                context,
+                path.to_string_lossy().as_ref(),
+                &mut pgid,
+                &args[1..],
+            )?,
+            Some(None) => {
+                tracing::error!("{}: command not found", self.command_name);
+                commands::CommandSpawnResult::ImmediateExit(127)
+            }
+            None => commands::execute(context, &mut pgid, args, false /* use functions? */).await?,
+        };
+
         #[allow(clippy::cast_possible_truncation)]
         #[allow(clippy::cast_sign_loss)]
-        match commands::execute(context, &mut pgid, args, false /* use functions? */).await? {
+        match spawn_result {
             commands::CommandSpawnResult::SpawnedProcess(mut child) => {
                 // TODO: jobs: review this logic
                 let wait_result = child.wait().await?;
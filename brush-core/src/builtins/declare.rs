@@ -4,8 +4,8 @@ use std::io::Write;
 
 use crate::{
     builtins, commands,
-    env::{EnvironmentLookup, EnvironmentScope},
-    error,
+    env::{self, EnvironmentLookup, EnvironmentScope},
+    error, expansion,
     variables::{
         self, ArrayLiteral, ShellValue, ShellValueLiteral, ShellValueUnsetType, ShellVariable,
         ShellVariableUpdateTransform,
@@ -125,13 +125,13 @@ impl builtins::Command for DeclareCommand {
             _ => DeclareVerb::Declare,
         };
 
-        // TODO: implement declare -I
-        if self.locals_inherit_from_prev_scope {
+        if matches!(verb, DeclareVerb::Local) && !context.shell.in_function() {
             writeln!(
                 context.stderr(),
-                "UNIMPLEMENTED: declare -I: locals inherit from previous scope"
+                "{}: can only be used in a function",
+                context.command_name
             )?;
-            return Ok(builtins::ExitCode::Unimplemented);
+            return Ok(builtins::ExitCode::Custom(1));
         }
 
         let mut result = builtins::ExitCode::Success;
@@ -202,7 +202,7 @@ impl DeclareCommand {
                 writeln!(context.stderr(), "declare: {name}: not found")?;
                 Ok(false)
             }
-        } else if let Some(variable) = context.shell.env.get_using_policy(name, lookup) {
+        } else if let Some(variable) = context.shell.env.get_unresolved_using_policy(name, lookup) {
             let mut cs = variable.get_attribute_flags();
             if cs.is_empty() {
                 cs.push('-');
@@ -242,22 +242,48 @@ impl DeclareCommand {
             return self.try_display_declaration(context, declaration, verb);
         }
 
+        // `local -` saves the shell's options, to be restored when the function returns.
+        if matches!(verb, DeclareVerb::Local)
+            && matches!(declaration, commands::CommandArg::String(s) if s == "-")
+        {
+            return Ok(context.shell.save_set_options_for_current_function());
+        }
+
         // Extract the variable name and the initial value being assigned (if any).
         let (name, assigned_index, initial_value, name_is_array) =
             Self::declaration_to_name_and_value(declaration)?;
 
         // Figure out where we should look.
-        let lookup = if create_var_local {
+        let mut lookup = if create_var_local {
             EnvironmentLookup::OnlyInCurrentLocal
         } else {
             EnvironmentLookup::Anywhere
         };
 
+        let mut name = name;
+        if self.make_nameref.is_some() {
+            if !self.validate_nameref_target(context, name.as_str(), initial_value.as_ref())? {
+                return Ok(false);
+            }
+        } else {
+            // Unless we're updating the name reference attribute itself, a declaration
+            // naming a name reference applies to the variable it refers to.
+            let resolved = context
+                .shell
+                .env
+                .resolve_nameref_using_policy(name.as_str(), lookup)?
+                .to_owned();
+            if resolved != name && env::split_array_element_name(resolved.as_str()).is_none() {
+                name = resolved;
+                lookup = EnvironmentLookup::Anywhere;
+            }
+        }
+
         // Look up the variable.
         if let Some(var) = context
             .shell
             .env
-            .get_mut_using_policy(name.as_str(), lookup)
+            .get_mut_unresolved_using_policy(name.as_str(), lookup)
         {
             if self.make_associative_array.is_some() {
                 var.convert_to_associative_array()?;
@@ -275,22 +301,33 @@ impl DeclareCommand {
 
             self.apply_attributes_after_update(var, verb)?;
         } else {
-            let unset_type = if self.make_indexed_array.is_some() {
-                ShellValueUnsetType::IndexedArray
-            } else if self.make_associative_array.is_some() {
-                ShellValueUnsetType::AssociativeArray
-            } else if name_is_array {
-                ShellValueUnsetType::IndexedArray
-            } else {
-                ShellValueUnsetType::Untyped
-            };
+            // A local variable can't shadow a readonly variable.
+            if create_var_local
+                && context
+                    .shell
+                    .env
+                    .get_unresolved(name.as_str())
+                    .is_some_and(|(_, var)| var.is_readonly())
+            {
+                writeln!(
+                    context.stderr(),
+                    "{}: {name}: readonly variable",
+                    context.command_name
+                )?;
+                return Ok(false);
+            }
 
-            let mut var = ShellVariable::new(ShellValue::Unset(unset_type));
+            let (mut var, inherited) = self.inherit_from_enclosing_scope(
+                context,
+                name.as_str(),
+                create_var_local,
+                name_is_array,
+            )?;
 
             self.apply_attributes_before_update(&mut var)?;
 
             if let Some(initial_value) = initial_value {
-                var.assign(initial_value, false)?;
+                var.assign(initial_value, inherited && assigned_index.is_some())?;
             }
 
             self.apply_attributes_after_update(&mut var, verb)?;
@@ -307,6 +344,111 @@ impl DeclareCommand {
         Ok(true)
     }
 
+    /// Creates the variable for a declaration of a variable that doesn't yet exist in the
+    /// scope being declared into, returning it along with whether it was inherited from
+    /// the variable it shadows in an enclosing scope.
+    fn inherit_from_enclosing_scope(
+        &self,
+        context: &crate::commands::ExecutionContext<'_>,
+        name: &str,
+        create_var_local: bool,
+        name_is_array: bool,
+    ) -> Result<(ShellVariable, bool), error::Error> {
+        // When requested, a new local variable starts out with the value and attributes
+        // of the variable it shadows.
+        let inherited_var = if create_var_local
+            && (self.locals_inherit_from_prev_scope
+                || context.shell.options.local_vars_inherit_value_and_attrs)
+        {
+            context
+                .shell
+                .env
+                .get_unresolved(name)
+                .map(|(_, var)| var.clone())
+        } else {
+            None
+        };
+
+        let inherited = inherited_var.is_some();
+        let var = if let Some(mut var) = inherited_var {
+            // N.B. The name reference attribute isn't inherited.
+            var.unset_treat_as_nameref();
+
+            if self.make_associative_array.is_some() {
+                var.convert_to_associative_array()?;
+            }
+            if self.make_indexed_array.is_some() {
+                var.convert_to_indexed_array()?;
+            }
+
+            var
+        } else {
+            let unset_type = if self.make_indexed_array.is_some() {
+                ShellValueUnsetType::IndexedArray
+            } else if self.make_associative_array.is_some() {
+                ShellValueUnsetType::AssociativeArray
+            } else if name_is_array {
+                ShellValueUnsetType::IndexedArray
+            } else {
+                ShellValueUnsetType::Untyped
+            };
+
+            let mut var = ShellVariable::new(ShellValue::Unset(unset_type));
+
+            // N.B. For compatibility with bash, a local variable always inherits the
+            // export attribute of the variable it shadows.
+            if create_var_local
+                && context
+                    .shell
+                    .env
+                    .get_unresolved(name)
+                    .is_some_and(|(_, var)| var.is_exported())
+            {
+                var.export();
+            }
+
+            var
+        };
+
+        Ok((var, inherited))
+    }
+
+    /// Checks the target assigned to a variable being made a name reference (if any),
+    /// reporting an error and returning false if it's not valid.
+    fn validate_nameref_target(
+        &self,
+        context: &crate::commands::ExecutionContext<'_>,
+        name: &str,
+        initial_value: Option<&ShellValueLiteral>,
+    ) -> Result<bool, error::Error> {
+        let (Some(true), Some(ShellValueLiteral::Scalar(target))) =
+            (self.make_nameref.to_bool(), initial_value)
+        else {
+            return Ok(true);
+        };
+
+        let target = target.as_str();
+        let target_name = env::split_array_element_name(target).map_or(target, |(n, _)| n);
+
+        if target == name {
+            writeln!(
+                context.stderr(),
+                "{}: {name}: nameref variable self references not allowed",
+                context.command_name
+            )?;
+            Ok(false)
+        } else if !target.is_empty() && !expansion::valid_variable_name(target_name) {
+            writeln!(
+                context.stderr(),
+                "{}: `{target}': invalid variable name for name reference",
+                context.command_name
+            )?;
+            Ok(false)
+        } else {
+            Ok(true)
+        }
+    }
+
     #[allow(clippy::unwrap_in_result)]
     fn declaration_to_name_and_value(
         declaration: &commands::CommandArg,
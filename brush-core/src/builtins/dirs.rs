@@ -21,8 +21,11 @@ pub(crate) struct DirsCommand {
     /// Print one directory per line with its index.
     #[arg(short = 'v')]
     print_one_per_line_with_index: bool,
-    //
-    // TODO: implement +N and -N
+
+    /// Display only the Nth entry, counting from the left (+N) or right (-N) of the list
+    /// shown by dirs, starting with zero.
+    #[arg(allow_negative_numbers = true, value_name = "+N | -N")]
+    index: Option<String>,
 }
 
 impl builtins::Command for DirsCommand {
@@ -30,41 +33,133 @@ impl builtins::Command for DirsCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        context.shell.apply_dirstack_var_updates();
+
+        let index = match &self.index {
+            Some(arg) => {
+                let Some(index) = StackIndex::parse(arg) else {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {arg}: invalid option",
+                        context.command_name
+                    )?;
+                    return Ok(builtins::ExitCode::InvalidUsage);
+                };
+                Some((arg, index))
+            }
+            None => None,
+        };
+
         if self.clear {
             context.shell.directory_stack.clear();
-        } else {
-            let dirs = vec![&context.shell.working_dir]
-                .into_iter()
-                .chain(context.shell.directory_stack.iter().rev())
-                .collect::<Vec<_>>();
+            context.shell.update_dirstack_var();
+            return Ok(builtins::ExitCode::Success);
+        }
 
-            let one_per_line = self.print_one_per_line || self.print_one_per_line_with_index;
+        let dirs = vec![&context.shell.working_dir]
+            .into_iter()
+            .chain(context.shell.directory_stack.iter().rev())
+            .collect::<Vec<_>>();
 
-            for (i, dir) in dirs.iter().enumerate() {
-                if !one_per_line && i > 0 {
-                    write!(context.stdout(), " ")?;
-                }
+        // Figure out which entries we're displaying, along with their positions.
+        let selected_dirs = if let Some((arg, index)) = index {
+            let Some(position) = index.resolve(context.shell.directory_stack.len()) else {
+                write_index_error(&context, arg)?;
+                return Ok(builtins::ExitCode::Custom(1));
+            };
+            vec![(position, dirs[position])]
+        } else {
+            dirs.into_iter().enumerate().collect()
+        };
 
-                if self.print_one_per_line_with_index {
-                    write!(context.stdout(), "{i:2}  ")?;
-                }
+        let one_per_line = self.print_one_per_line || self.print_one_per_line_with_index;
 
-                let mut dir_str = dir.to_string_lossy().to_string();
+        for (i, (position, dir)) in selected_dirs.iter().enumerate() {
+            if !one_per_line && i > 0 {
+                write!(context.stdout(), " ")?;
+            }
 
-                if !self.tilde_long {
-                    dir_str = context.shell.tilde_shorten(dir_str);
-                }
+            if self.print_one_per_line_with_index {
+                write!(context.stdout(), "{position:2}  ")?;
+            }
 
-                write!(context.stdout(), "{dir_str}")?;
+            let mut dir_str = dir.to_string_lossy().to_string();
 
-                if one_per_line || i == dirs.len() - 1 {
-                    writeln!(context.stdout())?;
-                }
+            if !self.tilde_long {
+                dir_str = context.shell.tilde_shorten(dir_str);
             }
 
-            return Ok(builtins::ExitCode::Success);
+            write!(context.stdout(), "{dir_str}")?;
+
+            if one_per_line || i == selected_dirs.len() - 1 {
+                writeln!(context.stdout())?;
+            }
         }
 
         Ok(builtins::ExitCode::Success)
     }
 }
+
+/// Identifies an entry in the directory stack, as specified by a `+N` or `-N` argument.
+#[derive(Clone, Copy)]
+pub(crate) enum StackIndex {
+    /// The Nth entry counting from the left of the list shown by `dirs`.
+    FromLeft(usize),
+    /// The Nth entry counting from the right of the list shown by `dirs`.
+    FromRight(usize),
+}
+
+impl StackIndex {
+    /// Parses a `+N` or `-N` argument; returns `None` if the argument doesn't have that form.
+    ///
+    /// # Arguments
+    ///
+    /// * `arg` - The argument to parse.
+    pub fn parse(arg: &str) -> Option<Self> {
+        if let Some(n) = arg.strip_prefix('+') {
+            n.parse().ok().map(StackIndex::FromLeft)
+        } else if let Some(n) = arg.strip_prefix('-') {
+            n.parse().ok().map(StackIndex::FromRight)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the position of the identified entry in the list shown by `dirs`, where
+    /// the current working directory is at position 0. Returns `None` if there's no
+    /// such entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `stack_len` - The number of directories on the stack, not including the current
+    ///   working directory.
+    pub fn resolve(self, stack_len: usize) -> Option<usize> {
+        match self {
+            StackIndex::FromLeft(n) if n <= stack_len => Some(n),
+            StackIndex::FromRight(n) if n <= stack_len => Some(stack_len - n),
+            _ => None,
+        }
+    }
+}
+
+/// Reports that the given `+N` or `-N` argument doesn't identify an entry in the stack.
+pub(crate) fn write_index_error(
+    context: &commands::ExecutionContext<'_>,
+    arg: &str,
+) -> Result<(), crate::error::Error> {
+    if context.shell.directory_stack.is_empty() {
+        writeln!(
+            context.stderr(),
+            "{}: directory stack empty",
+            context.command_name
+        )?;
+    } else {
+        writeln!(
+            context.stderr(),
+            "{}: {arg}: directory stack index out of range",
+            context.command_name
+        )?;
+    }
+
+    Ok(())
+}
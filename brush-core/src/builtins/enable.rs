@@ -40,15 +40,18 @@ pub(crate) struct EnableCommand {
 impl builtins::Command for EnableCommand {
     async fn execute(
         &self,
-        context: commands::ExecutionContext<'_>,
+        mut context: commands::ExecutionContext<'_>,
     ) -> Result<builtins::ExitCode, crate::error::Error> {
         let mut result = builtins::ExitCode::Success;
 
-        if self.shared_object_path.is_some() {
-            return error::unimp("enable -f");
+        if let Some(shared_object_path) = &self.shared_object_path {
+            if !self.names.is_empty() {
+                return self.load_builtins(&mut context, shared_object_path);
+            }
         }
-        if self.remove_loaded_builtin {
-            return error::unimp("enable -d");
+
+        if self.remove_loaded_builtin && !self.names.is_empty() {
+            return self.remove_loaded_builtins(&mut context);
         }
 
         if !self.names.is_empty() {
@@ -92,3 +95,91 @@ impl builtins::Command for EnableCommand {
         Ok(result)
     }
 }
+
+impl EnableCommand {
+    /// Loads the named builtins from the given shared object.
+    ///
+    /// N.B. Brush plugins are passed the shell's open files explicitly, but while a bash
+    /// loadable runs, the process-wide standard input, output, and error are redirected to
+    /// the shell's open files. Calls into bash loadables are serialized, but anything else
+    /// in the process using those file descriptors directly at the same time will still be
+    /// affected.
+    fn load_builtins(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+        shared_object_path: &str,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let shared_object =
+            match builtins::loadable::open_shared_object(context.shell, shared_object_path) {
+                Ok(shared_object) => shared_object,
+                Err(e) => {
+                    writeln!(
+                        context.stderr(),
+                        "{}: cannot open shared object {shared_object_path}: {e}",
+                        context.command_name
+                    )?;
+                    return Ok(builtins::ExitCode::Custom(1));
+                }
+            };
+
+        // N.B. For compatibility with bash, this only fails if none of the builtins could
+        // be loaded.
+        let mut loaded_count = 0;
+        for name in &self.names {
+            match builtins::loadable::load_builtin(
+                context,
+                &shared_object,
+                shared_object_path,
+                name,
+            ) {
+                Ok(registration) => {
+                    context.shell.builtins.insert(name.clone(), registration);
+                    loaded_count += 1;
+                }
+                Err(e) => {
+                    writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+                }
+            }
+        }
+
+        if loaded_count == 0 {
+            Ok(builtins::ExitCode::Custom(1))
+        } else {
+            Ok(builtins::ExitCode::Success)
+        }
+    }
+
+    fn remove_loaded_builtins(
+        &self,
+        context: &mut commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, error::Error> {
+        let mut result = builtins::ExitCode::Success;
+
+        for name in &self.names {
+            let Some(registration) = context.shell.builtins.get(name) else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {name}: not a shell builtin",
+                    context.command_name
+                )?;
+                result = builtins::ExitCode::Custom(1);
+                continue;
+            };
+
+            let Some(loaded_builtin) = registration.loaded_builtin.clone() else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {name}: not dynamically loaded",
+                    context.command_name
+                )?;
+                result = builtins::ExitCode::Custom(1);
+                continue;
+            };
+
+            loaded_builtin.unload(context);
+            context.shell.builtins.remove(name);
+        }
+
+        Ok(result)
+    }
+}
@@ -31,6 +31,7 @@ pub fn simple_builtin<B: SimpleCommand + Send + Sync>() -> builtins::Registratio
         disabled: false,
         special_builtin: false,
         declaration_builtin: false,
+        loaded_builtin: None,
     }
 }
 
@@ -43,6 +44,7 @@ pub fn builtin<B: builtins::Command + Send + Sync>() -> builtins::Registration {
         disabled: false,
         special_builtin: false,
         declaration_builtin: false,
+        loaded_builtin: None,
     }
 }
 
@@ -53,6 +55,7 @@ fn special_builtin<B: builtins::Command + Send + Sync>() -> builtins::Registrati
         disabled: false,
         special_builtin: true,
         declaration_builtin: false,
+        loaded_builtin: None,
     }
 }
 
@@ -63,6 +66,7 @@ fn decl_builtin<B: builtins::DeclarationCommand + Send + Sync>() -> builtins::Re
         disabled: false,
         special_builtin: false,
         declaration_builtin: true,
+        loaded_builtin: None,
     }
 }
 
@@ -73,6 +77,7 @@ fn special_decl_builtin<B: builtins::DeclarationCommand + Send + Sync>() -> buil
         disabled: false,
         special_builtin: true,
         declaration_builtin: true,
+        loaded_builtin: None,
     }
 }
 
@@ -156,7 +161,10 @@ async fn exec_declaration_builtin_impl<T: builtins::DeclarationCommand + Send +
 
     for (i, arg) in args.into_iter().enumerate() {
         match arg {
-            CommandArg::String(s) if i == 0 || s.starts_with('-') || s.starts_with('+') => {
+            // N.B. A lone '-' is treated as a declaration (e.g., `local -`).
+            CommandArg::String(s)
+                if i == 0 || (s.starts_with('-') && s != "-") || s.starts_with('+') =>
+            {
                 options.push(s);
             }
             _ => declarations.push(arg),
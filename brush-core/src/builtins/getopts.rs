@@ -1,8 +1,8 @@
-use std::{borrow::Cow, collections::HashMap, io::Write};
+use std::{collections::HashMap, io::Write};
 
 use clap::Parser;
 
-use crate::{builtins, commands, variables};
+use crate::{builtins, commands, env, error, expansion, variables};
 
 /// Parse command options.
 #[derive(Parser)]
@@ -18,7 +18,17 @@ pub(crate) struct GetOptsCommand {
     args: Vec<String>,
 }
 
-const VAR_GETOPTS_NEXT_CHAR_INDEX: &str = "__GETOPTS_NEXT_CHAR";
+/// The outcome of looking for the next option in the arguments.
+enum NextOption {
+    /// A valid option was found, along with its argument (if it takes one).
+    Found(char, Option<String>),
+    /// An option not present in the options string was found.
+    Unknown(char),
+    /// An option that requires an argument was found, but no argument followed it.
+    MissingArgument(char),
+    /// No options remain.
+    Done,
+}
 
 impl builtins::Command for GetOptsCommand {
     /// Override the default [`builtins::Command::new`] function to handle clap's limitation related
@@ -35,190 +45,227 @@ impl builtins::Command for GetOptsCommand {
         Ok(this)
     }
 
-    #[allow(clippy::too_many_lines)]
     async fn execute(
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        let mut args = HashMap::<char, bool>::new();
-        let mut treat_unknown_options_as_failure = true;
+        // A leading ':' in the options string requests "silent" error reporting.
+        let (silent, options_string) = match self.options_string.strip_prefix(':') {
+            Some(rest) => (true, rest),
+            None => (false, self.options_string.as_str()),
+        };
 
-        // Build the args map
-        let mut last_char = None;
-        for c in self.options_string.chars() {
+        // Build the options map, recording which options take an argument.
+        let mut options = HashMap::<char, bool>::new();
+        let mut chars = options_string.chars().peekable();
+        while let Some(c) = chars.next() {
             if c == ':' {
-                if let Some(last_char) = last_char {
-                    args.insert(last_char, true);
-                    continue;
-                } else if args.is_empty() {
-                    // This is the first character of the options string.
-                    // Its presence indicates a request for unknown
-                    // options *not* to be treated as failures.
-                    treat_unknown_options_as_failure = false;
-                } else {
-                    return Ok(builtins::ExitCode::InvalidUsage);
-                }
+                continue;
             }
 
-            args.insert(c, false);
-            last_char = Some(c);
+            let takes_arg = chars.next_if_eq(&':').is_some();
+            options.insert(c, takes_arg);
         }
 
-        // If unset, assume OPTIND is 1.
-        let mut next_index: usize = context
-            .shell
-            .env
-            .get_str("OPTIND")
-            .unwrap_or(Cow::Borrowed("1"))
-            .parse()?;
+        // Parse the explicitly provided arguments, or the positional parameters if none were.
+        let args = if self.args.is_empty() {
+            &context.shell.positional_parameters
+        } else {
+            &self.args
+        };
 
-        if next_index < 1 {
-            return Ok(builtins::ExitCode::InvalidUsage);
-        }
+        let (mut next_index, mut next_char_index) = get_option_index(&context.shell.env);
 
-        let mut new_optarg = None;
-        let new_optind;
-        let mut variable_value;
-        let exit_code;
-
-        // See if there are any args left to parse.
-        let mut next_index_zero_based = next_index - 1;
-        if next_index_zero_based < self.args.len() {
-            let arg = self.args[next_index_zero_based].as_str();
-
-            // See if this is an option.
-            if arg.starts_with('-') && arg != "--" {
-                // Figure out how far into this option we are.
-                const DEFAULT_NEXT_CHAR_INDEX: usize = 1;
-                let next_char_index = context
-                    .shell
-                    .env
-                    .get_str(VAR_GETOPTS_NEXT_CHAR_INDEX)
-                    .map_or(DEFAULT_NEXT_CHAR_INDEX, |s| {
-                        s.parse().unwrap_or(DEFAULT_NEXT_CHAR_INDEX)
-                    });
-
-                // Find the char.
-                let c = arg.chars().nth(next_char_index).unwrap();
-                let is_last_char_in_option = next_char_index == arg.len() - 1;
-
-                // Look up the char.
-                let mut is_error = false;
-                if let Some(takes_arg) = args.get(&c) {
-                    variable_value = String::from(c);
-
-                    if *takes_arg {
-                        // If the option takes a value but it's not the last option in this
-                        // argument, then this is an error.
-                        if is_last_char_in_option {
-                            next_index += 1;
-                            next_index_zero_based += 1;
-
-                            if next_index_zero_based >= self.args.len() {
-                                return Ok(builtins::ExitCode::Custom(1));
-                            }
-
-                            new_optarg = Some(self.args[next_index_zero_based].clone());
-                        } else {
-                            is_error = true;
-                        }
-                    } else {
-                        new_optarg = None;
-                    }
-                } else {
-                    // Set to satisfy compiler.
-                    variable_value = String::from("?");
+        let next_option = find_next_option(args, &options, &mut next_index, &mut next_char_index);
 
-                    is_error = true;
-                }
+        // Error messages are only displayed if requested via OPTERR and not suppressed by
+        // silent mode.
+        let display_errors = !silent
+            && context
+                .shell
+                .env
+                .get_str("OPTERR")
+                .map_or(true, |s| s.parse::<i64>().unwrap_or(0) != 0);
+        let shell_name = context.shell.shell_name.clone().unwrap_or_default();
 
-                if is_error {
-                    // Unknown option; set variable to '?' and OPTARG to the unknown option (sans
-                    // hyphen).
-                    variable_value = String::from("?");
-                    if !treat_unknown_options_as_failure {
-                        new_optarg = Some(String::from(c));
-                    } else {
-                        new_optarg = None;
-                    }
-
-                    // TODO: honor OPTERR=0 indicating suppression of error messages
-                    if treat_unknown_options_as_failure {
-                        writeln!(context.stderr(), "getopts: illegal option -- {c}")?;
-                    }
+        let done = matches!(next_option, NextOption::Done);
+        let (variable_value, optarg) = match next_option {
+            NextOption::Found(c, optarg) => (String::from(c), optarg),
+            NextOption::Unknown(c) => {
+                if display_errors {
+                    writeln!(context.stderr(), "{shell_name}: illegal option -- {c}")?;
                 }
 
-                if is_last_char_in_option {
-                    // We're done with this argument, so unset the internal char index variable
-                    // and request an update to OPTIND.
-                    new_optind = next_index + 1;
-                    context.shell.env.unset(VAR_GETOPTS_NEXT_CHAR_INDEX)?;
+                (String::from("?"), silent.then(|| String::from(c)))
+            }
+            NextOption::MissingArgument(c) => {
+                if silent {
+                    (String::from(":"), Some(String::from(c)))
                 } else {
-                    // We have more to go in this argument, so update the internal char index
-                    // and request that OPTIND not be updated.
-                    new_optind = next_index;
-                    context.shell.env.update_or_add(
-                        VAR_GETOPTS_NEXT_CHAR_INDEX,
-                        variables::ShellValueLiteral::Scalar((next_char_index + 1).to_string()),
-                        |_| Ok(()),
-                        crate::env::EnvironmentLookup::Anywhere,
-                        crate::env::EnvironmentScope::Global,
-                    )?;
-                }
-
-                exit_code = builtins::ExitCode::Success;
-            } else {
-                variable_value = String::from("?");
-                new_optarg = None;
+                    if display_errors {
+                        writeln!(
+                            context.stderr(),
+                            "{shell_name}: option requires an argument -- {c}"
+                        )?;
+                    }
 
-                // If it was "--" we encountered, then skip past it.
-                if arg == "--" {
-                    new_optind = next_index + 1;
-                } else {
-                    new_optind = next_index;
+                    (String::from("?"), None)
                 }
-
-                // Note that we're done parsing options.
-                exit_code = builtins::ExitCode::Custom(1);
             }
-        } else {
-            variable_value = String::from("?");
-            new_optarg = None;
-            new_optind = next_index;
-            exit_code = builtins::ExitCode::Custom(1);
-        }
+            NextOption::Done => (String::from("?"), None),
+        };
 
-        // Update variable value.
-        context.shell.env.update_or_add(
-            self.variable_name.as_str(),
-            variables::ShellValueLiteral::Scalar(variable_value),
-            |_| Ok(()),
-            crate::env::EnvironmentLookup::Anywhere,
-            crate::env::EnvironmentScope::Global,
-        )?;
+        set_option_index(&mut context.shell.env, next_index, next_char_index)?;
 
-        // Update OPTARG
-        if let Some(new_optarg) = new_optarg {
+        // Update OPTARG.
+        if let Some(optarg) = optarg {
             context.shell.env.update_or_add(
                 "OPTARG",
-                variables::ShellValueLiteral::Scalar(new_optarg),
+                variables::ShellValueLiteral::Scalar(optarg),
                 |_| Ok(()),
-                crate::env::EnvironmentLookup::Anywhere,
-                crate::env::EnvironmentScope::Global,
+                env::EnvironmentLookup::Anywhere,
+                env::EnvironmentScope::Global,
             )?;
         } else {
             let _ = context.shell.env.unset("OPTARG")?;
         }
 
-        // Update OPTIND
+        // Update the variable.
+        if !expansion::valid_variable_name(self.variable_name.as_str()) {
+            writeln!(
+                context.stderr(),
+                "{}: `{}': not a valid identifier",
+                context.command_name,
+                self.variable_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
         context.shell.env.update_or_add(
-            "OPTIND",
-            variables::ShellValueLiteral::Scalar(new_optind.to_string()),
+            self.variable_name.as_str(),
+            variables::ShellValueLiteral::Scalar(variable_value),
             |_| Ok(()),
-            crate::env::EnvironmentLookup::Anywhere,
-            crate::env::EnvironmentScope::Global,
+            env::EnvironmentLookup::Anywhere,
+            env::EnvironmentScope::Global,
         )?;
 
-        Ok(exit_code)
+        if done {
+            Ok(builtins::ExitCode::Custom(1))
+        } else {
+            Ok(builtins::ExitCode::Success)
+        }
+    }
+}
+
+/// Looks for the next option in the given arguments, starting at the given (1-based)
+/// argument index and character index within that argument; on return, the indices are
+/// updated to refer to where the search for the following option should start.
+fn find_next_option(
+    args: &[String],
+    options: &HashMap<char, bool>,
+    next_index: &mut usize,
+    next_char_index: &mut usize,
+) -> NextOption {
+    loop {
+        let Some(arg) = args.get(*next_index - 1) else {
+            *next_index = args.len() + 1;
+            *next_char_index = 1;
+            return NextOption::Done;
+        };
+
+        // At the start of an argument, check whether it holds options at all.
+        if *next_char_index == 1 {
+            if arg == "--" {
+                *next_index += 1;
+                return NextOption::Done;
+            } else if !arg.starts_with('-') || arg == "-" {
+                return NextOption::Done;
+            }
+        }
+
+        let mut remaining = arg.chars().skip(*next_char_index);
+        let Some(c) = remaining.next() else {
+            // We've run off the end of the argument (e.g., it changed since we last looked);
+            // move on to the next one.
+            *next_index += 1;
+            *next_char_index = 1;
+            continue;
+        };
+
+        let rest_of_arg: String = remaining.collect();
+
+        // Figure out where the next search should start.
+        if rest_of_arg.is_empty() {
+            *next_index += 1;
+            *next_char_index = 1;
+        } else {
+            *next_char_index += 1;
+        }
+
+        return match options.get(&c) {
+            None => NextOption::Unknown(c),
+            Some(false) => NextOption::Found(c, None),
+            Some(true) if !rest_of_arg.is_empty() => {
+                // The rest of the argument is the option's argument.
+                *next_index += 1;
+                *next_char_index = 1;
+                NextOption::Found(c, Some(rest_of_arg))
+            }
+            Some(true) => {
+                if let Some(optarg) = args.get(*next_index - 1) {
+                    *next_index += 1;
+                    NextOption::Found(c, Some(optarg.clone()))
+                } else {
+                    NextOption::MissingArgument(c)
+                }
+            }
+        };
+    }
+}
+
+/// Retrieves the (1-based) index of the next argument to be processed, along with the index
+/// of the next character to be processed within that argument.
+fn get_option_index(env: &env::ShellEnvironment) -> (usize, usize) {
+    let (index, next_char_index) = match env.get("OPTIND").map(|(_, var)| var.value()) {
+        Some(variables::ShellValue::Dynamic(variables::DynamicValue::OptionIndex {
+            index,
+            next_char_index,
+        })) => (index.parse().ok(), *next_char_index),
+        Some(value) => (value.to_cow_string().parse().ok(), 1),
+        None => (None, 1),
+    };
+
+    // Invalid indices restart processing from the first argument.
+    match index {
+        Some(index) if index >= 1 => (index, next_char_index),
+        _ => (1, 1),
     }
 }
+
+/// Updates `OPTIND`, retaining the position within the current argument if processing
+/// stopped partway through it.
+fn set_option_index(
+    env: &mut env::ShellEnvironment,
+    index: usize,
+    next_char_index: usize,
+) -> Result<(), error::Error> {
+    env.update_or_add(
+        "OPTIND",
+        variables::ShellValueLiteral::Scalar(index.to_string()),
+        |_| Ok(()),
+        env::EnvironmentLookup::Anywhere,
+        env::EnvironmentScope::Global,
+    )?;
+
+    if next_char_index > 1 {
+        if let Some((_, var)) = env.get_mut("OPTIND") {
+            *var.value_mut() =
+                variables::ShellValue::Dynamic(variables::DynamicValue::OptionIndex {
+                    index: index.to_string(),
+                    next_char_index,
+                });
+        }
+    }
+
+    Ok(())
+}
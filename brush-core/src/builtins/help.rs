@@ -78,6 +78,8 @@ impl HelpCommand {
     ) -> Result<(), crate::error::Error> {
         let pattern = crate::patterns::Pattern::from(topic_pattern)
             .set_extended_globbing(context.shell.options.extended_globbing)
+            .set_locale(context.shell.locale())
+            .set_ranges_use_c_locale(context.shell.options.glob_ranges_use_c_locale)
             .set_case_insensitive(context.shell.options.case_insensitive_pathname_expansion);
 
         let mut found_count = 0;
@@ -115,7 +117,11 @@ impl HelpCommand {
             builtins::ContentType::DetailedHelp
         };
 
-        let content = (registration.content_func)(name, content_type)?;
+        let content = if let Some(loaded_builtin) = &registration.loaded_builtin {
+            loaded_builtin.get_content(content_type)?
+        } else {
+            (registration.content_func)(name, content_type)?
+        };
 
         write!(context.stdout(), "{content}")?;
         context.stdout().flush()?;
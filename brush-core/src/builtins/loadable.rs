@@ -0,0 +1,635 @@
+//! Support for built-in commands loaded from shared objects at runtime (via `enable -f`).
+//!
+//! Two interfaces are supported for shared objects to provide builtins:
+//!
+//! * bash-compatible loadable builtins, which export a `struct builtin` named `<name>_struct`,
+//!   and optionally `<name>_builtin_load` and `<name>_builtin_unload` functions. Since brush
+//!   doesn't provide bash's internal functions, only loadables that restrict themselves to
+//!   the C runtime can be loaded.
+//! * brush plugins, which export a [`BuiltinDescriptor`] named `brush_builtin_<name>`. Rust
+//!   plugins can define one with the [`export_builtin!`](crate::export_builtin) macro.
+//!
+//! Plugin builtins are passed the shell's standard input, output, and error explicitly. bash
+//! loadables instead use the process's standard file descriptors, which are temporarily
+//! redirected to the shell's corresponding open files while they run. Since that redirection
+//! applies to the whole process, calls into bash loadables are serialized.
+//!
+//! Loaded builtins are run on a thread where blocking is allowed, since they may block
+//! (e.g., reading from their standard input).
+
+use futures::future::BoxFuture;
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::path::Path;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::commands::{self, CommandArg};
+use crate::{builtins, error, openfiles, sys, Shell};
+
+/// The version of the brush plugin ABI described by the types in this module. Plugins
+/// built against a different version are rejected.
+pub const PLUGIN_ABI_VERSION: u32 = 2;
+
+/// Describes a built-in command provided by a brush plugin. A plugin exports one of these
+/// as `brush_builtin_<name>` for each builtin it provides.
+#[repr(C)]
+pub struct BuiltinDescriptor {
+    /// The version of the plugin ABI the plugin was built against; must be
+    /// [`PLUGIN_ABI_VERSION`].
+    pub abi_version: u32,
+    /// Whether the builtin is classified as "special".
+    pub special_builtin: bool,
+    /// A nul-terminated usage synopsis, or null.
+    pub short_doc: *const c_char,
+    /// A nul-terminated (possibly multi-line) description, or null.
+    pub long_doc: *const c_char,
+    /// The function to execute the builtin.
+    pub execute: BuiltinEntryPoint,
+}
+
+// SAFETY: Descriptors only point to immutable, static data.
+unsafe impl Sync for BuiltinDescriptor {}
+
+/// Type of the function implementing a plugin builtin. It's given the command's arguments,
+/// starting with the name it was invoked by, along with its standard input, output, and
+/// error, and returns the command's exit status.
+pub type BuiltinEntryPoint = unsafe extern "C" fn(
+    args: *const BuiltinArg,
+    arg_count: usize,
+    stdio: *const BuiltinStdio,
+) -> i32;
+
+/// An argument passed to a plugin builtin.
+#[repr(C)]
+pub struct BuiltinArg {
+    /// Pointer to the argument's UTF-8 bytes; not nul-terminated.
+    pub data: *const u8,
+    /// The length of the argument, in bytes.
+    pub len: usize,
+}
+
+/// The standard input, output, and error of a plugin builtin, as file descriptors. They
+/// remain owned by the shell, and are only valid until the builtin returns; any that
+/// aren't open are -1.
+#[repr(C)]
+#[derive(Debug)]
+pub struct BuiltinStdio {
+    /// The builtin's standard input.
+    pub stdin: c_int,
+    /// The builtin's standard output.
+    pub stdout: c_int,
+    /// The builtin's standard error.
+    pub stderr: c_int,
+}
+
+impl BuiltinStdio {
+    /// Returns a new handle to the builtin's standard input.
+    pub fn stdin(&self) -> std::io::Result<std::fs::File> {
+        duplicate_fd(self.stdin)
+    }
+
+    /// Returns a new handle to the builtin's standard output.
+    pub fn stdout(&self) -> std::io::Result<std::fs::File> {
+        duplicate_fd(self.stdout)
+    }
+
+    /// Returns a new handle to the builtin's standard error.
+    pub fn stderr(&self) -> std::io::Result<std::fs::File> {
+        duplicate_fd(self.stderr)
+    }
+}
+
+#[cfg(unix)]
+fn duplicate_fd(fd: c_int) -> std::io::Result<std::fs::File> {
+    if fd < 0 {
+        return Err(std::io::ErrorKind::NotFound.into());
+    }
+
+    // SAFETY: The shell keeps the file descriptors it passes to a builtin open until the
+    // builtin returns.
+    let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(fd) };
+    Ok(fd.try_clone_to_owned()?.into())
+}
+
+#[cfg(not(unix))]
+fn duplicate_fd(_fd: c_int) -> std::io::Result<std::fs::File> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+/// Converts arguments passed to a plugin builtin into strings.
+///
+/// # Arguments
+///
+/// * `args` - Pointer to the first argument.
+/// * `arg_count` - The number of arguments.
+///
+/// # Safety
+///
+/// The pointers must be as passed by the shell to a [`BuiltinEntryPoint`].
+pub unsafe fn args_from_raw(args: *const BuiltinArg, arg_count: usize) -> Vec<String> {
+    if args.is_null() {
+        return vec![];
+    }
+
+    // SAFETY: The caller guarantees the pointers are valid.
+    unsafe { std::slice::from_raw_parts(args, arg_count) }
+        .iter()
+        .map(|arg| {
+            // SAFETY: See above.
+            let bytes = unsafe { std::slice::from_raw_parts(arg.data, arg.len) };
+            String::from_utf8_lossy(bytes).to_string()
+        })
+        .collect()
+}
+
+/// Defines and exports a [`BuiltinDescriptor`] for a builtin implemented by the given
+/// function, which takes the command's arguments (starting with the name it was invoked
+/// by) and its standard input, output, and error, and returns its exit status.
+///
+/// # Examples
+///
+/// ```
+/// use brush_core::builtins::loadable::BuiltinStdio;
+/// use std::io::Write;
+///
+/// fn hello(args: &[&str], stdio: &BuiltinStdio) -> i32 {
+///     let greeting = format!("hello, {}", args[1..].join(" "));
+///     match stdio.stdout().and_then(|mut stdout| writeln!(stdout, "{greeting}")) {
+///         Ok(()) => 0,
+///         Err(_) => 1,
+///     }
+/// }
+///
+/// brush_core::export_builtin!(hello, hello, "hello [name ...]", "Greets the named people.");
+/// ```
+#[macro_export]
+macro_rules! export_builtin {
+    ($name:ident, $func:path) => {
+        $crate::export_builtin!($name, $func, "", "");
+    };
+    ($name:ident, $func:path, $short_doc:literal, $long_doc:literal) => {
+        const _: () = {
+            unsafe extern "C" fn execute(
+                args: *const $crate::builtins::loadable::BuiltinArg,
+                arg_count: usize,
+                stdio: *const $crate::builtins::loadable::BuiltinStdio,
+            ) -> i32 {
+                let args = $crate::builtins::loadable::args_from_raw(args, arg_count);
+                let args: Vec<&str> = args.iter().map(String::as_str).collect();
+                let stdio = &*stdio;
+
+                // Panics mustn't unwind into the shell.
+                std::panic::catch_unwind(|| $func(args.as_slice(), stdio)).unwrap_or(1)
+            }
+
+            #[export_name = concat!("brush_builtin_", stringify!($name))]
+            static DESCRIPTOR: $crate::builtins::loadable::BuiltinDescriptor =
+                $crate::builtins::loadable::BuiltinDescriptor {
+                    abi_version: $crate::builtins::loadable::PLUGIN_ABI_VERSION,
+                    special_builtin: false,
+                    short_doc: concat!($short_doc, "\0").as_ptr().cast(),
+                    long_doc: concat!($long_doc, "\0").as_ptr().cast(),
+                    execute,
+                };
+        };
+    };
+}
+
+//
+// Definitions from bash's builtins.h and command.h.
+//
+
+const BASH_SPECIAL_BUILTIN: c_int = 0x08;
+
+/// Serializes calls into bash loadables, which redirect the process-wide standard file
+/// descriptors while they run.
+static BASH_LOADABLE_LOCK: Mutex<()> = Mutex::new(());
+
+#[repr(C)]
+struct BashWordDesc {
+    word: *mut c_char,
+    flags: c_int,
+}
+
+#[repr(C)]
+struct BashWordList {
+    next: *mut BashWordList,
+    word: *mut BashWordDesc,
+}
+
+#[repr(C)]
+struct BashBuiltin {
+    name: *mut c_char,
+    function: Option<unsafe extern "C" fn(*mut BashWordList) -> c_int>,
+    flags: c_int,
+    long_doc: *const *const c_char,
+    short_doc: *const c_char,
+    handle: *mut c_char,
+}
+
+type BashLoadFunc = unsafe extern "C" fn(*const c_char) -> c_int;
+type BashUnloadFunc = unsafe extern "C" fn(*const c_char);
+
+enum EntryPoint {
+    Bash(*const BashBuiltin),
+    Brush(*const BuiltinDescriptor),
+}
+
+/// A built-in command loaded from a shared object.
+pub struct LoadedBuiltin {
+    name: String,
+    entry_point: EntryPoint,
+    unload_func: Option<BashUnloadFunc>,
+    shared_object: Arc<sys::dynlib::SharedObject>,
+}
+
+// SAFETY: The entry point refers to static data in the shared object, which is kept
+// loaded for as long as this object exists.
+unsafe impl Send for LoadedBuiltin {}
+unsafe impl Sync for LoadedBuiltin {}
+
+impl LoadedBuiltin {
+    fn is_special(&self) -> bool {
+        match self.entry_point {
+            // SAFETY: The entry point remains valid while the shared object is loaded.
+            EntryPoint::Bash(builtin) => unsafe { (*builtin).flags & BASH_SPECIAL_BUILTIN != 0 },
+            // SAFETY: See above.
+            EntryPoint::Brush(descriptor) => unsafe { (*descriptor).special_builtin },
+        }
+    }
+
+    fn short_doc(&self) -> Option<String> {
+        // SAFETY: The entry point remains valid while the shared object is loaded.
+        let short_doc = unsafe {
+            match self.entry_point {
+                EntryPoint::Bash(builtin) => (*builtin).short_doc,
+                EntryPoint::Brush(descriptor) => (*descriptor).short_doc,
+            }
+        };
+
+        // SAFETY: Documentation strings are null or nul-terminated.
+        unsafe { string_from_ptr(short_doc) }
+    }
+
+    fn long_doc_lines(&self) -> Vec<String> {
+        match self.entry_point {
+            EntryPoint::Bash(builtin) => {
+                let mut lines = vec![];
+
+                // SAFETY: The entry point remains valid while the shared object is loaded,
+                // and its documentation is a null-terminated array of strings.
+                let mut line = unsafe { (*builtin).long_doc };
+                while !line.is_null() {
+                    match unsafe { string_from_ptr(*line) } {
+                        Some(s) => lines.push(s),
+                        None => break,
+                    }
+                    line = unsafe { line.add(1) };
+                }
+
+                lines
+            }
+            EntryPoint::Brush(descriptor) => {
+                // SAFETY: The entry point remains valid while the shared object is loaded.
+                unsafe { string_from_ptr((*descriptor).long_doc) }
+                    .map(|doc| doc.lines().map(|line| line.to_owned()).collect())
+                    .unwrap_or_default()
+            }
+        }
+    }
+
+    /// Returns the builtin's help content.
+    ///
+    /// # Arguments
+    ///
+    /// * `content_type` - The type of content to retrieve.
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn get_content(&self, content_type: builtins::ContentType) -> Result<String, error::Error> {
+        let name = &self.name;
+        let short_doc = self.short_doc().unwrap_or_default();
+
+        let content = match content_type {
+            builtins::ContentType::DetailedHelp => {
+                let mut content = std::format!("{name}: {short_doc}\n");
+                for line in self.long_doc_lines() {
+                    content.push_str("    ");
+                    content.push_str(line.as_str());
+                    content.push('\n');
+                }
+                content
+            }
+            builtins::ContentType::ShortUsage => std::format!("{name}: {short_doc}\n"),
+            builtins::ContentType::ShortDescription => {
+                let description = self.long_doc_lines().into_iter().next().unwrap_or_default();
+                std::format!("{name} - {description}\n")
+            }
+            builtins::ContentType::ManPage => {
+                return error::unimp("man page rendering is not yet implemented")
+            }
+        };
+
+        Ok(content)
+    }
+
+    /// Runs the builtin's unload hook, if it has one; this is expected to be called when
+    /// the builtin is removed from the shell via `enable -d`.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context of the command removing the builtin.
+    pub(crate) fn unload(&self, context: &commands::ExecutionContext<'_>) {
+        if let Some(unload_func) = self.unload_func {
+            if let Ok(c_name) = CString::new(self.name.as_str()) {
+                // SAFETY: The function was exported by the still-loaded shared object
+                // with this signature.
+                with_context_stdio(context, || unsafe { unload_func(c_name.as_ptr()) });
+            }
+        }
+    }
+
+    fn execute(&self, args: &[String], stdio_fds: [Option<c_int>; 3]) -> Result<i32, error::Error> {
+        // Make sure the shared object stays loaded until we're done.
+        let _shared_object = Arc::clone(&self.shared_object);
+
+        match self.entry_point {
+            EntryPoint::Bash(builtin) => {
+                // SAFETY: The entry point remains valid while the shared object is loaded.
+                let Some(function) = (unsafe { (*builtin).function }) else {
+                    return Ok(0);
+                };
+
+                // bash builtins are passed a linked list of their arguments, not including
+                // the command name.
+                let c_args = args
+                    .iter()
+                    .skip(1)
+                    .map(|arg| CString::new(arg.as_str()))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error::Error::InvalidArguments)?;
+                let mut words: Vec<_> = c_args
+                    .iter()
+                    .map(|arg| BashWordDesc {
+                        word: arg.as_ptr().cast_mut(),
+                        flags: 0,
+                    })
+                    .collect();
+                let mut list: Vec<_> = words
+                    .iter_mut()
+                    .map(|word| BashWordList {
+                        next: std::ptr::null_mut(),
+                        word,
+                    })
+                    .collect();
+                for i in (1..list.len()).rev() {
+                    let next: *mut BashWordList = &mut list[i];
+                    list[i - 1].next = next;
+                }
+                let head = list
+                    .first_mut()
+                    .map_or(std::ptr::null_mut(), |head| head as *mut BashWordList);
+
+                // SAFETY: The function was exported by the still-loaded shared object with
+                // this signature, and the list it's passed outlives the call.
+                Ok(with_process_stdio(stdio_fds, || unsafe { function(head) }))
+            }
+            EntryPoint::Brush(descriptor) => {
+                let raw_args: Vec<_> = args
+                    .iter()
+                    .map(|arg| BuiltinArg {
+                        data: arg.as_ptr(),
+                        len: arg.len(),
+                    })
+                    .collect();
+
+                let [stdin, stdout, stderr] = stdio_fds.map(|fd| fd.unwrap_or(-1));
+                let stdio = BuiltinStdio {
+                    stdin,
+                    stdout,
+                    stderr,
+                };
+
+                // SAFETY: The function was exported by the still-loaded shared object with
+                // this signature, and the arguments it's passed outlive the call.
+                Ok(unsafe { ((*descriptor).execute)(raw_args.as_ptr(), raw_args.len(), &stdio) })
+            }
+        }
+    }
+}
+
+/// Opens the named shared object. Names without a slash are looked up in the directories
+/// listed in `BASH_LOADABLES_PATH`, falling back to the dynamic loader's default search
+/// and then the current working directory.
+///
+/// # Arguments
+///
+/// * `shell` - The shell in which the object is being loaded.
+/// * `filename` - The name or path of the shared object.
+pub(crate) fn open_shared_object(
+    shell: &Shell,
+    filename: &str,
+) -> Result<Arc<sys::dynlib::SharedObject>, error::Error> {
+    if filename.contains('/') {
+        let path = shell.get_absolute_path(Path::new(filename));
+        return sys::dynlib::SharedObject::open(path.to_string_lossy().as_ref()).map(Arc::new);
+    }
+
+    if let Some(loadables_path) = shell.env.get_str("BASH_LOADABLES_PATH") {
+        for dir in loadables_path.split(':').filter(|dir| !dir.is_empty()) {
+            let candidate = shell.get_absolute_path(&Path::new(dir).join(filename));
+            if candidate.is_file() {
+                return sys::dynlib::SharedObject::open(candidate.to_string_lossy().as_ref())
+                    .map(Arc::new);
+            }
+        }
+    }
+
+    match sys::dynlib::SharedObject::open(filename) {
+        Ok(shared_object) => Ok(Arc::new(shared_object)),
+        Err(e) => {
+            let candidate = shell.get_absolute_path(Path::new(filename));
+            if candidate.is_file() {
+                sys::dynlib::SharedObject::open(candidate.to_string_lossy().as_ref()).map(Arc::new)
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Loads the named builtin from the given shared object, returning a registration for it.
+///
+/// # Arguments
+///
+/// * `context` - The context of the command loading the builtin.
+/// * `shared_object` - The shared object to load the builtin from.
+/// * `filename` - The name the shared object was specified by.
+/// * `name` - The name of the builtin.
+pub(crate) fn load_builtin(
+    context: &commands::ExecutionContext<'_>,
+    shared_object: &Arc<sys::dynlib::SharedObject>,
+    filename: &str,
+    name: &str,
+) -> Result<builtins::Registration, error::Error> {
+    let (entry_point, unload_func) = if let Ok(descriptor) =
+        shared_object.symbol(std::format!("brush_builtin_{name}").as_str())
+    {
+        let descriptor = descriptor.cast::<BuiltinDescriptor>().cast_const();
+
+        // SAFETY: The symbol is expected to be a plugin builtin descriptor; its version
+        // field is checked before anything else is used.
+        let abi_version = unsafe { (*descriptor).abi_version };
+        if abi_version != PLUGIN_ABI_VERSION {
+            return Err(error::Error::DynamicLoadError(std::format!(
+                "{name}: unsupported plugin ABI version {abi_version} in shared object {filename}"
+            )));
+        }
+
+        (EntryPoint::Brush(descriptor), None)
+    } else {
+        let struct_name = std::format!("{name}_struct");
+        let builtin = shared_object.symbol(struct_name.as_str()).map_err(|e| {
+            error::Error::DynamicLoadError(std::format!(
+                "cannot find {struct_name} in shared object {filename}: {e}"
+            ))
+        })?;
+
+        // Give the builtin a chance to initialize itself.
+        if let Ok(load_func) = shared_object.symbol(std::format!("{name}_builtin_load").as_str()) {
+            let c_name = CString::new(name).map_err(|_| error::Error::InvalidArguments)?;
+
+            // SAFETY: The symbol is expected to be a load function with this signature.
+            let load_func: BashLoadFunc = unsafe { std::mem::transmute(load_func) };
+            if with_context_stdio(context, || unsafe { load_func(c_name.as_ptr()) }) == 0 {
+                return Err(error::Error::DynamicLoadError(std::format!(
+                    "load function for {name} returns failure (0): not loaded"
+                )));
+            }
+        }
+
+        let unload_func = shared_object
+            .symbol(std::format!("{name}_builtin_unload").as_str())
+            .ok()
+            // SAFETY: The symbol is expected to be an unload function with this signature.
+            .map(|f| unsafe { std::mem::transmute::<_, BashUnloadFunc>(f) });
+
+        (
+            EntryPoint::Bash(builtin.cast::<BashBuiltin>().cast_const()),
+            unload_func,
+        )
+    };
+
+    let loaded = LoadedBuiltin {
+        name: name.to_owned(),
+        entry_point,
+        unload_func,
+        shared_object: Arc::clone(shared_object),
+    };
+
+    Ok(builtins::Registration {
+        execute_func: exec_loaded_builtin,
+        content_func: get_loaded_builtin_content,
+        disabled: false,
+        special_builtin: loaded.is_special(),
+        declaration_builtin: false,
+        loaded_builtin: Some(Arc::new(loaded)),
+    })
+}
+
+fn exec_loaded_builtin(
+    context: commands::ExecutionContext<'_>,
+    args: Vec<CommandArg>,
+) -> BoxFuture<'_, Result<builtins::BuiltinResult, error::Error>> {
+    Box::pin(async move { exec_loaded_builtin_impl(&context, args).await })
+}
+
+async fn exec_loaded_builtin_impl(
+    context: &commands::ExecutionContext<'_>,
+    args: Vec<CommandArg>,
+) -> Result<builtins::BuiltinResult, error::Error> {
+    let Some(loaded) = context
+        .shell
+        .builtins
+        .get(context.command_name.as_str())
+        .and_then(|registration| registration.loaded_builtin.clone())
+    else {
+        return error::unimp("executing an unregistered loaded builtin");
+    };
+
+    let args: Vec<_> = args
+        .into_iter()
+        .map(|arg| match arg {
+            CommandArg::String(s) => s,
+            CommandArg::Assignment(a) => a.to_string(),
+        })
+        .collect();
+
+    // N.B. The open files need to stay open until the builtin is done with them.
+    let stdio_files = [0, 1, 2].map(|fd| context.fd(fd));
+    let stdio_fds = raw_fds(&stdio_files);
+
+    let status = tokio::task::spawn_blocking(move || {
+        let _stdio_files = stdio_files;
+        loaded.execute(args.as_slice(), stdio_fds)
+    })
+    .await??;
+
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    Ok(builtins::BuiltinResult {
+        exit_code: builtins::ExitCode::Custom((status & 0xff) as u8),
+    })
+}
+
+#[allow(clippy::unnecessary_wraps)]
+fn get_loaded_builtin_content(
+    name: &str,
+    _content_type: builtins::ContentType,
+) -> Result<String, error::Error> {
+    // N.B. Content for loaded builtins is retrieved from the registration's loaded builtin.
+    Ok(std::format!("{name}: dynamically loaded builtin\n"))
+}
+
+/// Runs the given function with the process's standard input, output, and error redirected
+/// to those of the given execution context.
+fn with_context_stdio<R>(context: &commands::ExecutionContext<'_>, f: impl FnOnce() -> R) -> R {
+    // N.B. The open files need to stay open until the function is done with them.
+    let stdio_files = [0, 1, 2].map(|fd| context.fd(fd));
+    with_process_stdio(raw_fds(&stdio_files), f)
+}
+
+/// Runs the given function with the process's standard input, output, and error redirected
+/// to the given file descriptors (where provided), serialized with any other such calls.
+fn with_process_stdio<R>(stdio_fds: [Option<c_int>; 3], f: impl FnOnce() -> R) -> R {
+    // N.B. A poisoned lock only means a previous call panicked; the redirection it made
+    // was still undone.
+    let _guard = BASH_LOADABLE_LOCK
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    sys::dynlib::with_stdio_redirected(stdio_fds, f)
+}
+
+/// Returns the raw file descriptors of the given open files, where available.
+#[cfg(unix)]
+fn raw_fds(files: &[Option<openfiles::OpenFile>; 3]) -> [Option<c_int>; 3] {
+    [0, 1, 2].map(|i| files[i].as_ref().and_then(|file| file.as_raw_fd().ok()))
+}
+
+/// Returns the raw file descriptors of the given open files, where available.
+#[cfg(not(unix))]
+fn raw_fds(_files: &[Option<openfiles::OpenFile>; 3]) -> [Option<c_int>; 3] {
+    [None; 3]
+}
+
+/// Converts a possibly-null C string into an owned string.
+///
+/// # Safety
+///
+/// The pointer must be null or point to a nul-terminated string.
+unsafe fn string_from_ptr(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        None
+    } else {
+        // SAFETY: See above.
+        Some(unsafe { CStr::from_ptr(s) }.to_string_lossy().to_string())
+    }
+}
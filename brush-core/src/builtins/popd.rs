@@ -1,6 +1,7 @@
 use clap::Parser;
 use std::io::Write;
 
+use crate::builtins::dirs::{self, StackIndex};
 use crate::{builtins, commands};
 
 /// Pop a path from the current directory stack.
@@ -9,8 +10,11 @@ pub(crate) struct PopdCommand {
     /// Pop the path without changing the current working directory.
     #[clap(short = 'n')]
     no_directory_change: bool,
-    //
-    // TODO: implement +N and -N
+
+    /// Remove the Nth entry instead, counting from the left (+N) or right (-N) of the list
+    /// shown by dirs, starting with zero.
+    #[arg(allow_negative_numbers = true, value_name = "+N | -N")]
+    index: Option<String>,
 }
 
 impl builtins::Command for PopdCommand {
@@ -18,19 +22,62 @@ impl builtins::Command for PopdCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        if let Some(popped) = context.shell.directory_stack.pop() {
+        context.shell.apply_dirstack_var_updates();
+
+        let (arg, index) = match &self.index {
+            Some(arg) => {
+                let Some(index) = StackIndex::parse(arg) else {
+                    writeln!(
+                        context.stderr(),
+                        "{}: {arg}: invalid argument",
+                        context.command_name
+                    )?;
+                    return Ok(builtins::ExitCode::InvalidUsage);
+                };
+                (arg.as_str(), index)
+            }
+            None => ("", StackIndex::FromLeft(0)),
+        };
+
+        let stack_len = context.shell.directory_stack.len();
+        let position = match index.resolve(stack_len) {
+            Some(position) if stack_len > 0 => position,
+            _ => {
+                dirs::write_index_error(&context, arg)?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        };
+
+        if position == 0 {
+            // Removing the current working directory means changing to the directory at
+            // the top of the stack, and then removing it from the stack. N.B. For
+            // compatibility with bash, when the directory isn't being changed, the top of
+            // the stack is removed instead.
             if !self.no_directory_change {
-                context.shell.set_working_dir(&popped)?;
+                let top = context.shell.directory_stack[stack_len - 1].clone();
+                let physical = context
+                    .shell
+                    .options
+                    .do_not_resolve_symlinks_when_changing_dir;
+                if let Err(e) = context
+                    .shell
+                    .set_working_dir_and_run_hooks(&top, physical)
+                    .await
+                {
+                    writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+                    return Ok(builtins::ExitCode::Custom(1));
+                }
             }
 
-            // Display dirs.
-            let dirs_cmd = crate::builtins::dirs::DirsCommand::default();
-            dirs_cmd.execute(context).await?;
+            context.shell.directory_stack.pop();
         } else {
-            writeln!(context.stderr(), "popd: directory stack empty")?;
-            return Ok(builtins::ExitCode::Custom(1));
+            context.shell.directory_stack.remove(stack_len - position);
         }
 
-        Ok(builtins::ExitCode::Success)
+        context.shell.update_dirstack_var();
+
+        // Display dirs.
+        let dirs_cmd = dirs::DirsCommand::default();
+        dirs_cmd.execute(context).await
     }
 }
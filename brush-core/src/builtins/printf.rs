@@ -1,7 +1,7 @@
 use clap::Parser;
 use std::io::Write;
 
-use crate::{builtins, commands, escape, expansion};
+use crate::{builtins, commands, expansion, printf};
 
 /// Format a string.
 #[derive(Parser)]
@@ -12,7 +12,7 @@ pub(crate) struct PrintfCommand {
     output_variable: Option<String>,
 
     /// Format string + arguments to the format string.
-    #[arg(trailing_var_arg = true, allow_hyphen_values = true, num_args = 1..)]
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
     format_and_args: Vec<String>,
 }
 
@@ -21,71 +21,43 @@ impl builtins::Command for PrintfCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        let result = self.evaluate(&context)?;
-
-        if let Some(variable_name) = &self.output_variable {
-            expansion::assign_to_named_parameter(context.shell, variable_name, result).await?;
-        } else {
-            write!(context.stdout(), "{result}")?;
-            context.stdout().flush()?;
+        let Some((format, args)) = self.format_and_args.split_first() else {
+            writeln!(
+                context.stderr(),
+                "{}: usage: printf [-v var] format [arguments]",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::InvalidUsage);
+        };
+
+        let time_zone = context.shell.time_zone();
+        let result = printf::format(format, args, context.shell.start_time, time_zone.as_deref());
+
+        let mut exit_code = builtins::ExitCode::Success;
+        for diagnostic in &result.diagnostics {
+            writeln!(context.stderr(), "{}: {diagnostic}", context.command_name)?;
+            if diagnostic.is_error() {
+                exit_code = builtins::ExitCode::Custom(1);
+            }
         }
 
-        Ok(builtins::ExitCode::Success)
-    }
-}
-
-impl PrintfCommand {
-    fn evaluate(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        match self.format_and_args.as_slice() {
-            // Special-case common format string: "%s".
-            [fmt, arg] if fmt == "%s" => Ok(arg.clone()),
-            // Special-case invocation of printf with %q-based format string from bash-completion.
-            // It has hard-coded expectation of backslash-style escaping instead of quoting.
-            [fmt, arg] if fmt == "%q" => Ok(Self::evaluate_format_with_percent_q(None, arg)),
-            [fmt, arg] if fmt == "~%q" => Ok(Self::evaluate_format_with_percent_q(Some("~"), arg)),
-            // Fallback to external command.
-            _ => self.evaluate_via_external_command(context),
-        }
-    }
-
-    fn evaluate_format_with_percent_q(prefix: Option<&str>, arg: &str) -> String {
-        let mut result =
-            escape::quote_if_needed(arg, escape::QuoteMode::BackslashEscape).to_string();
-
-        if let Some(prefix) = prefix {
-            result.insert_str(0, prefix);
+        for (variable_name, byte_count) in result.byte_counts {
+            expansion::assign_to_named_parameter(
+                context.shell,
+                &variable_name,
+                byte_count.to_string(),
+            )
+            .await?;
         }
 
-        result
-    }
-
-    #[allow(clippy::unwrap_in_result)]
-    fn evaluate_via_external_command(
-        &self,
-        context: &commands::ExecutionContext<'_>,
-    ) -> Result<String, crate::error::Error> {
-        // TODO: Don't call external printf command.
-        let mut cmd = std::process::Command::new("printf");
-        cmd.env_clear();
-        cmd.args(&self.format_and_args);
-
-        let output = cmd.output()?;
-
-        let stdout = String::from_utf8(output.stdout)?;
-        let stderr = String::from_utf8(output.stderr)?;
-
-        write!(context.stderr(), "{stderr}")?;
-        context.stderr().flush()?;
-
-        if output.status.success() {
-            Ok(stdout)
+        if let Some(variable_name) = &self.output_variable {
+            let output = String::from_utf8_lossy(result.output.as_slice()).to_string();
+            expansion::assign_to_named_parameter(context.shell, variable_name, output).await?;
         } else {
-            Err(crate::error::Error::PrintfFailure(
-                output.status.code().unwrap(),
-            ))
+            context.stdout().write_all(result.output.as_slice())?;
+            context.stdout().flush()?;
         }
+
+        Ok(exit_code)
     }
 }
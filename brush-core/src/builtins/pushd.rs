@@ -1,5 +1,9 @@
 use clap::Parser;
+use std::io::Write;
+use std::path::PathBuf;
 
+use crate::builtins::dirs::{self, StackIndex};
+use crate::builtins::Command;
 use crate::{builtins, commands};
 
 /// Push a path onto the current directory stack.
@@ -9,10 +13,10 @@ pub(crate) struct PushdCommand {
     #[clap(short = 'n')]
     no_directory_change: bool,
 
-    /// Directory to push on the directory stack.
-    dir: String,
-    //
-    // TODO: implement +N and -N
+    /// Directory to push on the directory stack, or the entry to rotate to the top of the
+    /// stack, counting from the left (+N) or right (-N) of the list shown by dirs.
+    #[arg(allow_negative_numbers = true, value_name = "DIR | +N | -N")]
+    args: Vec<String>,
 }
 
 impl builtins::Command for PushdCommand {
@@ -20,24 +24,150 @@ impl builtins::Command for PushdCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
+        context.shell.apply_dirstack_var_updates();
+
+        let dir = match self.args.as_slice() {
+            [] => {
+                // With no arguments at all, exchange the top two directories.
+                if self.no_directory_change {
+                    return Ok(builtins::ExitCode::Success);
+                }
+                return self.exchange_top_dirs(context).await;
+            }
+            [dir] => dir,
+            _ => {
+                writeln!(
+                    context.stderr(),
+                    "{}: too many arguments",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
+        };
+
+        if matches!(dir.chars().next(), Some('+' | '-')) {
+            let Some(index) = StackIndex::parse(dir) else {
+                writeln!(
+                    context.stderr(),
+                    "{}: {dir}: invalid number",
+                    context.command_name
+                )?;
+                return Ok(builtins::ExitCode::InvalidUsage);
+            };
+
+            return self.rotate(context, dir, index).await;
+        }
+
         if self.no_directory_change {
-            context
-                .shell
-                .directory_stack
-                .push(std::path::PathBuf::from(&self.dir));
+            context.shell.directory_stack.push(PathBuf::from(dir));
         } else {
             let prev_working_dir = context.shell.working_dir.clone();
 
-            let dir = std::path::Path::new(&self.dir);
-            context.shell.set_working_dir(dir)?;
+            let physical = context
+                .shell
+                .options
+                .do_not_resolve_symlinks_when_changing_dir;
+            if let Err(e) = context
+                .shell
+                .set_working_dir_and_run_hooks(std::path::Path::new(dir), physical)
+                .await
+            {
+                writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+                return Ok(builtins::ExitCode::Custom(1));
+            }
 
             context.shell.directory_stack.push(prev_working_dir);
         }
 
+        context.shell.update_dirstack_var();
+
         // Display dirs.
-        let dirs_cmd = crate::builtins::dirs::DirsCommand::default();
-        dirs_cmd.execute(context).await?;
+        let dirs_cmd = dirs::DirsCommand::default();
+        dirs_cmd.execute(context).await
+    }
+}
+
+impl PushdCommand {
+    async fn exchange_top_dirs(
+        &self,
+        context: commands::ExecutionContext<'_>,
+    ) -> Result<builtins::ExitCode, crate::error::Error> {
+        let Some(top) = context.shell.directory_stack.last().cloned() else {
+            writeln!(
+                context.stderr(),
+                "{}: no other directory",
+                context.command_name
+            )?;
+            return Ok(builtins::ExitCode::Custom(1));
+        };
+
+        let prev_working_dir = context.shell.working_dir.clone();
+        let physical = context
+            .shell
+            .options
+            .do_not_resolve_symlinks_when_changing_dir;
+        if let Err(e) = context
+            .shell
+            .set_working_dir_and_run_hooks(&top, physical)
+            .await
+        {
+            writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
+
+        if let Some(top) = context.shell.directory_stack.last_mut() {
+            *top = prev_working_dir;
+        }
+        context.shell.update_dirstack_var();
+
+        let dirs_cmd = dirs::DirsCommand::default();
+        dirs_cmd.execute(context).await
+    }
+
+    async fn rotate(
+        &self,
+        context: commands::ExecutionContext<'_>,
+        arg: &str,
+        index: StackIndex,
+    ) -> Result<builtins::ExitCode, crate::error::Error> {
+        let Some(position) = index.resolve(context.shell.directory_stack.len()) else {
+            dirs::write_index_error(&context, arg)?;
+            return Ok(builtins::ExitCode::Custom(1));
+        };
+
+        // Rotate the full list of directories (as shown by dirs) so that the selected one
+        // is at its front. The front entry becomes the new working directory; the rest make
+        // up the new stack.
+        let mut dirs: Vec<_> = std::iter::once(context.shell.working_dir.clone())
+            .chain(context.shell.directory_stack.iter().rev().cloned())
+            .collect();
+        dirs.rotate_left(position);
+
+        let new_working_dir = dirs.remove(0);
+        context.shell.directory_stack = dirs.into_iter().rev().collect();
+        context.shell.update_dirstack_var();
+
+        // N.B. For compatibility with bash, when the directory isn't being changed, the
+        // working directory is left as-is and the directory rotated to the front of the
+        // list is dropped.
+        if self.no_directory_change {
+            return Ok(builtins::ExitCode::Success);
+        }
+
+        let physical = context
+            .shell
+            .options
+            .do_not_resolve_symlinks_when_changing_dir;
+        if let Err(e) = context
+            .shell
+            .set_working_dir_and_run_hooks(&new_working_dir, physical)
+            .await
+        {
+            writeln!(context.stderr(), "{}: {e}", context.command_name)?;
+            return Ok(builtins::ExitCode::Custom(1));
+        }
 
-        Ok(builtins::ExitCode::Success)
+        let dirs_cmd = dirs::DirsCommand::default();
+        dirs_cmd.execute(context).await
     }
 }
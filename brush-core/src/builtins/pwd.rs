@@ -6,11 +6,11 @@ use std::io::Write;
 #[derive(Parser)]
 pub(crate) struct PwdCommand {
     /// Print the physical directory without any symlinks.
-    #[arg(short = 'P')]
+    #[arg(short = 'P', overrides_with = "allow_symlinks")]
     physical: bool,
 
     /// Print $PWD if it names the current working directory.
-    #[arg(short = 'L')]
+    #[arg(short = 'L', overrides_with = "physical")]
     allow_symlinks: bool,
 }
 
@@ -19,19 +19,23 @@ impl builtins::Command for PwdCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        //
-        // TODO: implement flags
-        // TODO: look for 'physical' option in execution context
-        //
+        // The last of -L and -P wins; otherwise, we defer to the `physical` option.
+        let physical = if self.physical || self.allow_symlinks {
+            self.physical
+        } else {
+            context
+                .shell
+                .options
+                .do_not_resolve_symlinks_when_changing_dir
+        };
 
-        if self.physical || self.allow_symlinks {
-            writeln!(context.stderr(), "UNIMPLEMENTED: pwd with -P or -L")?;
-            return Ok(builtins::ExitCode::Unimplemented);
-        }
+        let cwd = if physical {
+            context.shell.working_dir.canonicalize()?
+        } else {
+            context.shell.working_dir.clone()
+        };
 
-        let cwd = context.shell.working_dir.to_string_lossy().into_owned();
-
-        writeln!(context.stdout(), "{cwd}")?;
+        writeln!(context.stdout(), "{}", cwd.to_string_lossy())?;
 
         Ok(builtins::ExitCode::Success)
     }
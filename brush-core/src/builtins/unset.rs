@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use crate::{builtins, commands};
+use crate::{builtins, commands, env};
 
 /// Unset a variable.
 #[derive(Parser)]
@@ -39,16 +39,31 @@ impl builtins::Command for UnsetCommand {
         &self,
         context: commands::ExecutionContext<'_>,
     ) -> Result<crate::builtins::ExitCode, crate::error::Error> {
-        //
-        // TODO: implement nameref
-        //
-        if self.name_interpretation.name_references {
-            return crate::error::unimp("unset: name references are not yet implemented");
-        }
-
         let unspecified = self.name_interpretation.unspecified();
 
+        let prior_local_behavior = if context.shell.options.localvar_unset {
+            env::PriorLocalUnsetBehavior::LeavePlaceholder
+        } else {
+            env::PriorLocalUnsetBehavior::Remove
+        };
+
         for name in &self.names {
+            if self.name_interpretation.name_references {
+                // Only name references themselves are unset; other variables are left alone.
+                if context
+                    .shell
+                    .env
+                    .get_unresolved(name)
+                    .is_some_and(|(_, var)| var.is_treated_as_nameref())
+                {
+                    context
+                        .shell
+                        .env
+                        .unset_unresolved_using_policy(name.as_str(), prior_local_behavior)?;
+                }
+                continue;
+            }
+
             if unspecified || self.name_interpretation.shell_variables {
                 let parameter =
                     brush_parser::word::parse_parameter(name, &context.shell.parser_options())?;
@@ -56,9 +71,11 @@ impl builtins::Command for UnsetCommand {
                 let result = match parameter {
                     brush_parser::word::Parameter::Positional(_) => continue,
                     brush_parser::word::Parameter::Special(_) => continue,
-                    brush_parser::word::Parameter::Named(name) => {
-                        context.shell.env.unset(name.as_str())?.is_some()
-                    }
+                    brush_parser::word::Parameter::Named(name) => context
+                        .shell
+                        .env
+                        .unset_using_policy(name.as_str(), prior_local_behavior)?
+                        .is_some(),
                     brush_parser::word::Parameter::NamedWithIndex { name, index } => {
                         // First evaluate the index expression.
                         let index_as_expr = brush_parser::arithmetic::parse(index.as_str())?;
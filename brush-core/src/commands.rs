@@ -321,6 +321,10 @@ pub(crate) async fn execute(
             }
         }
 
+        if is_autocd_target(cmd_context.shell, cmd_context.command_name.as_str()) {
+            return execute_autocd(cmd_context).await;
+        }
+
         if let Some(path) = cmd_context
             .shell
             .find_first_executable_in_path_using_cache(&cmd_context.command_name)
@@ -336,6 +340,8 @@ pub(crate) async fn execute(
             tracing::error!("{}: command not found", cmd_context.command_name);
             Ok(CommandSpawnResult::ImmediateExit(127))
         }
+    } else if is_autocd_target(cmd_context.shell, cmd_context.command_name.as_str()) {
+        execute_autocd(cmd_context).await
     } else {
         let resolved_path = cmd_context.command_name.clone();
 
@@ -349,6 +355,34 @@ pub(crate) async fn execute(
     }
 }
 
+/// Checks whether the given command name should be treated as the name of a directory to
+/// change to; interactive shells do so when the `autocd` option is enabled.
+fn is_autocd_target(shell: &Shell, command_name: &str) -> bool {
+    shell.options.interactive
+        && shell.options.auto_cd
+        && shell
+            .get_absolute_path(std::path::Path::new(command_name))
+            .is_dir()
+}
+
+/// Executes the command as if its name were the argument to `cd`.
+async fn execute_autocd(
+    mut cmd_context: ExecutionContext<'_>,
+) -> Result<CommandSpawnResult, error::Error> {
+    let Some(builtin) = cmd_context.shell.builtins.get("cd").cloned() else {
+        return Ok(CommandSpawnResult::ImmediateExit(127));
+    };
+
+    let dir = std::mem::replace(&mut cmd_context.command_name, String::from("cd"));
+    let args = vec![
+        CommandArg::String(String::from("cd")),
+        CommandArg::String(String::from("--")),
+        CommandArg::String(dir),
+    ];
+
+    execute_builtin_command(&builtin, cmd_context, args).await
+}
+
 #[allow(clippy::too_many_lines)]
 pub(crate) fn execute_external_command(
     context: ExecutionContext<'_>,
@@ -537,5 +571,10 @@ pub(crate) async fn invoke_shell_function(
     // Restore positional parameters.
     context.shell.positional_parameters = prior_positional_params;
 
-    Ok(CommandSpawnResult::ImmediateExit(result?.exit_code))
+    let result = result?;
+    if result.exit_shell {
+        Ok(CommandSpawnResult::ExitShell(result.exit_code))
+    } else {
+        Ok(CommandSpawnResult::ImmediateExit(result.exit_code))
+    }
 }
@@ -254,6 +254,10 @@ impl Spec {
         if let Some(glob_pattern) = &self.glob_pattern {
             let pattern = patterns::Pattern::from(glob_pattern.as_str())
                 .set_extended_globbing(shell.options.extended_globbing)
+                .set_locale(shell.locale())
+                .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
+                .set_star_star_glob(shell.options.enable_star_star_glob)
+                .set_match_dotfiles(shell.options.glob_matches_dotfiles)
                 .set_case_insensitive(shell.options.case_insensitive_pathname_expansion);
 
             let expansions = pattern.expand(
@@ -437,11 +441,25 @@ impl Spec {
                     }
                 }
                 CompleteAction::HostName => {
-                    // N.B. We only retrieve one hostname.
-                    if let Ok(name) = sys::network::get_hostname() {
-                        let name = name.to_string_lossy();
+                    // As in bash, HOSTFILE takes precedence over the system hosts file.
+                    let hosts_file = shell
+                        .env
+                        .get_str("HOSTFILE")
+                        .filter(|s| !s.is_empty())
+                        .map(|s| shell.get_absolute_path(Path::new(s.as_ref())));
+
+                    let mut hostnames = sys::network::get_known_hostnames(hosts_file.as_deref())?;
+
+                    // Fall back to the local hostname if no hosts could be found.
+                    if hostnames.is_empty() {
+                        if let Ok(name) = sys::network::get_hostname() {
+                            hostnames.push(name.to_string_lossy().to_string());
+                        }
+                    }
+
+                    for name in hostnames {
                         if name.starts_with(token) {
-                            candidates.insert(name.to_string());
+                            candidates.insert(name);
                         }
                     }
                 }
@@ -471,7 +489,11 @@ impl Spec {
                     }
                 }
                 CompleteAction::Service => {
-                    tracing::debug!(target: trace_categories::COMPLETION, "UNIMPLEMENTED: complete -A service");
+                    for service_name in sys::network::get_all_services()? {
+                        if service_name.starts_with(token) {
+                            candidates.insert(service_name);
+                        }
+                    }
                 }
                 CompleteAction::SetOpt => {
                     for (name, _) in namedoptions::SET_O_OPTIONS.iter() {
@@ -769,12 +791,42 @@ impl Config {
     /// * `shell` - The shell instance to use for completion generation.
     /// * `input` - The input line for which completions are being generated.
     /// * `position` - The 0-based index of the cursor in the input line.
-    #[allow(clippy::cast_sign_loss)]
     pub async fn get_completions(
         &self,
         shell: &mut Shell,
         input: &str,
         position: usize,
+    ) -> Result<Completions, error::Error> {
+        self.get_completions_impl(shell, input, position, true)
+            .await
+    }
+
+    /// Generates completions for the given input line and cursor position, without consulting
+    /// any programmable completion specs. Only file names, commands, builtins, functions,
+    /// aliases, and keywords are considered; no shell functions or external commands are run.
+    ///
+    /// # Arguments
+    ///
+    /// * `shell` - The shell instance to use for completion generation.
+    /// * `input` - The input line for which completions are being generated.
+    /// * `position` - The 0-based index of the cursor in the input line.
+    pub async fn get_basic_completions(
+        &self,
+        shell: &mut Shell,
+        input: &str,
+        position: usize,
+    ) -> Result<Completions, error::Error> {
+        self.get_completions_impl(shell, input, position, false)
+            .await
+    }
+
+    #[allow(clippy::cast_sign_loss)]
+    async fn get_completions_impl(
+        &self,
+        shell: &mut Shell,
+        input: &str,
+        position: usize,
+        use_specs: bool,
     ) -> Result<Completions, error::Error> {
         const MAX_RESTARTS: u32 = 10;
 
@@ -855,9 +907,12 @@ impl Config {
                 cursor_index: position,
             };
 
-            result = self
-                .get_completions_for_token(shell, completion_context)
-                .await;
+            result = if use_specs {
+                self.get_completions_for_token(shell, completion_context)
+                    .await
+            } else {
+                get_completions_using_basic_lookup(shell, &completion_context).await
+            };
 
             restart_count += 1;
         }
@@ -957,8 +1012,12 @@ async fn get_file_completions(
 
     let path_filter = |path: &Path| !must_be_dir || shell.get_absolute_path(path).is_dir();
 
+    // N.B. Like readline, we match hidden files even if the token doesn't start with a '.'.
     let pattern = patterns::Pattern::from(glob)
         .set_extended_globbing(shell.options.extended_globbing)
+        .set_locale(shell.locale())
+        .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
+        .set_match_dotfiles(true)
         .set_case_insensitive(shell.options.case_insensitive_pathname_expansion);
 
     pattern
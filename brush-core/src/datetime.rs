@@ -0,0 +1,89 @@
+//! Formatting of dates and times in the time zone selected by the shell.
+
+use std::path::Path;
+
+use jiff::fmt::strtime;
+use jiff::tz::TimeZone;
+
+/// Formats the given time (in seconds since the Unix epoch) using a `strftime(3)`-style
+/// format, as in the POSIX locale. If the format isn't valid, it's returned as-is.
+///
+/// # Arguments
+///
+/// * `time` - The time to format, in seconds since the Unix epoch.
+/// * `format` - The format to use.
+/// * `time_zone` - The time zone to format the time in, as a `TZ` value; if not provided,
+///   the system's time zone is used.
+pub(crate) fn format_time(time: i64, format: &str, time_zone: Option<&str>) -> String {
+    let Ok(timestamp) = jiff::Timestamp::from_second(time) else {
+        return String::new();
+    };
+
+    let zoned = timestamp.to_zoned(load_time_zone(time_zone));
+    let config = strtime::Config::new().custom(strtime::PosixCustom::new());
+
+    let mut formatted = String::new();
+    if strtime::BrokenDownTime::from(&zoned)
+        .format_with_config(&config, format, &mut formatted)
+        .is_err()
+    {
+        return format.to_owned();
+    }
+
+    formatted
+}
+
+/// Formats the current time; see [`format_time`].
+///
+/// # Arguments
+///
+/// * `format` - The format to use.
+/// * `time_zone` - The time zone to format the time in, as a `TZ` value.
+pub(crate) fn format_now(format: &str, time_zone: Option<&str>) -> String {
+    format_time(jiff::Timestamp::now().as_second(), format, time_zone)
+}
+
+/// Loads the time zone described by the given `TZ` value. As with the C library, the value
+/// may name a time zone database entry or file (optionally preceded by a `:`), or be a
+/// POSIX time zone string; values that can't be loaded are treated as UTC.
+fn load_time_zone(time_zone: Option<&str>) -> TimeZone {
+    let Some(time_zone) = time_zone else {
+        return TimeZone::system();
+    };
+
+    let name = time_zone.strip_prefix(':').unwrap_or(time_zone);
+    if name.is_empty() {
+        return TimeZone::UTC;
+    }
+
+    let loaded = if Path::new(name).is_absolute() {
+        std::fs::read(name)
+            .ok()
+            .and_then(|data| TimeZone::tzif(name, data.as_slice()).ok())
+    } else {
+        jiff::tz::db()
+            .get(name)
+            .or_else(|_| TimeZone::posix(time_zone))
+            .ok()
+    };
+
+    loaded.unwrap_or(TimeZone::UTC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(
+            format_time(1_721_044_800, "%c|%x|%X|%D", Some("UTC0")),
+            "Mon Jul 15 12:00:00 2024|07/15/24|12:00:00|07/15/24"
+        );
+        assert_eq!(
+            format_time(1_721_044_800, "%H:%M %Z", Some("")),
+            "12:00 UTC"
+        );
+        assert_eq!(format_time(0, "%J", Some("UTC0")), "%J");
+    }
+}
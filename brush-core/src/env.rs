@@ -17,6 +17,16 @@ pub enum EnvironmentLookup {
     OnlyInLocal,
 }
 
+/// Represents how unsetting a variable treats a local variable declared by a calling
+/// function (i.e., in a local scope other than the current one).
+#[derive(Clone, Copy)]
+pub enum PriorLocalUnsetBehavior {
+    /// Remove the variable, exposing any variable of the same name in an enclosing scope.
+    Remove,
+    /// Leave the variable in place but unset, as is done for locals in the current scope.
+    LeavePlaceholder,
+}
+
 /// Represents a shell environment scope.
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum EnvironmentScope {
@@ -134,12 +144,44 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve an immutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment. If the name refers to a name reference, then the variable
+    /// it refers to is retrieved instead.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to retrieve.
     pub fn get<S: AsRef<str>>(&self, name: S) -> Option<(EnvironmentScope, &ShellVariable)> {
+        let name = self.resolve_nameref_for_lookup(name.as_ref(), EnvironmentLookup::Anywhere)?;
+        self.get_unresolved(name)
+    }
+
+    /// Tries to retrieve a mutable reference to the variable with the given name
+    /// in the environment. If the name refers to a name reference, then the variable
+    /// it refers to is retrieved instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    pub fn get_mut<S: AsRef<str>>(
+        &mut self,
+        name: S,
+    ) -> Option<(EnvironmentScope, &mut ShellVariable)> {
+        let name = self
+            .resolve_nameref_for_lookup(name.as_ref(), EnvironmentLookup::Anywhere)?
+            .to_owned();
+        self.get_mut_unresolved(name)
+    }
+
+    /// Tries to retrieve an immutable reference to the variable with the given name
+    /// in the environment, without following name references.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    pub fn get_unresolved<S: AsRef<str>>(
+        &self,
+        name: S,
+    ) -> Option<(EnvironmentScope, &ShellVariable)> {
         // Look through scopes, from the top of the stack on down.
         for (scope_type, map) in self.scopes.iter().rev() {
             if let Some(var) = map.get(name.as_ref()) {
@@ -151,12 +193,12 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve a mutable reference to the variable with the given name
-    /// in the environment.
+    /// in the environment, without following name references.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to retrieve.
-    pub fn get_mut<S: AsRef<str>>(
+    pub fn get_mut_unresolved<S: AsRef<str>>(
         &mut self,
         name: S,
     ) -> Option<(EnvironmentScope, &mut ShellVariable)> {
@@ -170,6 +212,70 @@ impl ShellEnvironment {
         None
     }
 
+    /// Resolves the given variable name through any name references, returning the name
+    /// of the variable ultimately referred to. The resulting name may identify an element
+    /// of an array (e.g., `arr[1]`). Names that don't refer to a name reference with a value
+    /// are returned as-is.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    pub fn resolve_nameref<'a>(&'a self, name: &'a str) -> Result<&'a str, error::Error> {
+        self.resolve_nameref_using_policy(name, EnvironmentLookup::Anywhere)
+    }
+
+    /// Resolves the given variable name through any name references, using the given
+    /// lookup policy to find the variable initially named. See `resolve_nameref` for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to resolve.
+    /// * `lookup_policy` - The policy to use when looking up the variable initially named.
+    pub fn resolve_nameref_using_policy<'a>(
+        &'a self,
+        name: &'a str,
+        lookup_policy: EnvironmentLookup,
+    ) -> Result<&'a str, error::Error> {
+        let mut visited = vec![];
+        let mut current = name;
+        let mut var = self.get_unresolved_using_policy(name, lookup_policy);
+
+        while let Some(target) = var.and_then(nameref_target) {
+            if target == current || visited.contains(&target) {
+                return Err(error::Error::CircularNameReference(name.to_owned()));
+            }
+
+            visited.push(current);
+            current = target;
+
+            // A reference to an array element ends the chain.
+            if split_array_element_name(current).is_some() {
+                break;
+            }
+
+            var = self.get_unresolved(current).map(|(_, var)| var);
+        }
+
+        Ok(current)
+    }
+
+    /// Resolves the given name for the purposes of looking up a whole variable, yielding
+    /// `None` if the name can't be resolved or refers to an array element.
+    fn resolve_nameref_for_lookup<'a>(
+        &'a self,
+        name: &'a str,
+        lookup_policy: EnvironmentLookup,
+    ) -> Option<&'a str> {
+        match self.resolve_nameref_using_policy(name, lookup_policy) {
+            Ok(resolved) if split_array_element_name(resolved).is_none() => Some(resolved),
+            Ok(_) => None,
+            Err(e) => {
+                tracing::warn!("{e}");
+                None
+            }
+        }
+    }
+
     /// Tries to retrieve the string value of the variable with the given name in the
     /// environment.
     ///
@@ -199,12 +305,60 @@ impl ShellEnvironment {
     //
 
     /// Tries to unset the variable with the given name in the environment, returning
-    /// whether or not such a variable existed.
+    /// whether or not such a variable existed. If the name refers to a name reference,
+    /// then the variable it refers to is unset instead.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to unset.
     pub fn unset(&mut self, name: &str) -> Result<Option<ShellVariable>, error::Error> {
+        self.unset_using_policy(name, PriorLocalUnsetBehavior::Remove)
+    }
+
+    /// Tries to unset the variable with the given name in the environment, using the given
+    /// policy for local variables declared by calling functions. See `unset` for details.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to unset.
+    /// * `prior_local_behavior` - How to treat a local variable declared by a calling function.
+    pub fn unset_using_policy(
+        &mut self,
+        name: &str,
+        prior_local_behavior: PriorLocalUnsetBehavior,
+    ) -> Result<Option<ShellVariable>, error::Error> {
+        let name = self.resolve_nameref(name)?.to_owned();
+        if let Some((array_name, index)) = split_array_element_name(name.as_str()) {
+            self.unset_index(array_name, index)?;
+            return Ok(None);
+        }
+
+        self.unset_unresolved_using_policy(name.as_str(), prior_local_behavior)
+    }
+
+    /// Tries to unset the variable with the given name in the environment, without
+    /// following name references. Returns whether or not such a variable existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to unset.
+    pub fn unset_unresolved(&mut self, name: &str) -> Result<Option<ShellVariable>, error::Error> {
+        self.unset_unresolved_using_policy(name, PriorLocalUnsetBehavior::Remove)
+    }
+
+    /// Tries to unset the variable with the given name in the environment, without
+    /// following name references, and using the given policy for local variables declared
+    /// by calling functions. Returns whether or not such a variable existed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to unset.
+    /// * `prior_local_behavior` - How to treat a local variable declared by a calling function.
+    pub fn unset_unresolved_using_policy(
+        &mut self,
+        name: &str,
+        prior_local_behavior: PriorLocalUnsetBehavior,
+    ) -> Result<Option<ShellVariable>, error::Error> {
         let mut local_count = 0;
         for (scope_type, map) in self.scopes.iter_mut().rev() {
             if matches!(scope_type, EnvironmentScope::Local) {
@@ -214,9 +368,15 @@ impl ShellEnvironment {
             let unset_result = Self::try_unset_in_map(map, name)?;
 
             if unset_result.is_some() {
-                // If we end up finding a local in the top-most local frame, then we replace
-                // it with a placeholder.
-                if matches!(scope_type, EnvironmentScope::Local) && local_count == 1 {
+                // If we end up finding a local in the top-most local frame (or in any local
+                // frame, if so requested), then we replace it with a placeholder.
+                if matches!(scope_type, EnvironmentScope::Local)
+                    && (local_count == 1
+                        || matches!(
+                            prior_local_behavior,
+                            PriorLocalUnsetBehavior::LeavePlaceholder
+                        ))
+                {
                     map.set(
                         name,
                         ShellVariable::new(ShellValue::Unset(ShellValueUnsetType::Untyped)),
@@ -238,7 +398,10 @@ impl ShellEnvironment {
     /// * `name` - The name of the array variable to unset an element from.
     /// * `index` - The index of the element to unset.
     pub fn unset_index(&mut self, name: &str, index: &str) -> Result<bool, error::Error> {
-        if let Some((_, var)) = self.get_mut(name) {
+        let name = self.resolve_nameref(name)?.to_owned();
+        let name = split_array_element_name(name.as_str()).map_or(name.as_str(), |(n, _)| n);
+
+        if let Some((_, var)) = self.get_mut_unresolved(name) {
             var.unset_index(index)
         } else {
             Ok(false)
@@ -257,7 +420,8 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve an immutable reference to a variable from the environment,
-    /// using the given name and lookup policy.
+    /// using the given name and lookup policy. If the name refers to a name reference,
+    /// then the variable it refers to is retrieved instead.
     ///
     /// # Arguments
     ///
@@ -267,6 +431,51 @@ impl ShellEnvironment {
         &self,
         name: N,
         lookup_policy: EnvironmentLookup,
+    ) -> Option<&ShellVariable> {
+        let name = name.as_ref();
+        let resolved = self.resolve_nameref_for_lookup(name, lookup_policy)?;
+        if resolved == name {
+            self.get_unresolved_using_policy(name, lookup_policy)
+        } else {
+            self.get_unresolved(resolved).map(|(_, var)| var)
+        }
+    }
+
+    /// Tries to retrieve a mutable reference to a variable from the environment,
+    /// using the given name and lookup policy. If the name refers to a name reference,
+    /// then the variable it refers to is retrieved instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    /// * `lookup_policy` - The policy to use when looking up the variable.
+    pub fn get_mut_using_policy<N: AsRef<str>>(
+        &mut self,
+        name: N,
+        lookup_policy: EnvironmentLookup,
+    ) -> Option<&mut ShellVariable> {
+        let name = name.as_ref();
+        let resolved = self
+            .resolve_nameref_for_lookup(name, lookup_policy)?
+            .to_owned();
+        if resolved == name {
+            self.get_mut_unresolved_using_policy(name, lookup_policy)
+        } else {
+            self.get_mut_unresolved(resolved).map(|(_, var)| var)
+        }
+    }
+
+    /// Tries to retrieve an immutable reference to a variable from the environment,
+    /// using the given name and lookup policy, without following name references.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the variable to retrieve.
+    /// * `lookup_policy` - The policy to use when looking up the variable.
+    pub fn get_unresolved_using_policy<N: AsRef<str>>(
+        &self,
+        name: N,
+        lookup_policy: EnvironmentLookup,
     ) -> Option<&ShellVariable> {
         let mut local_count = 0;
         for (scope_type, var_map) in self.scopes.iter().rev() {
@@ -308,13 +517,13 @@ impl ShellEnvironment {
     }
 
     /// Tries to retrieve a mutable reference to a variable from the environment,
-    /// using the given name and lookup policy.
+    /// using the given name and lookup policy, without following name references.
     ///
     /// # Arguments
     ///
     /// * `name` - The name of the variable to retrieve.
     /// * `lookup_policy` - The policy to use when looking up the variable.
-    pub fn get_mut_using_policy<N: AsRef<str>>(
+    pub fn get_mut_unresolved_using_policy<N: AsRef<str>>(
         &mut self,
         name: N,
         lookup_policy: EnvironmentLookup,
@@ -358,7 +567,8 @@ impl ShellEnvironment {
         None
     }
 
-    /// Update a variable in the environment, or add it if it doesn't already exist.
+    /// Update a variable in the environment, or add it if it doesn't already exist. If the
+    /// name refers to a name reference, then the variable it refers to is updated instead.
     ///
     /// # Arguments
     ///
@@ -376,8 +586,35 @@ impl ShellEnvironment {
         scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
         let name = name.into();
+        let resolved = self
+            .resolve_nameref_using_policy(name.as_str(), lookup_policy)?
+            .to_owned();
+
+        if resolved != name {
+            return match (split_array_element_name(resolved.as_str()), value) {
+                (Some((array_name, index)), variables::ShellValueLiteral::Scalar(value)) => self
+                    .update_or_add_array_element(
+                        array_name,
+                        index.to_owned(),
+                        value,
+                        updater,
+                        EnvironmentLookup::Anywhere,
+                        scope_if_creating,
+                    ),
+                (Some(_), variables::ShellValueLiteral::Array(_)) => {
+                    Err(error::Error::AssigningListToArrayMember)
+                }
+                (None, value) => self.update_or_add(
+                    resolved,
+                    value,
+                    updater,
+                    EnvironmentLookup::Anywhere,
+                    scope_if_creating,
+                ),
+            };
+        }
 
-        if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
+        if let Some(var) = self.get_mut_unresolved_using_policy(&name, lookup_policy) {
             var.assign(value, false)?;
             updater(var)
         } else {
@@ -390,6 +627,8 @@ impl ShellEnvironment {
     }
 
     /// Update an array element in the environment, or add it if it doesn't already exist.
+    /// If the name refers to a name reference, then the variable it refers to is updated
+    /// instead.
     ///
     /// # Arguments
     ///
@@ -409,8 +648,20 @@ impl ShellEnvironment {
         scope_if_creating: EnvironmentScope,
     ) -> Result<(), error::Error> {
         let name = name.into();
+        let resolved = self.resolve_nameref_using_policy(name.as_str(), lookup_policy)?;
+        let resolved = split_array_element_name(resolved).map_or(resolved, |(n, _)| n);
+        if resolved != name {
+            return self.update_or_add_array_element(
+                resolved.to_owned(),
+                index,
+                value,
+                updater,
+                EnvironmentLookup::Anywhere,
+                scope_if_creating,
+            );
+        }
 
-        if let Some(var) = self.get_mut_using_policy(&name, lookup_policy) {
+        if let Some(var) = self.get_mut_unresolved_using_policy(&name, lookup_policy) {
             var.assign_at_index(index, value, false)?;
             updater(var)
         } else {
@@ -466,6 +717,29 @@ impl ShellEnvironment {
     }
 }
 
+/// Returns the name of the variable referred to by the given variable, if it's a name
+/// reference with a value.
+fn nameref_target(var: &ShellVariable) -> Option<&str> {
+    match var.value() {
+        ShellValue::String(target) if var.is_treated_as_nameref() && !target.is_empty() => {
+            Some(target.as_str())
+        }
+        _ => None,
+    }
+}
+
+/// Splits a reference to an array element (e.g., `arr[1]`) into the name of the array and
+/// the element's index. Returns `None` if the name doesn't refer to an array element.
+///
+/// # Arguments
+///
+/// * `name` - The name to split.
+pub(crate) fn split_array_element_name(name: &str) -> Option<(&str, &str)> {
+    let (array_name, rest) = name.split_once('[')?;
+    let index = rest.strip_suffix(']')?;
+    Some((array_name, index))
+}
+
 /// Represents a map from names to shell variables.
 #[derive(Clone, Debug)]
 pub struct ShellVariableMap {
@@ -39,6 +39,14 @@ pub enum Error {
     #[error("expansion error: {0}")]
     CheckedExpansionError(String),
 
+    /// An unset parameter was expanded while `set -u` was in effect.
+    #[error("{0}: unbound variable")]
+    UnboundVariable(String),
+
+    /// A pattern matched no file paths while `failglob` was in effect.
+    #[error("no match: {0}")]
+    NoPathnameMatches(String),
+
     /// A reference was made to an unknown shell function.
     #[error("function not found: {0}")]
     FunctionNotFound(String),
@@ -99,6 +107,14 @@ pub enum Error {
     #[error("failed to decode utf-8")]
     Utf8Error(#[from] std::str::Utf8Error),
 
+    /// A shared object or one of its symbols could not be loaded.
+    #[error("{0}")]
+    DynamicLoadError(String),
+
+    /// A name reference was found to refer, directly or indirectly, to itself.
+    #[error("{0}: circular name reference")]
+    CircularNameReference(String),
+
     /// An attempt was made to modify a readonly variable.
     #[error("cannot mutate readonly variable")]
     ReadonlyVariable,
@@ -186,6 +202,18 @@ pub enum Error {
     Interrupted,
 }
 
+impl Error {
+    /// Returns whether the error should cause a non-interactive shell to exit.
+    pub(crate) fn is_fatal_to_noninteractive_shell(&self) -> bool {
+        matches!(
+            self,
+            Error::UnboundVariable(_)
+                | Error::CheckedExpansionError(_)
+                | Error::EvalError(crate::arithmetic::EvalError::UnboundVariable(_))
+        )
+    }
+}
+
 /// Convenience function for returning an error for unimplemented functionality.
 ///
 /// # Arguments
@@ -1,4 +1,5 @@
 use std::borrow::Cow;
+use std::fmt::Write;
 
 use itertools::Itertools;
 
@@ -8,6 +9,10 @@ use crate::error;
 pub(crate) enum EscapeExpansionMode {
     EchoBuiltin,
     AnsiCQuotes,
+    /// Escapes in the format string given to `printf`.
+    PrintfFormat,
+    /// Escapes in an argument to `printf`'s `%b` conversion.
+    PrintfEscapeArg,
 }
 
 #[allow(clippy::too_many_lines)]
@@ -15,6 +20,11 @@ pub(crate) fn expand_backslash_escapes(
     s: &str,
     mode: EscapeExpansionMode,
 ) -> Result<(Vec<u8>, bool), crate::error::Error> {
+    let removes_backslash_from_quotes = matches!(
+        mode,
+        EscapeExpansionMode::AnsiCQuotes | EscapeExpansionMode::PrintfFormat
+    );
+
     let mut result: Vec<u8> = vec![];
     let mut it = s.chars();
     while let Some(c) = it.next() {
@@ -29,10 +39,14 @@ pub(crate) fn expand_backslash_escapes(
             Some('b') => result.push(b'\x08'),
             Some('c') => {
                 match mode {
-                    EscapeExpansionMode::EchoBuiltin => {
+                    EscapeExpansionMode::EchoBuiltin | EscapeExpansionMode::PrintfEscapeArg => {
                         // Stop all additional output!
                         return Ok((result, false));
                     }
+                    EscapeExpansionMode::PrintfFormat => {
+                        result.push(b'\\');
+                        result.push(b'c');
+                    }
                     EscapeExpansionMode::AnsiCQuotes => {
                         if let Some(_next_next) = it.next() {
                             return error::unimp("control character in ANSI C quotes");
@@ -50,29 +64,41 @@ pub(crate) fn expand_backslash_escapes(
             Some('t') => result.push(b'\t'),
             Some('v') => result.push(b'\x0b'),
             Some('\\') => result.push(b'\\'),
-            Some('\'') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\''),
-            Some('\"') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'\"'),
-            Some('?') if matches!(mode, EscapeExpansionMode::AnsiCQuotes) => result.push(b'?'),
-            Some('0') => {
-                // Consume 0-3 valid octal chars
+            Some('\'') if removes_backslash_from_quotes => result.push(b'\''),
+            Some('\"') if removes_backslash_from_quotes => result.push(b'\"'),
+            Some('?') if removes_backslash_from_quotes => result.push(b'?'),
+            Some(first @ '0'..='7')
+                if first == '0'
+                    || matches!(
+                        mode,
+                        EscapeExpansionMode::PrintfFormat | EscapeExpansionMode::PrintfEscapeArg
+                    ) =>
+            {
+                // A leading 0 may be followed by up to 3 more octal chars, except in printf
+                // format strings, which take at most 3 octal chars in total.
+                let max_chars =
+                    if first == '0' && !matches!(mode, EscapeExpansionMode::PrintfFormat) {
+                        3
+                    } else {
+                        2
+                    };
+
                 let mut taken_so_far = 0;
-                let mut octal_chars: String = it
+                let value = it
                     .take_while_ref(|c| {
-                        if taken_so_far < 3 && matches!(*c, '0'..='7') {
+                        if taken_so_far < max_chars && matches!(*c, '0'..='7') {
                             taken_so_far += 1;
                             true
                         } else {
                             false
                         }
                     })
-                    .collect();
+                    .fold(first.to_digit(8).unwrap_or(0), |value, c| {
+                        value * 8 + c.to_digit(8).unwrap_or(0)
+                    });
 
-                if octal_chars.is_empty() {
-                    octal_chars.push('0');
-                }
-
-                let value = u8::from_str_radix(octal_chars.as_str(), 8)?;
-                result.push(value);
+                // Values that don't fit in a byte wrap around.
+                result.push(value.to_le_bytes()[0]);
             }
             Some('x') => {
                 // Consume 1-2 valid hex chars
@@ -90,7 +116,7 @@ pub(crate) fn expand_backslash_escapes(
 
                 if hex_chars.is_empty() {
                     result.push(b'\\');
-                    result.append(c.to_string().into_bytes().as_mut());
+                    result.push(b'x');
                 } else {
                     let value = u8::from_str_radix(hex_chars.as_str(), 16)?;
                     result.push(value);
@@ -112,7 +138,7 @@ pub(crate) fn expand_backslash_escapes(
 
                 if hex_chars.is_empty() {
                     result.push(b'\\');
-                    result.append(c.to_string().into_bytes().as_mut());
+                    result.push(b'u');
                 } else {
                     let value = u16::from_str_radix(hex_chars.as_str(), 16)?;
 
@@ -120,7 +146,7 @@ pub(crate) fn expand_backslash_escapes(
                         result.append(decoded.to_string().into_bytes().as_mut());
                     } else {
                         result.push(b'\\');
-                        result.append(c.to_string().into_bytes().as_mut());
+                        result.push(b'u');
                     }
                 }
             }
@@ -140,7 +166,7 @@ pub(crate) fn expand_backslash_escapes(
 
                 if hex_chars.is_empty() {
                     result.push(b'\\');
-                    result.append(c.to_string().into_bytes().as_mut());
+                    result.push(b'U');
                 } else {
                     let value = u32::from_str_radix(hex_chars.as_str(), 16)?;
 
@@ -148,7 +174,7 @@ pub(crate) fn expand_backslash_escapes(
                         result.append(decoded.to_string().into_bytes().as_mut());
                     } else {
                         result.push(b'\\');
-                        result.append(c.to_string().into_bytes().as_mut());
+                        result.push(b'U');
                     }
                 }
             }
@@ -171,33 +197,80 @@ pub(crate) fn expand_backslash_escapes(
 pub(crate) enum QuoteMode {
     BackslashEscape,
     Quote,
+    /// ANSI-C quoting (i.e., `$'...'`), for strings containing control characters.
+    AnsiC,
 }
 
 pub(crate) fn quote_if_needed(s: &str, mode: QuoteMode) -> Cow<'_, str> {
     match mode {
         QuoteMode::BackslashEscape => escape_with_backslash(s),
         QuoteMode::Quote => escape_with_quoting(s),
+        QuoteMode::AnsiC => escape_with_ansi_c_quoting(s),
     }
 }
 
 fn escape_with_backslash(s: &str) -> Cow<'_, str> {
-    if !s.chars().any(needs_escaping) {
+    let mut output = String::new();
+
+    let mut prev_char = None;
+    for c in s.chars() {
+        // Comment and tilde chars are only special at the start of a word; tildes are
+        // additionally expanded after a colon or equals sign in assignments.
+        let special = match c {
+            '#' => prev_char.is_none(),
+            '~' => matches!(prev_char, None | Some(':' | '=')),
+            '\'' | '\t' | '\n' => true,
+            c => needs_escaping(c),
+        };
+
+        if special {
+            output.push('\\');
+        }
+
+        output.push(c);
+        prev_char = Some(c);
+    }
+
+    if output.len() == s.len() {
+        s.into()
+    } else {
+        output.into()
+    }
+}
+
+fn escape_with_ansi_c_quoting(s: &str) -> Cow<'_, str> {
+    if !s.chars().any(|c| c.is_control()) {
         return s.into();
     }
 
-    let mut output = String::new();
+    let mut output = String::from("$'");
 
-    // TODO: Handle other interesting sequences.
     for c in s.chars() {
         match c {
-            c if needs_escaping(c) => {
+            '\x07' => output.push_str("\\a"),
+            '\x08' => output.push_str("\\b"),
+            '\x1b' => output.push_str("\\E"),
+            '\x0c' => output.push_str("\\f"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\x0b' => output.push_str("\\v"),
+            '\\' | '\'' => {
                 output.push('\\');
                 output.push(c);
             }
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    // N.B. Writing to a String is infallible.
+                    let _ = write!(output, "\\{b:03o}");
+                }
+            }
             c => output.push(c),
         }
     }
 
+    output.push('\'');
     output.into()
 }
 
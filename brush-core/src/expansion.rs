@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::cmp::min;
 
 use brush_parser::ast;
@@ -6,6 +7,7 @@ use brush_parser::word::SubstringMatchKind;
 use itertools::Itertools;
 
 use crate::arithmetic::ExpandAndEvaluate;
+use crate::braceexpansion;
 use crate::env;
 use crate::error;
 use crate::escape;
@@ -440,11 +442,6 @@ impl<'a> WordExpander<'a> {
     async fn basic_expand(&mut self, word: &str) -> Result<Expansion, error::Error> {
         tracing::debug!(target: trace_categories::EXPANSION, "Basic expanding: '{word}'");
 
-        //
-        // TODO: Brace expansion in unquoted pieces
-        // Issue #42
-        //
-
         //
         // Expand: tildes, parameters, command substitutions, arithmetic.
         //
@@ -459,11 +456,31 @@ impl<'a> WordExpander<'a> {
         Ok(coalesce_expansions(expansions))
     }
 
-    /// Apply tilde-expansion, parameter expansion, command substitution, and arithmetic expansion;
-    /// then perform field splitting and pathname expansion.
+    /// Apply brace expansion; then apply tilde-expansion, parameter expansion, command
+    /// substitution, and arithmetic expansion to each resulting word; then perform field
+    /// splitting and pathname expansion.
     pub async fn full_expand_with_splitting(
         &mut self,
         word: &str,
+    ) -> Result<Vec<String>, error::Error> {
+        // Brace expansion comes first, and may yield multiple words.
+        let words = if self.shell.options.perform_brace_expansion {
+            braceexpansion::expand_braces(word, &self.parser_options)
+        } else {
+            vec![word.to_owned()]
+        };
+
+        let mut result = vec![];
+        for word in words {
+            result.append(&mut self.full_expand_word_with_splitting(word.as_str()).await?);
+        }
+
+        Ok(result)
+    }
+
+    async fn full_expand_word_with_splitting(
+        &mut self,
+        word: &str,
     ) -> Result<Vec<String>, error::Error> {
         // Perform basic expansion first.
         let basic_expansion = self.basic_expand(word).await?;
@@ -472,16 +489,14 @@ impl<'a> WordExpander<'a> {
         let fields: Vec<WordField> = self.split_fields(basic_expansion);
 
         // Now expand pathnames if necessary. This also unquotes as a side effect.
-        let result = fields
-            .into_iter()
-            .flat_map(|field| {
-                if self.shell.options.disable_filename_globbing {
-                    vec![String::from(field)]
-                } else {
-                    self.expand_pathnames_in_field(field)
-                }
-            })
-            .collect();
+        let mut result = vec![];
+        for field in fields {
+            if self.shell.options.disable_filename_globbing {
+                result.push(String::from(field));
+            } else {
+                result.extend(self.expand_pathnames_in_field(field)?);
+            }
+        }
 
         Ok(result)
     }
@@ -526,25 +541,103 @@ impl<'a> WordExpander<'a> {
         fields
     }
 
-    fn expand_pathnames_in_field(&self, field: WordField) -> Vec<String> {
+    fn expand_pathnames_in_field(&self, field: WordField) -> Result<Vec<String>, error::Error> {
         let pattern = patterns::Pattern::from(field.clone())
             .set_extended_globbing(self.parser_options.enable_extended_globbing)
+            .set_locale(self.shell.locale())
+            .set_ranges_use_c_locale(self.shell.options.glob_ranges_use_c_locale)
             .set_case_insensitive(self.shell.options.case_insensitive_pathname_expansion);
 
+        if !pattern.requires_expansion() {
+            return Ok(vec![String::from(field)]);
+        }
+
+        // A non-empty GLOBIGNORE implies dotglob.
+        let ignore_patterns = self.get_globignore_patterns();
+        let match_dotfiles =
+            self.shell.options.glob_matches_dotfiles || !ignore_patterns.is_empty();
+
+        let pattern = pattern
+            .set_star_star_glob(self.shell.options.enable_star_star_glob)
+            .set_match_dotfiles(match_dotfiles);
+
+        // Expanded paths are absolute; GLOBIGNORE patterns are matched against them as
+        // they'll be presented, i.e., relative to the working directory unless the
+        // pattern itself was absolute.
+        let relative_to = if String::from(field.clone()).starts_with('/') {
+            None
+        } else {
+            Some(self.shell.working_dir.as_path())
+        };
+
+        let path_filter = |path: &std::path::Path| {
+            let path = relative_to
+                .and_then(|dir| path.strip_prefix(dir).ok())
+                .unwrap_or(path);
+
+            !ignore_patterns
+                .iter()
+                .any(|ignore_pattern| self.ignore_pattern_matches(ignore_pattern, path))
+        };
+
         let expansions = pattern
-            .expand(
-                self.shell.working_dir.as_path(),
-                Some(&patterns::Pattern::accept_all_expand_filter),
-            )
+            .expand(self.shell.working_dir.as_path(), Some(&path_filter))
             .unwrap_or_default();
 
-        if expansions.is_empty() && !self.shell.options.expand_non_matching_patterns_to_null {
-            vec![String::from(field)]
+        if !expansions.is_empty() {
+            Ok(expansions)
+        } else if self.shell.options.fail_expansion_on_globs_without_match {
+            Err(error::Error::NoPathnameMatches(String::from(field)))
+        } else if self.shell.options.expand_non_matching_patterns_to_null {
+            Ok(vec![])
         } else {
-            expansions
+            Ok(vec![String::from(field)])
         }
     }
 
+    /// Returns the colon-separated patterns in GLOBIGNORE, if any.
+    fn get_globignore_patterns(&self) -> Vec<String> {
+        self.shell
+            .env
+            .get_str("GLOBIGNORE")
+            .unwrap_or_default()
+            .split(':')
+            .filter(|s| !s.is_empty())
+            .map(|s| s.to_owned())
+            .collect()
+    }
+
+    /// Checks whether the given GLOBIGNORE pattern matches an expanded path. As with bash,
+    /// wildcards in the pattern do not match '/'; the pattern must match the path one
+    /// component at a time.
+    fn ignore_pattern_matches(&self, ignore_pattern: &str, path: &std::path::Path) -> bool {
+        let path = path.to_string_lossy();
+
+        // The special entries '.' and '..' are always ignored.
+        let file_name = path.rsplit('/').next().unwrap_or_default();
+        if file_name == "." || file_name == ".." {
+            return true;
+        }
+
+        let pattern_components: Vec<_> = ignore_pattern.split('/').collect();
+        let path_components: Vec<_> = path.split('/').collect();
+
+        pattern_components.len() == path_components.len()
+            && pattern_components.into_iter().zip(path_components).all(
+                |(pattern_component, path_component)| {
+                    patterns::Pattern::from(pattern_component)
+                        .set_extended_globbing(self.parser_options.enable_extended_globbing)
+                        .set_locale(self.shell.locale())
+                        .set_ranges_use_c_locale(self.shell.options.glob_ranges_use_c_locale)
+                        .set_case_insensitive(
+                            self.shell.options.case_insensitive_pathname_expansion,
+                        )
+                        .exactly_matches(path_component)
+                        .unwrap_or(false)
+                },
+            )
+    }
+
     #[async_recursion::async_recursion]
     #[allow(clippy::too_many_lines)]
     async fn expand_word_piece(
@@ -648,6 +741,13 @@ impl<'a> WordExpander<'a> {
                 // Insantiate a subshell to run the command in.
                 let mut subshell = self.shell.clone();
 
+                // Unless inherit_errexit is enabled, command substitutions don't inherit
+                // errexit (outside of POSIX mode).
+                if !subshell.options.command_subst_inherits_errexit && !subshell.options.posix_mode
+                {
+                    subshell.options.exit_on_nonzero_command_exit = false;
+                }
+
                 // Set up pipe so we can read the output.
                 let (reader, writer) = sys::pipes::pipe()?;
                 subshell
@@ -712,7 +812,7 @@ impl<'a> WordExpander<'a> {
             brush_parser::word::ParameterExpr::Parameter {
                 parameter,
                 indirect,
-            } => self.expand_parameter(&parameter, indirect).await,
+            } => self.expand_set_parameter(&parameter, indirect).await,
             brush_parser::word::ParameterExpr::UseDefaultValues {
                 parameter,
                 indirect,
@@ -801,7 +901,7 @@ impl<'a> WordExpander<'a> {
                 parameter,
                 indirect,
             } => {
-                let expansion = self.expand_parameter(&parameter, indirect).await?;
+                let expansion = self.expand_set_parameter(&parameter, indirect).await?;
                 Ok(Expansion::from(expansion.polymorphic_len().to_string()))
             }
             brush_parser::word::ParameterExpr::RemoveSmallestSuffixPattern {
@@ -809,7 +909,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
                 transform_expansion(expanded_parameter, |s| {
                     patterns::remove_smallest_matching_suffix(s.as_str(), &expanded_pattern)
@@ -821,7 +921,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
                 transform_expansion(expanded_parameter, |s| {
                     patterns::remove_largest_matching_suffix(s.as_str(), &expanded_pattern)
@@ -833,7 +933,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
                 transform_expansion(expanded_parameter, |s| {
                     patterns::remove_smallest_matching_prefix(s.as_str(), &expanded_pattern)
@@ -845,7 +945,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
                 transform_expansion(expanded_parameter, |s| {
                     patterns::remove_largest_matching_prefix(s.as_str(), &expanded_pattern)
@@ -858,7 +958,8 @@ impl<'a> WordExpander<'a> {
                 offset,
                 length,
             } => {
-                let mut expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let mut expanded_parameter =
+                    self.expand_set_parameter(&parameter, indirect).await?;
 
                 // If this is ${@:...} then make sure $0 is in the array being sliced.
                 if matches!(
@@ -939,7 +1040,8 @@ impl<'a> WordExpander<'a> {
                     match var.value() {
                         ShellValue::IndexedArray(_)
                         | ShellValue::AssociativeArray(_)
-                        | ShellValue::Random => {
+                        | ShellValue::Random
+                        | ShellValue::Dynamic(_) => {
                             let equals_or_nothing = if assignable_value_str.is_empty() {
                                 ""
                             } else {
@@ -967,7 +1069,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 op,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 transform_expansion(expanded_parameter, |s| self.apply_transform_to(&op, s))
             }
             brush_parser::word::ParameterExpr::UppercaseFirstChar {
@@ -975,7 +1077,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
 
                 transform_expansion(expanded_parameter, |s| {
@@ -987,7 +1089,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
 
                 transform_expansion(expanded_parameter, |s| {
@@ -999,7 +1101,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
 
                 Ok(transform_expansion(expanded_parameter, |s| {
@@ -1011,7 +1113,7 @@ impl<'a> WordExpander<'a> {
                 indirect,
                 pattern,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_opt_pattern(&pattern).await?;
 
                 Ok(transform_expansion(expanded_parameter, |s| {
@@ -1025,7 +1127,7 @@ impl<'a> WordExpander<'a> {
                 replacement,
                 match_kind,
             } => {
-                let expanded_parameter = self.expand_parameter(&parameter, indirect).await?;
+                let expanded_parameter = self.expand_set_parameter(&parameter, indirect).await?;
                 let expanded_pattern = self.basic_expand_to_str(&pattern).await?;
 
                 // If no replacement was provided, then we replace with an empty string.
@@ -1034,6 +1136,8 @@ impl<'a> WordExpander<'a> {
 
                 let pattern = patterns::Pattern::from(expanded_pattern.as_str())
                     .set_extended_globbing(self.parser_options.enable_extended_globbing)
+                    .set_locale(self.shell.locale())
+                    .set_ranges_use_c_locale(self.shell.options.glob_ranges_use_c_locale)
                     .set_case_insensitive(self.shell.options.case_insensitive_conditionals);
 
                 let regex = pattern.to_regex(
@@ -1107,7 +1211,8 @@ impl<'a> WordExpander<'a> {
         parameter: &brush_parser::word::Parameter,
         value: String,
     ) -> Result<(), error::Error> {
-        let (variable_name, index) = match parameter {
+        let parameter = self.resolve_nameref_in_parameter(parameter);
+        let (variable_name, index) = match parameter.as_ref() {
             brush_parser::word::Parameter::Named(name) => (name, None),
             brush_parser::word::Parameter::NamedWithIndex { name, index } => {
                 let is_set_assoc_array = if let Some((_, var)) = self.shell.env.get(name.as_str()) {
@@ -1175,7 +1280,8 @@ impl<'a> WordExpander<'a> {
         &self,
         parameter: &brush_parser::word::Parameter,
     ) -> (Option<String>, Option<String>, Option<ShellVariable>) {
-        let (name, index) = match parameter {
+        let parameter = self.resolve_nameref_in_parameter(parameter);
+        let (name, index) = match parameter.as_ref() {
             brush_parser::word::Parameter::Positional(_)
             | brush_parser::word::Parameter::Special(_) => (None, None),
             brush_parser::word::Parameter::Named(name) => (Some(name.to_owned()), Some("0".into())),
@@ -1195,11 +1301,33 @@ impl<'a> WordExpander<'a> {
         (name, index, var)
     }
 
+    /// Expands the given parameter, failing if it's unset and `set -u` is in effect.
+    async fn expand_set_parameter(
+        &mut self,
+        parameter: &brush_parser::word::Parameter,
+        indirect: bool,
+    ) -> Result<Expansion, error::Error> {
+        let expansion = self.expand_parameter(parameter, indirect).await?;
+
+        if expansion.undefined && self.shell.options.treat_unset_variables_as_error {
+            return Err(error::Error::UnboundVariable(describe_parameter(parameter)));
+        }
+
+        Ok(expansion)
+    }
+
     async fn expand_parameter(
         &mut self,
         parameter: &brush_parser::word::Parameter,
         indirect: bool,
     ) -> Result<Expansion, error::Error> {
+        // The indirect expansion of a name reference yields the name it refers to.
+        if indirect {
+            if let Some(target) = self.try_get_nameref_target(parameter) {
+                return Ok(Expansion::from(target));
+            }
+        }
+
         let expansion = self.expand_parameter_without_indirect(parameter).await?;
         if !indirect {
             Ok(expansion)
@@ -1213,11 +1341,76 @@ impl<'a> WordExpander<'a> {
         }
     }
 
+    /// Returns the name referred to by the given parameter, if it names a name reference
+    /// with a value.
+    fn try_get_nameref_target(&self, parameter: &brush_parser::word::Parameter) -> Option<String> {
+        let brush_parser::word::Parameter::Named(name) = parameter else {
+            return None;
+        };
+
+        match self.shell.env.get_unresolved(name) {
+            Some((_, var)) if var.is_treated_as_nameref() => match var.value() {
+                ShellValue::String(target) => Some(target.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Rewrites the given parameter to refer to the variable ultimately referred to by any
+    /// name reference it names.
+    fn resolve_nameref_in_parameter<'p>(
+        &self,
+        parameter: &'p brush_parser::word::Parameter,
+    ) -> Cow<'p, brush_parser::word::Parameter> {
+        let name = match parameter {
+            brush_parser::word::Parameter::Named(name)
+            | brush_parser::word::Parameter::NamedWithIndex { name, .. }
+            | brush_parser::word::Parameter::NamedWithAllIndices { name, .. } => name,
+            brush_parser::word::Parameter::Positional(_)
+            | brush_parser::word::Parameter::Special(_) => return Cow::Borrowed(parameter),
+        };
+
+        // N.B. Circular references are reported when the variable is looked up.
+        let resolved = match self.shell.env.resolve_nameref(name) {
+            Ok(resolved) if resolved != name => resolved,
+            _ => return Cow::Borrowed(parameter),
+        };
+
+        let (resolved_name, resolved_index) = match env::split_array_element_name(resolved) {
+            Some((array_name, index)) => (array_name.to_owned(), Some(index.to_owned())),
+            None => (resolved.to_owned(), None),
+        };
+
+        Cow::Owned(match (parameter, resolved_index) {
+            (brush_parser::word::Parameter::Named(_), Some(index)) => {
+                brush_parser::word::Parameter::NamedWithIndex {
+                    name: resolved_name,
+                    index,
+                }
+            }
+            (brush_parser::word::Parameter::NamedWithIndex { index, .. }, _) => {
+                brush_parser::word::Parameter::NamedWithIndex {
+                    name: resolved_name,
+                    index: index.clone(),
+                }
+            }
+            (brush_parser::word::Parameter::NamedWithAllIndices { concatenate, .. }, _) => {
+                brush_parser::word::Parameter::NamedWithAllIndices {
+                    name: resolved_name,
+                    concatenate: *concatenate,
+                }
+            }
+            _ => brush_parser::word::Parameter::Named(resolved_name),
+        })
+    }
+
     async fn expand_parameter_without_indirect(
         &mut self,
         parameter: &brush_parser::word::Parameter,
     ) -> Result<Expansion, error::Error> {
-        match parameter {
+        let parameter = self.resolve_nameref_in_parameter(parameter);
+        match parameter.as_ref() {
             brush_parser::word::Parameter::Positional(p) => {
                 if *p == 0 {
                     self.expand_special_parameter(&brush_parser::word::SpecialParameter::ShellName)
@@ -1556,7 +1749,7 @@ fn to_initial_capitals(s: &str) -> String {
     result
 }
 
-fn valid_variable_name(s: &str) -> bool {
+pub(crate) fn valid_variable_name(s: &str) -> bool {
     let mut cs = s.chars();
     match cs.next() {
         Some(c) if c.is_ascii_alphabetic() || c == '_' => {
@@ -1566,6 +1759,37 @@ fn valid_variable_name(s: &str) -> bool {
     }
 }
 
+/// Describes the given parameter the way bash does in "unbound variable" errors.
+fn describe_parameter(parameter: &brush_parser::word::Parameter) -> String {
+    match parameter {
+        brush_parser::word::Parameter::Positional(p) => std::format!("${p}"),
+        brush_parser::word::Parameter::Special(special) => {
+            let c = match special {
+                brush_parser::word::SpecialParameter::AllPositionalParameters {
+                    concatenate: false,
+                } => '@',
+                brush_parser::word::SpecialParameter::AllPositionalParameters {
+                    concatenate: true,
+                } => '*',
+                brush_parser::word::SpecialParameter::PositionalParameterCount => '#',
+                brush_parser::word::SpecialParameter::LastExitStatus => '?',
+                brush_parser::word::SpecialParameter::CurrentOptionFlags => '-',
+                brush_parser::word::SpecialParameter::ProcessId => '$',
+                brush_parser::word::SpecialParameter::LastBackgroundProcessId => '!',
+                brush_parser::word::SpecialParameter::ShellName => '0',
+            };
+            std::format!("${c}")
+        }
+        brush_parser::word::Parameter::Named(name) => name.clone(),
+        brush_parser::word::Parameter::NamedWithIndex { name, index } => {
+            std::format!("{name}[{index}]")
+        }
+        brush_parser::word::Parameter::NamedWithAllIndices { name, concatenate } => {
+            std::format!("{name}[{}]", if *concatenate { "*" } else { "@" })
+        }
+    }
+}
+
 fn transform_expansion(
     expansion: Expansion,
     mut f: impl FnMut(String) -> Result<String, error::Error>,
@@ -1676,4 +1900,31 @@ mod tests {
         assert!(valid_variable_name("a1"));
         assert!(valid_variable_name("A1"));
     }
+
+    #[test]
+    fn test_describe_parameter() {
+        use brush_parser::word::{Parameter, SpecialParameter};
+
+        assert_eq!(describe_parameter(&Parameter::Positional(3)), "$3");
+        assert_eq!(describe_parameter(&Parameter::Named("x".into())), "x");
+
+        for (special, expected) in [
+            (
+                SpecialParameter::AllPositionalParameters { concatenate: false },
+                "$@",
+            ),
+            (
+                SpecialParameter::AllPositionalParameters { concatenate: true },
+                "$*",
+            ),
+            (SpecialParameter::PositionalParameterCount, "$#"),
+            (SpecialParameter::LastExitStatus, "$?"),
+            (SpecialParameter::CurrentOptionFlags, "$-"),
+            (SpecialParameter::ProcessId, "$$"),
+            (SpecialParameter::LastBackgroundProcessId, "$!"),
+            (SpecialParameter::ShellName, "$0"),
+        ] {
+            assert_eq!(describe_parameter(&Parameter::Special(special)), expected);
+        }
+    }
 }
@@ -7,7 +7,7 @@ use crate::{
         fs::{MetadataExt, PathExt},
         users,
     },
-    variables::{self, ArrayLiteral},
+    variables::{self, ArrayLiteral, ShellValue},
     Shell,
 };
 
@@ -50,10 +50,12 @@ async fn apply_unary_predicate(
     let expanded_operand = expansion::basic_expand_word(shell, operand).await?;
 
     if shell.options.print_commands_and_arguments {
-        shell.trace_command(std::format!(
-            "[[ {op} {} ]]",
-            escape::quote_if_needed(&expanded_operand, escape::QuoteMode::Quote)
-        ))?;
+        shell
+            .trace_command(std::format!(
+                "[[ {op} {} ]]",
+                escape::quote_if_needed(&expanded_operand, escape::QuoteMode::Quote)
+            ))
+            .await?;
     }
 
     apply_unary_predicate_to_str(op, expanded_operand.as_str(), shell)
@@ -150,7 +152,15 @@ pub(crate) fn apply_unary_predicate_to_str(
             Ok(md.gid() == users::get_effective_gid()?)
         }
         ast::UnaryPredicate::FileExistsAndModifiedSinceLastRead => {
-            error::unimp("unary extended test predicate: FileExistsAndModifiedSinceLastRead")
+            let path = shell.get_absolute_path(Path::new(operand));
+            if let Ok(metadata) = path.metadata() {
+                match (metadata.modified(), metadata.accessed()) {
+                    (Ok(modified), Ok(accessed)) => Ok(modified > accessed),
+                    _ => Ok(false),
+                }
+            } else {
+                Ok(false)
+            }
         }
         ast::UnaryPredicate::FileExistsAndOwnedByEffectiveUserId => {
             let path = shell.get_absolute_path(Path::new(operand));
@@ -175,7 +185,9 @@ pub(crate) fn apply_unary_predicate_to_str(
         }
         ast::UnaryPredicate::ShellVariableIsSetAndAssigned => Ok(shell.env.is_set(operand)),
         ast::UnaryPredicate::ShellVariableIsSetAndNameRef => {
-            error::unimp("unary extended test predicate: ShellVariableIsSetAndNameRef")
+            Ok(shell.env.get_unresolved(operand).is_some_and(|(_, var)| {
+                var.is_treated_as_nameref() && !matches!(var.value(), ShellValue::Unset(_))
+            }))
         }
     }
 }
@@ -191,7 +203,9 @@ async fn apply_binary_predicate(
     match op {
         ast::BinaryPredicate::StringMatchesRegex => {
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             let s = expansion::basic_expand_word(shell, left).await?;
@@ -225,48 +239,59 @@ async fn apply_binary_predicate(
             let substring = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {s} {op} {substring} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {substring} ]]"))
+                    .await?;
             }
 
             Ok(s.contains(substring.as_str()))
         }
-        ast::BinaryPredicate::FilesReferToSameDeviceAndInodeNumbers => {
-            error::unimp("extended test binary predicate FilesReferToSameDeviceAndInodeNumbers")
-        }
-        ast::BinaryPredicate::LeftFileIsNewerOrExistsWhenRightDoesNot => {
-            error::unimp("extended test binary predicate LeftFileIsNewerOrExistsWhenRightDoesNot")
+        ast::BinaryPredicate::FilesReferToSameDeviceAndInodeNumbers
+        | ast::BinaryPredicate::LeftFileIsNewerOrExistsWhenRightDoesNot
+        | ast::BinaryPredicate::LeftFileIsOlderOrDoesNotExistWhenRightDoes => {
+            let left = expansion::basic_expand_word(shell, left).await?;
+            let right = expansion::basic_expand_word(shell, right).await?;
+
+            if shell.options.print_commands_and_arguments {
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
+            }
+
+            apply_binary_predicate_to_strs(op, left.as_str(), right.as_str(), shell)
         }
-        ast::BinaryPredicate::LeftFileIsOlderOrDoesNotExistWhenRightDoes => error::unimp(
-            "extended test binary predicate LeftFileIsOlderOrDoesNotExistWhenRightDoes",
-        ),
         ast::BinaryPredicate::LeftSortsBeforeRight => {
             let left = expansion::basic_expand_word(shell, left).await?;
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
-            // TODO: According to docs, should be lexicographical order of the current locale.
-            Ok(left < right)
+            Ok(shell.locale().compare(&left, &right).is_lt())
         }
         ast::BinaryPredicate::LeftSortsAfterRight => {
             let left = expansion::basic_expand_word(shell, left).await?;
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
-            // TODO: According to docs, should be lexicographical order of the current locale.
-            Ok(left > right)
+            Ok(shell.locale().compare(&left, &right).is_gt())
         }
         ast::BinaryPredicate::ArithmeticEqualTo => {
             let left = expansion::basic_expand_word(shell, left).await?;
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -280,7 +305,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -294,7 +321,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -308,7 +337,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -322,7 +353,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -336,7 +369,9 @@ async fn apply_binary_predicate(
             let right = expansion::basic_expand_word(shell, right).await?;
 
             if shell.options.print_commands_and_arguments {
-                shell.trace_command(std::format!("[[ {left} {op} {right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {left} {op} {right} ]]"))
+                    .await?;
             }
 
             Ok(apply_binary_arithmetic_predicate(
@@ -354,11 +389,15 @@ async fn apply_binary_predicate(
             let pattern = expansion::basic_expand_pattern(shell, right)
                 .await?
                 .set_extended_globbing(shell.options.extended_globbing)
+                .set_locale(shell.locale())
+                .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
                 .set_case_insensitive(shell.options.case_insensitive_conditionals);
 
             if shell.options.print_commands_and_arguments {
                 let expanded_right = expansion::basic_expand_word(shell, right).await?;
-                shell.trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))
+                    .await?;
             }
 
             pattern.exactly_matches(s.as_str())
@@ -368,11 +407,15 @@ async fn apply_binary_predicate(
             let pattern = expansion::basic_expand_pattern(shell, right)
                 .await?
                 .set_extended_globbing(shell.options.extended_globbing)
+                .set_locale(shell.locale())
+                .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
                 .set_case_insensitive(shell.options.case_insensitive_conditionals);
 
             if shell.options.print_commands_and_arguments {
                 let expanded_right = expansion::basic_expand_word(shell, right).await?;
-                shell.trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))?;
+                shell
+                    .trace_command(std::format!("[[ {s} {op} {expanded_right} ]]"))
+                    .await?;
             }
 
             let eq = pattern.exactly_matches(s.as_str())?;
@@ -389,21 +432,39 @@ pub(crate) fn apply_binary_predicate_to_strs(
 ) -> Result<bool, error::Error> {
     match op {
         ast::BinaryPredicate::FilesReferToSameDeviceAndInodeNumbers => {
-            error::unimp("extended test binary predicate FilesReferToSameDeviceAndInodeNumbers")
+            let left = shell.get_absolute_path(Path::new(left)).metadata();
+            let right = shell.get_absolute_path(Path::new(right)).metadata();
+
+            match (left, right) {
+                (Ok(left), Ok(right)) => Ok(left.dev() == right.dev() && left.ino() == right.ino()),
+                _ => Ok(false),
+            }
         }
         ast::BinaryPredicate::LeftFileIsNewerOrExistsWhenRightDoesNot => {
-            error::unimp("extended test binary predicate LeftFileIsNewerOrExistsWhenRightDoesNot")
+            let left = get_file_modification_time(shell, left);
+            let right = get_file_modification_time(shell, right);
+
+            match (left, right) {
+                (Some(left), Some(right)) => Ok(left > right),
+                (Some(_), None) => Ok(true),
+                _ => Ok(false),
+            }
+        }
+        ast::BinaryPredicate::LeftFileIsOlderOrDoesNotExistWhenRightDoes => {
+            let left = get_file_modification_time(shell, left);
+            let right = get_file_modification_time(shell, right);
+
+            match (left, right) {
+                (Some(left), Some(right)) => Ok(left < right),
+                (None, Some(_)) => Ok(true),
+                _ => Ok(false),
+            }
         }
-        ast::BinaryPredicate::LeftFileIsOlderOrDoesNotExistWhenRightDoes => error::unimp(
-            "extended test binary predicate LeftFileIsOlderOrDoesNotExistWhenRightDoes",
-        ),
         ast::BinaryPredicate::LeftSortsBeforeRight => {
-            // TODO: According to docs, should be lexicographical order of the current locale.
-            Ok(left < right)
+            Ok(shell.locale().compare(left, right).is_lt())
         }
         ast::BinaryPredicate::LeftSortsAfterRight => {
-            // TODO: According to docs, should be lexicographical order of the current locale.
-            Ok(left > right)
+            Ok(shell.locale().compare(left, right).is_gt())
         }
         ast::BinaryPredicate::ArithmeticEqualTo => Ok(apply_binary_arithmetic_predicate(
             left,
@@ -436,6 +497,8 @@ pub(crate) fn apply_binary_predicate_to_strs(
         ast::BinaryPredicate::StringExactlyMatchesPattern => {
             let pattern = patterns::Pattern::from(right)
                 .set_extended_globbing(shell.options.extended_globbing)
+                .set_locale(shell.locale())
+                .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
                 .set_case_insensitive(shell.options.case_insensitive_conditionals);
 
             pattern.exactly_matches(left)
@@ -443,6 +506,8 @@ pub(crate) fn apply_binary_predicate_to_strs(
         ast::BinaryPredicate::StringDoesNotExactlyMatchPattern => {
             let pattern = patterns::Pattern::from(right)
                 .set_extended_globbing(shell.options.extended_globbing)
+                .set_locale(shell.locale())
+                .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
                 .set_case_insensitive(shell.options.case_insensitive_conditionals);
 
             let eq = pattern.exactly_matches(left)?;
@@ -462,3 +527,13 @@ fn apply_binary_arithmetic_predicate(left: &str, right: &str, op: fn(i64, i64) -
         false
     }
 }
+
+/// Returns the last modification time of the given file, following symbolic links;
+/// returns `None` if the file doesn't exist or the time can't be retrieved.
+fn get_file_modification_time(shell: &Shell, path: &str) -> Option<std::time::SystemTime> {
+    shell
+        .get_absolute_path(Path::new(path))
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
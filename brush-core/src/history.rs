@@ -0,0 +1,816 @@
+//! Command history, along with metadata about each command's execution.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use crate::{error, sys};
+
+/// Prefix identifying the metadata we append to timestamp lines in the history file.
+const METADATA_MARKER: &str = "brush:";
+
+/// Escape sequence used for embedded newlines by history files written by older versions
+/// of brush (via reedline's file-backed history).
+const LEGACY_NEWLINE_ESCAPE: &str = "<\\n>";
+
+/// An item in the shell's command history.
+#[derive(Clone, Debug, Default)]
+pub struct Item {
+    /// Unique identifier of the item within its history.
+    pub id: i64,
+    /// The command line, as entered.
+    pub command_line: String,
+    /// The time at which execution of the command started.
+    pub start_time: Option<SystemTime>,
+    /// How long the command took to execute.
+    pub duration: Option<Duration>,
+    /// The exit status of the command.
+    pub exit_status: Option<u8>,
+    /// The working directory of the shell when the command was run.
+    pub working_dir: Option<PathBuf>,
+    /// Identifies the shell session in which the command was run.
+    pub session_id: Option<u64>,
+}
+
+impl Item {
+    /// Returns a new history item for the given command line, with no metadata.
+    ///
+    /// # Arguments
+    ///
+    /// * `command_line` - The command line.
+    pub fn new<S: Into<String>>(command_line: S) -> Self {
+        Self {
+            command_line: command_line.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Filter on the command line of history items.
+#[derive(Clone, Debug)]
+pub enum CommandLineFilter {
+    /// The command line must start with the given text.
+    Prefix(String),
+    /// The command line must contain the given text.
+    Substring(String),
+    /// The command line must exactly match the given text.
+    Exact(String),
+}
+
+/// Filter on the working directory of history items.
+#[derive(Clone, Debug)]
+pub enum DirectoryFilter {
+    /// The command must have been run in the given directory.
+    Exact(PathBuf),
+    /// The command must have been run in the given directory or one of its descendants.
+    Within(PathBuf),
+}
+
+/// Filter on the exit status of history items.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatusFilter {
+    /// The command must have exited with status 0.
+    Succeeded,
+    /// The command must have exited with a non-zero status.
+    Failed,
+}
+
+/// A query against a shell's command history.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    /// Optionally filters on the command line.
+    pub command_line: Option<CommandLineFilter>,
+    /// Optionally filters on the working directory the command was run in.
+    pub working_dir: Option<DirectoryFilter>,
+    /// Optionally filters on the exit status of the command.
+    pub exit_status: Option<ExitStatusFilter>,
+    /// Optionally filters on the session in which the command was run.
+    pub session_id: Option<u64>,
+    /// If specified, only items with an ID greater than this one are included.
+    pub after_id: Option<i64>,
+    /// If specified, only items with an ID less than this one are included.
+    pub before_id: Option<i64>,
+    /// Whether to return the most recent items first.
+    pub newest_first: bool,
+    /// Optionally limits the number of items returned.
+    pub limit: Option<usize>,
+}
+
+impl Query {
+    /// Checks whether the given item satisfies this query's filters. Note that ID ranges
+    /// and limits are not considered.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The item to check.
+    pub fn matches(&self, item: &Item) -> bool {
+        let command_line_matches = match &self.command_line {
+            Some(CommandLineFilter::Prefix(s)) => item.command_line.starts_with(s.as_str()),
+            Some(CommandLineFilter::Substring(s)) => item.command_line.contains(s.as_str()),
+            Some(CommandLineFilter::Exact(s)) => item.command_line == *s,
+            None => true,
+        };
+
+        let working_dir_matches = match &self.working_dir {
+            Some(DirectoryFilter::Exact(dir)) => item.working_dir.as_ref() == Some(dir),
+            Some(DirectoryFilter::Within(dir)) => item
+                .working_dir
+                .as_ref()
+                .is_some_and(|item_dir| item_dir.starts_with(dir)),
+            None => true,
+        };
+
+        let exit_status_matches = match self.exit_status {
+            Some(ExitStatusFilter::Succeeded) => item.exit_status == Some(0),
+            Some(ExitStatusFilter::Failed) => item.exit_status.is_some_and(|status| status != 0),
+            None => true,
+        };
+
+        let session_matches = self
+            .session_id
+            .map_or(true, |session_id| item.session_id == Some(session_id));
+
+        command_line_matches && working_dir_matches && exit_status_matches && session_matches
+    }
+}
+
+/// A shell's command history.
+#[derive(Clone)]
+pub struct History {
+    /// The items in the history, oldest first. Shared with clones of the history (e.g.,
+    /// in subshells) until one of them is modified.
+    items: Arc<Vec<Item>>,
+    /// The ID to assign to the next item added.
+    next_id: i64,
+    /// Identifies the current shell session.
+    session_id: u64,
+    /// Path to the file backing the history, if there is one.
+    file_path: Option<PathBuf>,
+    /// Index of the first item not yet written to the history file.
+    first_unsaved_index: usize,
+    /// Length (in bytes) of the history file, as of when we last read or wrote it.
+    file_len: u64,
+    /// Number of items in the history file, as of when we last read or wrote it.
+    file_item_count: usize,
+    /// Items appended to the history file by other shell sessions since we loaded it; these
+    /// aren't visible in this session, but need to be preserved when rewriting the file.
+    foreign_items: Vec<Item>,
+    /// Whether the lines following a timestamp line in the history file form a single
+    /// (multi-line) entry.
+    multiline_entries: bool,
+    /// Whether the history file needs to be rewritten in full (e.g., after deletions).
+    needs_rewrite: bool,
+    /// ID of the most recently added item, if its execution hasn't yet completed.
+    pending_item_id: Option<i64>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl History {
+    /// Returns a new, empty history not backed by any file.
+    pub fn new() -> Self {
+        Self {
+            items: Arc::new(vec![]),
+            next_id: 0,
+            session_id: rand::random(),
+            file_path: None,
+            first_unsaved_index: 0,
+            file_len: 0,
+            file_item_count: 0,
+            foreign_items: vec![],
+            multiline_entries: false,
+            needs_rewrite: false,
+            pending_item_id: None,
+        }
+    }
+
+    /// Loads history from the given file; later changes will be saved back to it. It's not
+    /// an error for the file to not yet exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - Path to the history file.
+    /// * `multiline_entries` - Whether the file may contain multi-line entries, i.e., whether
+    ///   all lines following a timestamp line (up until the next one) form a single entry.
+    ///   This is only the case when multi-line commands are saved with embedded newlines.
+    pub fn load(file_path: &Path, multiline_entries: bool) -> Result<Self, error::Error> {
+        let contents = match std::fs::File::open(file_path) {
+            Ok(mut file) => {
+                // Make sure we don't observe another session's partially written update.
+                let _lock = sys::fs::lock_file_exclusive(&file)?;
+                let mut contents = String::new();
+                file.read_to_string(&mut contents)?;
+                contents
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut history = Self::new();
+        let items = parse_history_file(contents.as_str(), multiline_entries);
+
+        history.next_id = i64::try_from(items.len())?;
+        history.first_unsaved_index = items.len();
+        history.file_len = u64::try_from(contents.len())?;
+        history.file_item_count = items.len();
+        history.items = Arc::new(items);
+        history.file_path = Some(file_path.to_owned());
+        history.multiline_entries = multiline_entries;
+
+        Ok(history)
+    }
+
+    /// Returns the identifier of the current shell session.
+    pub fn session_id(&self) -> u64 {
+        self.session_id
+    }
+
+    /// Returns the number of items in the history.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns whether the history is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns an iterator over the items in the history, oldest first.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Item> {
+        self.items.iter()
+    }
+
+    /// Adds an item to the history, returning its assigned ID. The item is treated as
+    /// pending until [`History::complete_pending_item`] is called to record the outcome
+    /// of its execution.
+    ///
+    /// # Arguments
+    ///
+    /// * `item` - The item to add; its ID is ignored.
+    pub fn add(&mut self, mut item: Item) -> i64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        item.id = id;
+        if item.session_id.is_none() {
+            item.session_id = Some(self.session_id);
+        }
+
+        Arc::make_mut(&mut self.items).push(item);
+        self.pending_item_id = Some(id);
+
+        id
+    }
+
+    /// Records the outcome of executing the most recently added item, if it's still pending.
+    ///
+    /// # Arguments
+    ///
+    /// * `exit_status` - The exit status of the command.
+    /// * `duration` - How long the command took to execute.
+    pub fn complete_pending_item(&mut self, exit_status: u8, duration: Duration) {
+        if let Some(id) = self.pending_item_id.take() {
+            self.update(id, |item| {
+                item.exit_status = Some(exit_status);
+                item.duration = Some(duration);
+            });
+        }
+    }
+
+    /// Retrieves the item with the given ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the item.
+    pub fn get(&self, id: i64) -> Option<&Item> {
+        self.index_of(id).map(|index| &self.items[index])
+    }
+
+    /// Updates the item with the given ID, returning whether it was found.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the item.
+    /// * `updater` - Function to apply to the item.
+    pub fn update(&mut self, id: i64, updater: impl FnOnce(&mut Item)) -> bool {
+        let Some(index) = self.index_of(id) else {
+            return false;
+        };
+
+        updater(&mut Arc::make_mut(&mut self.items)[index]);
+
+        // Items already written to the file can only be updated by rewriting it.
+        if index < self.first_unsaved_index {
+            self.needs_rewrite = true;
+        }
+
+        true
+    }
+
+    /// Removes the item with the given ID, returning whether it was found.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the item.
+    pub fn delete(&mut self, id: i64) -> bool {
+        let Some(index) = self.index_of(id) else {
+            return false;
+        };
+
+        Arc::make_mut(&mut self.items).remove(index);
+
+        if index < self.first_unsaved_index {
+            self.first_unsaved_index -= 1;
+            self.needs_rewrite = true;
+        }
+
+        true
+    }
+
+    /// Discards the oldest items in the history, as needed to keep at most the given number
+    /// of items. The history file is unaffected.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_items` - The maximum number of items to retain.
+    pub fn truncate(&mut self, max_items: usize) {
+        let excess = self.items.len().saturating_sub(max_items);
+        if excess > 0 {
+            Arc::make_mut(&mut self.items).drain(..excess);
+            self.first_unsaved_index = self.first_unsaved_index.saturating_sub(excess);
+        }
+    }
+
+    /// Removes all items from the history.
+    pub fn clear(&mut self) {
+        self.items = Arc::new(vec![]);
+        self.first_unsaved_index = 0;
+        self.needs_rewrite = true;
+        self.pending_item_id = None;
+    }
+
+    /// Searches the history, returning the matching items.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The query to evaluate.
+    pub fn search(&self, query: &Query) -> Vec<&Item> {
+        let matching = self.items.iter().filter(|item| {
+            query.after_id.map_or(true, |id| item.id > id)
+                && query.before_id.map_or(true, |id| item.id < id)
+                && query.matches(item)
+        });
+
+        let limit = query.limit.unwrap_or(usize::MAX);
+        if query.newest_first {
+            matching.rev().take(limit).collect()
+        } else {
+            matching.take(limit).collect()
+        }
+    }
+
+    /// Writes any unsaved changes to the history file, if there is one. The file may be
+    /// shared with other shell sessions: new items are appended to it, and items appended by
+    /// other sessions are preserved even if the file needs to be rewritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_file_items` - If specified, the maximum number of items to retain in the file;
+    ///   the oldest items are discarded as needed.
+    pub fn save(&mut self, max_file_items: Option<usize>) -> Result<(), error::Error> {
+        let Some(file_path) = &self.file_path else {
+            return Ok(());
+        };
+
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(file_path)?;
+        let _lock = sys::fs::lock_file_exclusive(&file)?;
+
+        self.read_foreign_items(&mut file)?;
+
+        if self.needs_rewrite {
+            // Our items replace whatever we last saw in the file; anything other sessions
+            // have appended since then goes after them.
+            let items: Vec<&Item> = self.items.iter().chain(self.foreign_items.iter()).collect();
+            let retained = retain_newest(items.as_slice(), max_file_items);
+            rewrite_file(&mut file, retained)?;
+
+            self.file_item_count = retained.len();
+            let foreign_excess = self.foreign_items.len().saturating_sub(retained.len());
+            self.foreign_items.drain(..foreign_excess);
+        } else {
+            let mut buffer = String::new();
+            for item in &self.items[self.first_unsaved_index..] {
+                format_item(&mut buffer, item)?;
+            }
+
+            file.seek(SeekFrom::End(0))?;
+            file.write_all(buffer.as_bytes())?;
+            self.file_item_count += self.items.len() - self.first_unsaved_index;
+
+            // Trim the oldest items from the file if it's grown too large.
+            if max_file_items.is_some_and(|max| self.file_item_count > max) {
+                let mut contents = String::new();
+                file.seek(SeekFrom::Start(0))?;
+                file.read_to_string(&mut contents)?;
+
+                let items = parse_history_file(contents.as_str(), self.multiline_entries);
+                let items: Vec<&Item> = items.iter().collect();
+                let retained = retain_newest(items.as_slice(), max_file_items);
+                rewrite_file(&mut file, retained)?;
+
+                self.file_item_count = retained.len();
+                let foreign_excess = self.foreign_items.len().saturating_sub(retained.len());
+                self.foreign_items.drain(..foreign_excess);
+            }
+        }
+
+        self.file_len = file.metadata()?.len();
+        self.first_unsaved_index = self.items.len();
+        self.needs_rewrite = false;
+
+        Ok(())
+    }
+
+    /// Reads any items appended to the history file by other sessions since we last read
+    /// or wrote it.
+    fn read_foreign_items(&mut self, file: &mut std::fs::File) -> Result<(), error::Error> {
+        let file_len = file.metadata()?.len();
+
+        if file_len < self.file_len {
+            // Another session has rewritten the file; whatever it contains now, it's not
+            // something we can reconcile with what we last saw.
+            let mut contents = String::new();
+            file.read_to_string(&mut contents)?;
+            self.file_item_count =
+                parse_history_file(contents.as_str(), self.multiline_entries).len();
+            self.foreign_items.clear();
+        } else if file_len > self.file_len {
+            let mut appended = String::new();
+            file.seek(SeekFrom::Start(self.file_len))?;
+            file.read_to_string(&mut appended)?;
+
+            let appended_items = parse_history_file(appended.as_str(), self.multiline_entries);
+            self.file_item_count += appended_items.len();
+            self.foreign_items.extend(appended_items);
+        }
+
+        Ok(())
+    }
+
+    fn index_of(&self, id: i64) -> Option<usize> {
+        // IDs are assigned in increasing order.
+        self.items.binary_search_by_key(&id, |item| item.id).ok()
+    }
+}
+
+/// Returns the newest items in the given list, keeping at most the given number of them.
+fn retain_newest<T>(items: &[T], max_items: Option<usize>) -> &[T] {
+    let excess = max_items.map_or(0, |max| items.len().saturating_sub(max));
+    &items[excess..]
+}
+
+/// Replaces the contents of the given history file with the given items.
+fn rewrite_file(file: &mut std::fs::File, items: &[&Item]) -> Result<(), error::Error> {
+    let mut buffer = String::new();
+    for item in items {
+        format_item(&mut buffer, item)?;
+    }
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(buffer.as_bytes())?;
+
+    Ok(())
+}
+
+/// Parses the contents of a history file. The format is compatible with bash's: each
+/// entry may be preceded by a `#<seconds-since-epoch>` timestamp line. If multi-line
+/// entries are enabled, all lines following a timestamp line (up until the next one) form
+/// a single entry; otherwise, each line is its own entry, since the file may be shared
+/// with shells that don't write timestamps. We append the rest of our metadata to the
+/// timestamp line, which bash ignores but preserves.
+fn parse_history_file(contents: &str, multiline_entries: bool) -> Vec<Item> {
+    let mut items = vec![];
+    let mut current: Option<Item> = None;
+
+    for line in contents.lines() {
+        if let Some(item) = parse_timestamp_line(line) {
+            items.extend(current.replace(item));
+        } else if let Some(item) = current
+            .as_mut()
+            .filter(|item| multiline_entries || item.command_line.is_empty())
+        {
+            if !item.command_line.is_empty() {
+                item.command_line.push('\n');
+            }
+            item.command_line.push_str(line);
+        } else {
+            items.extend(current.take());
+            if !line.is_empty() {
+                items.push(Item::new(line.replace(LEGACY_NEWLINE_ESCAPE, "\n")));
+            }
+        }
+    }
+
+    items.extend(current);
+
+    // Drop any entries left empty (e.g., by a trailing timestamp line) and assign IDs.
+    items.retain(|item| !item.command_line.is_empty());
+    for (id, item) in (0..).zip(items.iter_mut()) {
+        item.id = id;
+    }
+
+    items
+}
+
+fn parse_timestamp_line(line: &str) -> Option<Item> {
+    let line = line.strip_prefix('#')?;
+    let (timestamp, metadata) = line.split_once(' ').unwrap_or((line, ""));
+
+    if timestamp.is_empty() || !timestamp.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let mut item = Item {
+        start_time: timestamp
+            .parse()
+            .ok()
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+        ..Item::default()
+    };
+
+    if let Some(metadata) = metadata.trim().strip_prefix(METADATA_MARKER) {
+        for field in metadata.split(';') {
+            let Some((key, value)) = field.split_once('=') else {
+                continue;
+            };
+
+            match key {
+                "cwd" => item.working_dir = Some(PathBuf::from(decode_metadata_value(value))),
+                "status" => item.exit_status = value.parse().ok(),
+                "duration_ms" => item.duration = value.parse().ok().map(Duration::from_millis),
+                "session" => item.session_id = value.parse().ok(),
+                _ => (),
+            }
+        }
+    }
+
+    Some(item)
+}
+
+fn format_item(output: &mut String, item: &Item) -> Result<(), std::fmt::Error> {
+    use std::fmt::Write;
+
+    // Items without a start time get no timestamp line (and hence no metadata), rather
+    // than one claiming they were run at the epoch.
+    let Some(secs) = item
+        .start_time
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+    else {
+        writeln!(output, "{}", item.command_line)?;
+        return Ok(());
+    };
+
+    write!(output, "#{secs} {METADATA_MARKER}")?;
+
+    let mut fields = vec![];
+    if let Some(working_dir) = &item.working_dir {
+        fields.push(std::format!(
+            "cwd={}",
+            encode_metadata_value(working_dir.to_string_lossy().as_ref())
+        ));
+    }
+    if let Some(exit_status) = item.exit_status {
+        fields.push(std::format!("status={exit_status}"));
+    }
+    if let Some(duration) = item.duration {
+        fields.push(std::format!("duration_ms={}", duration.as_millis()));
+    }
+    if let Some(session_id) = item.session_id {
+        fields.push(std::format!("session={session_id}"));
+    }
+
+    writeln!(output, "{}", fields.join(";"))?;
+    writeln!(output, "{}", item.command_line)?;
+
+    Ok(())
+}
+
+fn encode_metadata_value(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' => encoded.push_str("%25"),
+            ';' => encoded.push_str("%3B"),
+            '\n' => encoded.push_str("%0A"),
+            '\r' => encoded.push_str("%0D"),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+fn decode_metadata_value(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut remaining = value;
+
+    while let Some(index) = remaining.find('%') {
+        decoded.push_str(&remaining[..index]);
+
+        let code = remaining
+            .get(index + 1..index + 3)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        if let Some(code) = code {
+            decoded.push(char::from(code));
+            remaining = &remaining[index + 3..];
+        } else {
+            decoded.push('%');
+            remaining = &remaining[index + 1..];
+        }
+    }
+
+    decoded.push_str(remaining);
+    decoded
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn parse_plain_history_file() {
+        let items = parse_history_file(
+            "echo hi\nls -l\nfor x in 1; do<\\n>  echo $x<\\n>done\n",
+            true,
+        );
+
+        let command_lines: Vec<_> = items.iter().map(|i| i.command_line.as_str()).collect();
+        assert_eq!(
+            command_lines,
+            ["echo hi", "ls -l", "for x in 1; do\n  echo $x\ndone"]
+        );
+        assert!(items.iter().all(|i| i.start_time.is_none()));
+    }
+
+    #[test]
+    fn round_trip_history_metadata() -> Result<()> {
+        let item = Item {
+            id: 0,
+            command_line: "while true; do\n  sleep 1\ndone".into(),
+            start_time: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+            duration: Some(Duration::from_millis(1500)),
+            exit_status: Some(130),
+            working_dir: Some(PathBuf::from("/tmp/odd;dir%name")),
+            session_id: Some(42),
+        };
+
+        let mut contents = String::from("echo legacy\n");
+        format_item(&mut contents, &item)?;
+
+        let items = parse_history_file(contents.as_str(), true);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].command_line, "echo legacy");
+
+        let parsed = &items[1];
+        assert_eq!(parsed.id, 1);
+        assert_eq!(parsed.command_line, item.command_line);
+        assert_eq!(parsed.start_time, item.start_time);
+        assert_eq!(parsed.duration, item.duration);
+        assert_eq!(parsed.exit_status, item.exit_status);
+        assert_eq!(parsed.working_dir, item.working_dir);
+        assert_eq!(parsed.session_id, item.session_id);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_history_file_without_multiline_entries() {
+        let contents = "#1700000000\necho one\necho two\n#1700000001\nfor x in 1; do\n";
+        let items = parse_history_file(contents, false);
+
+        let command_lines: Vec<_> = items.iter().map(|i| i.command_line.as_str()).collect();
+        assert_eq!(command_lines, ["echo one", "echo two", "for x in 1; do"]);
+        assert!(items[0].start_time.is_some());
+        assert!(items[1].start_time.is_none());
+        assert!(items[2].start_time.is_some());
+    }
+
+    #[test]
+    fn format_item_without_start_time() -> Result<()> {
+        let mut contents = String::new();
+        format_item(&mut contents, &Item::new("echo hi"))?;
+        assert_eq!(contents, "echo hi\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn search_history() {
+        let mut history = History::new();
+        for (command_line, dir, status) in [
+            ("make", "/src", 2),
+            ("make test", "/src", 0),
+            ("ls", "/tmp", 0),
+        ] {
+            history.add(Item {
+                working_dir: Some(PathBuf::from(dir)),
+                ..Item::new(command_line)
+            });
+            history.complete_pending_item(status, Duration::ZERO);
+        }
+
+        let query = Query {
+            working_dir: Some(DirectoryFilter::Exact(PathBuf::from("/src"))),
+            exit_status: Some(ExitStatusFilter::Succeeded),
+            ..Query::default()
+        };
+        let results: Vec<_> = history.search(&query).iter().map(|i| i.id).collect();
+        assert_eq!(results, [1]);
+
+        let query = Query {
+            command_line: Some(CommandLineFilter::Prefix("make".into())),
+            newest_first: true,
+            limit: Some(1),
+            ..Query::default()
+        };
+        let results: Vec<_> = history.search(&query).iter().map(|i| i.id).collect();
+        assert_eq!(results, [1]);
+    }
+
+    fn command_lines(history: &History) -> Vec<&str> {
+        history.iter().map(|i| i.command_line.as_str()).collect()
+    }
+
+    fn temp_history_file_path() -> PathBuf {
+        std::env::temp_dir().join(std::format!("brush-history-test-{}", rand::random::<u64>()))
+    }
+
+    #[test]
+    fn save_preserves_other_sessions_items() -> Result<()> {
+        let file_path = temp_history_file_path();
+
+        let mut first = History::load(&file_path, false)?;
+        let mut second = History::load(&file_path, false)?;
+
+        let id = first.add(Item::new("first 1"));
+        first.add(Item::new("first 2"));
+        second.add(Item::new("second 1"));
+        first.save(None)?;
+        second.save(None)?;
+
+        // Deleting a saved item forces a rewrite of the file, which mustn't lose the items
+        // the other session appended after we last saved.
+        first.delete(id);
+        first.add(Item::new("first 3"));
+        second.add(Item::new("second 2"));
+        second.save(None)?;
+        first.save(None)?;
+
+        let reloaded = History::load(&file_path, false)?;
+        std::fs::remove_file(&file_path)?;
+
+        assert_eq!(
+            command_lines(&reloaded),
+            ["first 2", "first 3", "second 1", "second 2"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn save_trims_history_file() -> Result<()> {
+        let file_path = temp_history_file_path();
+
+        let mut history = History::load(&file_path, false)?;
+        for i in 0..5 {
+            history.add(Item::new(std::format!("echo {i}")));
+            history.save(Some(3))?;
+        }
+
+        // Only the file is trimmed; the in-memory history is trimmed separately.
+        assert_eq!(history.len(), 5);
+        history.truncate(2);
+        assert_eq!(command_lines(&history), ["echo 3", "echo 4"]);
+
+        let reloaded = History::load(&file_path, false)?;
+        std::fs::remove_file(&file_path)?;
+
+        assert_eq!(command_lines(&reloaded), ["echo 2", "echo 3", "echo 4"]);
+
+        Ok(())
+    }
+}
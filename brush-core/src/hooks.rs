@@ -0,0 +1,132 @@
+//! Hooks invoked by the shell before and after executing commands, and when its working
+//! directory changes.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::Shell;
+
+/// An event for which hooks are invoked.
+#[derive(Clone, Debug)]
+pub enum HookEvent {
+    /// A command line has been read and is about to be executed.
+    PreExec {
+        /// The command line.
+        command_line: String,
+    },
+    /// A command line has been executed.
+    PostExec {
+        /// The command line.
+        command_line: String,
+        /// The exit status of the command line.
+        exit_status: u8,
+        /// How long the command line took to execute.
+        duration: Duration,
+    },
+    /// The working directory of the shell has changed.
+    DirectoryChange {
+        /// The previous working directory.
+        old_dir: PathBuf,
+        /// The new working directory.
+        new_dir: PathBuf,
+    },
+}
+
+impl HookEvent {
+    /// Returns the name of the array variable that lists the shell functions to invoke for
+    /// this event.
+    pub const fn functions_var_name(&self) -> &'static str {
+        match self {
+            HookEvent::PreExec { .. } => "BRUSH_PREEXEC_FUNCTIONS",
+            HookEvent::PostExec { .. } => "BRUSH_POSTEXEC_FUNCTIONS",
+            HookEvent::DirectoryChange { .. } => "BRUSH_CHPWD_FUNCTIONS",
+        }
+    }
+
+    /// Returns the arguments to pass to shell functions invoked for this event.
+    pub(crate) fn function_args(&self) -> Vec<String> {
+        match self {
+            HookEvent::PreExec { command_line } => vec![command_line.clone()],
+            HookEvent::PostExec {
+                command_line,
+                exit_status,
+                duration,
+            } => vec![
+                command_line.clone(),
+                exit_status.to_string(),
+                duration.as_millis().to_string(),
+            ],
+            HookEvent::DirectoryChange { old_dir, new_dir } => vec![
+                old_dir.to_string_lossy().to_string(),
+                new_dir.to_string_lossy().to_string(),
+            ],
+        }
+    }
+}
+
+/// A hook implemented in Rust.
+pub type HookFn = dyn Fn(&mut Shell, &HookEvent) + Send + Sync;
+
+/// Hooks registered by code embedding the shell.
+#[derive(Clone, Default)]
+pub struct HookRegistry {
+    hooks: Vec<Arc<HookFn>>,
+}
+
+impl HookRegistry {
+    /// Registers a hook to be invoked for all events.
+    ///
+    /// # Arguments
+    ///
+    /// * `hook` - The hook to register.
+    pub fn register<F>(&mut self, hook: F)
+    where
+        F: Fn(&mut Shell, &HookEvent) + Send + Sync + 'static,
+    {
+        self.hooks.push(Arc::new(hook));
+    }
+
+    /// Returns the registered hooks.
+    pub(crate) fn hooks(&self) -> Vec<Arc<HookFn>> {
+        self.hooks.clone()
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn, clippy::unwrap_in_result)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use std::sync::Mutex;
+
+    #[tokio::test]
+    async fn test_directory_change_hooks() -> Result<()> {
+        let mut shell = Shell::new(&crate::CreateOptions::default()).await?;
+
+        let new_dirs = Arc::new(Mutex::new(vec![]));
+        let hook_new_dirs = new_dirs.clone();
+        shell.hooks.register(move |_shell, event| {
+            if let HookEvent::DirectoryChange { new_dir, .. } = event {
+                if let Ok(mut new_dirs) = hook_new_dirs.lock() {
+                    new_dirs.push(new_dir.clone());
+                }
+            }
+        });
+
+        let temp_dir = std::env::temp_dir().canonicalize()?;
+        shell
+            .set_working_dir_and_run_hooks(&temp_dir, false)
+            .await?;
+        shell
+            .set_working_dir_and_run_hooks(&temp_dir, false)
+            .await?;
+
+        let new_dirs = new_dirs
+            .lock()
+            .map_err(|_| anyhow::anyhow!("lock poisoned"))?;
+        assert_eq!(new_dirs.as_slice(), &[temp_dir]);
+
+        Ok(())
+    }
+}
@@ -17,7 +17,7 @@ use crate::shell::Shell;
 use crate::variables::{
     ArrayLiteral, ShellValue, ShellValueLiteral, ShellValueUnsetType, ShellVariable,
 };
-use crate::{error, expansion, extendedtests, jobs, openfiles, processes, sys, traps};
+use crate::{env, error, expansion, extendedtests, jobs, openfiles, processes, sys, traps};
 
 /// Encapsulates the result of executing a command.
 #[derive(Debug, Default)]
@@ -192,8 +192,8 @@ impl Execute for ast::CompoundList {
                 result = ao_list.execute(shell, params).await?;
             }
 
-            // Check for early return.
-            if result.return_from_function_or_script {
+            // Check for early return or exit.
+            if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
 
@@ -243,9 +243,17 @@ impl Execute for ast::AndOrList {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let mut result = self.first.execute(shell, params).await?;
-
-        for next_ao in &self.additional {
+        // All but the last pipeline in the list are executed with errexit suppressed.
+        let mut result = execute_pipeline_in_and_or_list(
+            &self.first,
+            !self.additional.is_empty(),
+            shell,
+            params,
+        )
+        .await?;
+        let mut last_executed = &self.first;
+
+        for (index, next_ao) in self.additional.iter().enumerate() {
             // Check for exit/return
             if result.exit_shell || result.return_from_function_or_script {
                 break;
@@ -274,13 +282,78 @@ impl Execute for ast::AndOrList {
                 continue;
             }
 
-            result = pipeline.execute(shell, params).await?;
+            let is_last = index + 1 == self.additional.len();
+            result = execute_pipeline_in_and_or_list(pipeline, !is_last, shell, params).await?;
+            last_executed = pipeline;
+        }
+
+        // Only a failure of the last pipeline in the list is subject to errexit.
+        let last_in_list = self.additional.last().map_or(&self.first, |ao| match ao {
+            ast::AndOr::And(p) | ast::AndOr::Or(p) => p,
+        });
+        if std::ptr::eq(last_executed, last_in_list)
+            && should_exit_on_failure(shell, last_executed, &result)
+        {
+            result.exit_shell = true;
         }
 
         Ok(result)
     }
 }
 
+async fn execute_pipeline_in_and_or_list(
+    pipeline: &ast::Pipeline,
+    suppress_errexit: bool,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    if !suppress_errexit {
+        return pipeline.execute(shell, params).await;
+    }
+
+    shell.errexit_suppression_depth += 1;
+    let result = pipeline.execute(shell, params).await;
+    shell.errexit_suppression_depth -= 1;
+
+    result
+}
+
+/// Checks whether the given result of executing a pipeline should cause the shell to exit,
+/// per `set -e`.
+fn should_exit_on_failure(
+    shell: &Shell,
+    pipeline: &ast::Pipeline,
+    result: &ExecutionResult,
+) -> bool {
+    if !shell.options.exit_on_nonzero_command_exit
+        || shell.errexit_suppression_depth > 0
+        || result.is_success()
+        || pipeline.bang
+    {
+        return false;
+    }
+
+    // Control flow (e.g., `return 1`) takes care of itself.
+    if result.exit_shell
+        || result.return_from_function_or_script
+        || result.break_loop.is_some()
+        || result.continue_loop.is_some()
+    {
+        return false;
+    }
+
+    // A compound command (other than a subshell or arithmetic command) that fails must have
+    // done so because of a command whose failure was ignored; anything else would have
+    // already caused an exit.
+    match pipeline.seq.as_slice() {
+        [ast::Command::Compound(compound, _)] => matches!(
+            compound,
+            ast::CompoundCommand::Subshell(_) | ast::CompoundCommand::Arithmetic(_)
+        ),
+        _ => true,
+    }
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::Pipeline {
     async fn execute(
@@ -288,12 +361,23 @@ impl Execute for ast::Pipeline {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        // Spawn all the processes required for the pipeline, connecting outputs/inputs with pipes
-        // as needed.
-        let spawn_results = spawn_pipeline_processes(self, shell, params).await?;
+        // The failure of any command in a negated pipeline is exempt from errexit.
+        if self.bang {
+            shell.errexit_suppression_depth += 1;
+        }
 
-        // Wait for the processes.
-        let mut result = wait_for_pipeline_processes(self, spawn_results, shell).await?;
+        let result = execute_pipeline_commands(self, shell, params).await;
+
+        if self.bang {
+            shell.errexit_suppression_depth -= 1;
+        }
+
+        let mut result = result?;
+
+        // Exiting from a command in a multi-command pipeline only exits its subshell.
+        if self.seq.len() > 1 {
+            result.exit_shell = false;
+        }
 
         // Invert the exit code if requested.
         if self.bang {
@@ -306,6 +390,19 @@ impl Execute for ast::Pipeline {
     }
 }
 
+async fn execute_pipeline_commands(
+    pipeline: &ast::Pipeline,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    // Spawn all the processes required for the pipeline, connecting outputs/inputs with pipes
+    // as needed.
+    let spawn_results = spawn_pipeline_processes(pipeline, shell, params).await?;
+
+    // Wait for the processes.
+    wait_for_pipeline_processes(pipeline, spawn_results, shell).await
+}
+
 async fn spawn_pipeline_processes(
     pipeline: &ast::Pipeline,
     shell: &mut Shell,
@@ -363,6 +460,7 @@ async fn wait_for_pipeline_processes(
 ) -> Result<ExecutionResult, error::Error> {
     let mut result = ExecutionResult::success();
     let mut stopped_children = vec![];
+    let mut exit_codes = vec![];
 
     while let Some(child) = process_spawn_results.pop_front() {
         match child.wait(!stopped_children.is_empty()).await? {
@@ -377,6 +475,30 @@ async fn wait_for_pipeline_processes(
                 stopped_children.push(jobs::JobTask::External(child));
             }
         }
+
+        exit_codes.push(result.exit_code);
+    }
+
+    // Publish the exit codes of all commands in the pipeline.
+    shell.env.update_or_add(
+        "PIPESTATUS",
+        ShellValueLiteral::Array(ArrayLiteral(
+            exit_codes
+                .iter()
+                .map(|exit_code| (None, exit_code.to_string()))
+                .collect(),
+        )),
+        |_| Ok(()),
+        EnvironmentLookup::Anywhere,
+        EnvironmentScope::Global,
+    )?;
+
+    // With pipefail, the pipeline's status is that of the last command to fail (if any).
+    if shell.options.return_first_failure_from_pipeline {
+        if let Some(exit_code) = exit_codes.iter().rev().find(|exit_code| **exit_code != 0) {
+            result.exit_code = *exit_code;
+            shell.last_exit_status = result.exit_code;
+        }
     }
 
     if shell.options.interactive {
@@ -480,7 +602,13 @@ impl Execute for ast::CompoundCommand {
             ast::CompoundCommand::Subshell(ast::SubshellCommand(s)) => {
                 // Clone off a new subshell, and run the body of the subshell there.
                 let mut subshell = shell.clone();
-                s.execute(&mut subshell, params).await
+                let mut result = s.execute(&mut subshell, params).await?;
+
+                // Exiting or returning only affects the subshell.
+                result.exit_shell = false;
+                result.return_from_function_or_script = false;
+
+                Ok(result)
             }
             ast::CompoundCommand::ForClause(f) => f.execute(shell, params).await,
             ast::CompoundCommand::CaseClause(c) => c.execute(shell, params).await,
@@ -516,24 +644,35 @@ impl Execute for ast::ForClauseCommand {
 
             for value in expanded_values {
                 if shell.options.print_commands_and_arguments {
-                    shell.trace_command(std::format!(
-                        "for {} in {}",
-                        self.variable_name,
-                        unexpanded_values.iter().join(" ")
-                    ))?;
+                    shell
+                        .trace_command(std::format!(
+                            "for {} in {}",
+                            self.variable_name,
+                            unexpanded_values.iter().join(" ")
+                        ))
+                        .await?;
                 }
 
-                // Update the variable.
-                shell.env.update_or_add(
-                    &self.variable_name,
-                    ShellValueLiteral::Scalar(value),
-                    |_| Ok(()),
-                    EnvironmentLookup::Anywhere,
-                    EnvironmentScope::Global,
-                )?;
+                // Update the variable. If it's a name reference, then each value names the
+                // variable it will refer to.
+                if let Some((_, var)) = shell
+                    .env
+                    .get_mut_unresolved(&self.variable_name)
+                    .filter(|(_, var)| var.is_treated_as_nameref())
+                {
+                    var.assign(ShellValueLiteral::Scalar(value), false)?;
+                } else {
+                    shell.env.update_or_add(
+                        &self.variable_name,
+                        ShellValueLiteral::Scalar(value),
+                        |_| Ok(()),
+                        EnvironmentLookup::Anywhere,
+                        EnvironmentScope::Global,
+                    )?;
+                }
 
                 result = self.body.0.execute(shell, params).await?;
-                if result.return_from_function_or_script {
+                if result.exit_shell || result.return_from_function_or_script {
                     break;
                 }
 
@@ -570,7 +709,9 @@ impl Execute for ast::CaseClauseCommand {
         // N.B. One would think it makes sense to trace the expanded value being switched
         // on, but that's not it.
         if shell.options.print_commands_and_arguments {
-            shell.trace_command(std::format!("case {} in", &self.value))?;
+            shell
+                .trace_command(std::format!("case {} in", &self.value))
+                .await?;
         }
 
         let expanded_value = expansion::basic_expand_word(shell, &self.value).await?;
@@ -586,6 +727,8 @@ impl Execute for ast::CaseClauseCommand {
                     let expanded_pattern = expansion::basic_expand_pattern(shell, pattern)
                         .await?
                         .set_extended_globbing(shell.options.extended_globbing)
+                        .set_locale(shell.locale())
+                        .set_ranges_use_c_locale(shell.options.glob_ranges_use_c_locale)
                         .set_case_insensitive(shell.options.case_insensitive_conditionals);
 
                     if expanded_pattern.exactly_matches(expanded_value.as_str())? {
@@ -605,6 +748,10 @@ impl Execute for ast::CaseClauseCommand {
                 ExecutionResult::success()
             };
 
+            if result.exit_shell || result.return_from_function_or_script {
+                break;
+            }
+
             match case.post_action {
                 ast::CaseItemPostAction::ExitCase => break,
                 ast::CaseItemPostAction::UnconditionallyExecuteNextCaseItem => {
@@ -627,7 +774,11 @@ impl Execute for ast::IfClauseCommand {
         shell: &mut Shell,
         params: &ExecutionParameters,
     ) -> Result<ExecutionResult, error::Error> {
-        let condition = self.condition.execute(shell, params).await?;
+        let condition = execute_condition(&self.condition, shell, params).await?;
+
+        if condition.exit_shell || condition.return_from_function_or_script {
+            return Ok(condition);
+        }
 
         if condition.is_success() {
             return self.then.execute(shell, params).await;
@@ -637,7 +788,14 @@ impl Execute for ast::IfClauseCommand {
             for else_clause in elses {
                 match &else_clause.condition {
                     Some(else_condition) => {
-                        let else_condition_result = else_condition.execute(shell, params).await?;
+                        let else_condition_result =
+                            execute_condition(else_condition, shell, params).await?;
+                        if else_condition_result.exit_shell
+                            || else_condition_result.return_from_function_or_script
+                        {
+                            return Ok(else_condition_result);
+                        }
+
                         if else_condition_result.is_success() {
                             return else_clause.body.execute(shell, params).await;
                         }
@@ -673,18 +831,19 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
         let mut result = ExecutionResult::success();
 
         loop {
-            let condition_result = test_condition.execute(shell, params).await?;
+            let condition_result = execute_condition(test_condition, shell, params).await?;
 
-            if condition_result.is_success() != is_while {
+            if condition_result.exit_shell || condition_result.return_from_function_or_script {
+                result = condition_result;
                 break;
             }
 
-            if condition_result.return_from_function_or_script {
+            if condition_result.is_success() != is_while {
                 break;
             }
 
             result = body.0.execute(shell, params).await?;
-            if result.return_from_function_or_script {
+            if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
 
@@ -709,6 +868,20 @@ impl Execute for (WhileOrUntil, &ast::WhileOrUntilClauseCommand) {
     }
 }
 
+/// Executes the condition of an `if`, `while`, or `until` command; failures in a condition
+/// are exempt from errexit.
+async fn execute_condition(
+    condition: &ast::CompoundList,
+    shell: &mut Shell,
+    params: &ExecutionParameters,
+) -> Result<ExecutionResult, error::Error> {
+    shell.errexit_suppression_depth += 1;
+    let result = condition.execute(shell, params).await;
+    shell.errexit_suppression_depth -= 1;
+
+    result
+}
+
 #[async_trait::async_trait]
 impl Execute for ast::ArithmeticCommand {
     async fn execute(
@@ -749,7 +922,7 @@ impl Execute for ast::ArithmeticForClauseCommand {
             }
 
             result = self.body.0.execute(shell, params).await?;
-            if result.return_from_function_or_script {
+            if result.exit_shell || result.return_from_function_or_script {
                 break;
             }
 
@@ -883,35 +1056,18 @@ impl ExecuteInPipeline for ast::SimpleCommand {
                     }
                 }
                 CommandPrefixOrSuffixItem::Word(arg) => {
-                    let mut next_args =
+                    let next_args =
                         expansion::full_expand_and_split_word(context.shell, arg).await?;
 
                     if args.is_empty() {
                         if let Some(cmd_name) = next_args.first() {
-                            if let Some(alias_value) = context.shell.aliases.get(cmd_name.as_str())
-                            {
-                                //
-                                // TODO: This is a total hack; aliases are supposed to be handled
-                                // much earlier in the process.
-                                //
-                                let mut alias_pieces: Vec<_> = alias_value
-                                    .split_ascii_whitespace()
-                                    .map(|i| i.to_owned())
-                                    .collect();
-
-                                next_args.remove(0);
-                                alias_pieces.append(&mut next_args);
-
-                                next_args = alias_pieces;
-                            }
-
                             // Check if we're going to be invoking a special declaration builtin.
                             // That will change how we parse and process
                             // args.
                             if context
                                 .shell
                                 .builtins
-                                .get(next_args[0].as_str())
+                                .get(cmd_name.as_str())
                                 .is_some_and(|r| r.declaration_builtin)
                             {
                                 invoking_declaration_builtin = true;
@@ -946,7 +1102,8 @@ impl ExecuteInPipeline for ast::SimpleCommand {
             if context.shell.options.print_commands_and_arguments {
                 context
                     .shell
-                    .trace_command(args.iter().map(|arg| arg.quote_for_tracing()).join(" "))?;
+                    .trace_command(args.iter().map(|arg| arg.quote_for_tracing()).join(" "))
+                    .await?;
             }
 
             // TODO: This is adding more complexity here; should be factored out into an appropriate
@@ -1123,6 +1280,15 @@ async fn apply_assignment(
         }
     };
 
+    // If the variable is a name reference, then we assign to the variable it refers to.
+    let mut variable_name = shell.env.resolve_nameref(variable_name)?.to_owned();
+    if let Some((array_name, index)) = env::split_array_element_name(variable_name.as_str()) {
+        if array_index.is_none() {
+            array_index = Some(index.to_owned());
+        }
+        variable_name = array_name.to_owned();
+    }
+
     // Expand the values.
     let new_value = match &assignment.value {
         ast::AssignmentValue::Scalar(unexpanded_value) => {
@@ -1156,7 +1322,9 @@ async fn apply_assignment(
 
     if shell.options.print_commands_and_arguments {
         let op = if assignment.append { "+=" } else { "=" };
-        shell.trace_command(std::format!("{}{op}{new_value}", assignment.name))?;
+        shell
+            .trace_command(std::format!("{}{op}{new_value}", assignment.name))
+            .await?;
     }
 
     // See if we need to eval an array index.
@@ -6,22 +6,28 @@
 pub mod completion;
 
 mod arithmetic;
+mod braceexpansion;
 pub mod builtins;
 mod commands;
+mod datetime;
 mod env;
 mod error;
 mod escape;
 mod expansion;
 mod extendedtests;
 mod functions;
+pub mod history;
+pub mod hooks;
 mod interp;
 mod jobs;
 mod keywords;
+pub mod locale;
 mod namedoptions;
 mod openfiles;
 mod options;
 mod pathcache;
 mod patterns;
+mod printf;
 mod processes;
 mod prompt;
 mod regex;
@@ -36,6 +42,8 @@ mod variables;
 pub use commands::ExecutionContext;
 pub use error::Error;
 pub use interp::{ExecutionParameters, ExecutionResult};
+pub use options::RuntimeOptions;
 pub use shell::{CreateOptions, Shell};
+pub use sys::fs::is_executable;
 pub use terminal::TerminalControl;
 pub use variables::{ShellValue, ShellVariable};
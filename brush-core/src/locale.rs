@@ -0,0 +1,263 @@
+//! Support for the shell's locale, as configured by `LC_*` and `LANG` variables.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::{env, sys};
+
+/// Maps the start and end of character ranges to the characters they include.
+type RangeMap = HashMap<(char, char), Vec<char>>;
+
+/// Represents the locale settings in effect in a shell, for the categories the shell
+/// itself is sensitive to.
+#[derive(Clone, Default)]
+pub struct Locale {
+    /// Name of the locale used for collating (ordering) strings.
+    collate: String,
+    /// Name of the locale used for classifying characters.
+    ctype: String,
+    /// Collator for the collation locale; only present if the locale doesn't collate
+    /// strings the same way the C locale does and is available on this system.
+    collator: Option<Arc<sys::locale::Collator>>,
+    /// Results of previous calls to [`Locale::chars_in_range`], shared between clones.
+    ranges: Arc<Mutex<RangeMap>>,
+}
+
+impl std::fmt::Debug for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Locale")
+            .field("collate", &self.collate)
+            .field("ctype", &self.ctype)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Locale {
+    fn new(collate: String, ctype: String) -> Self {
+        let collator = if name_collates_as_c(collate.as_str()) {
+            None
+        } else {
+            sys::locale::Collator::new(collate.as_str()).map(Arc::new)
+        };
+
+        Self {
+            collate,
+            ctype,
+            collator,
+            ranges: Arc::default(),
+        }
+    }
+
+    /// Returns whether strings are collated in the same order as the C locale would (i.e.,
+    /// by code point).
+    pub fn collates_as_c(&self) -> bool {
+        self.collator.is_none()
+    }
+
+    /// Returns whether characters are classified using UTF-8 encoding rules.
+    pub fn ctype_is_utf8(&self) -> bool {
+        let ctype = self.ctype.to_ascii_lowercase();
+        ctype.contains("utf-8") || ctype.contains("utf8")
+    }
+
+    /// Compares two strings according to the locale's collation order.
+    ///
+    /// # Arguments
+    ///
+    /// * `left` - The first string to compare.
+    /// * `right` - The second string to compare.
+    pub fn compare(&self, left: &str, right: &str) -> Ordering {
+        if let Some(collator) = &self.collator {
+            // N.B. Like strcoll(), the collator may consider distinct strings equal;
+            // we fall back to ordering such strings by code point.
+            collator
+                .compare(left, right)
+                .unwrap_or(Ordering::Equal)
+                .then_with(|| left.cmp(right))
+        } else {
+            left.cmp(right)
+        }
+    }
+
+    /// Returns the characters, among a set of candidates, that collate between the given
+    /// start and end characters (inclusive). If either character isn't itself a candidate,
+    /// the characters between them in code point order are returned instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - The start of the range.
+    /// * `end` - The end of the range.
+    pub(crate) fn chars_in_range(&self, start: char, end: char) -> Vec<char> {
+        if let Some(chars) = self
+            .ranges
+            .lock()
+            .ok()
+            .and_then(|ranges| ranges.get(&(start, end)).cloned())
+        {
+            return chars;
+        }
+
+        let chars = self.compute_chars_in_range(start, end);
+        if let Ok(mut ranges) = self.ranges.lock() {
+            ranges.insert((start, end), chars.clone());
+        }
+
+        chars
+    }
+
+    fn compute_chars_in_range(&self, start: char, end: char) -> Vec<char> {
+        // The candidates can't say anything about ranges over other scripts (e.g., `[α-ω]`).
+        if !RANGE_CANDIDATES.contains(&u32::from(start))
+            || !RANGE_CANDIDATES.contains(&u32::from(end))
+        {
+            return (start..=end).collect();
+        }
+
+        let mut start_buf = [0; 4];
+        let mut end_buf = [0; 4];
+        let start = start.encode_utf8(&mut start_buf);
+        let end = end.encode_utf8(&mut end_buf);
+
+        RANGE_CANDIDATES
+            .filter_map(char::from_u32)
+            .filter(|c| {
+                let mut buf = [0; 4];
+                let c = c.encode_utf8(&mut buf);
+                self.compare(start, c).is_le() && self.compare(c, end).is_le()
+            })
+            .collect()
+    }
+}
+
+/// Caches the locale selected by a shell's variables, so it's only rebuilt when the
+/// variables that select it change.
+#[derive(Default)]
+pub(crate) struct LocaleCache {
+    locale: Mutex<Option<Locale>>,
+}
+
+impl Clone for LocaleCache {
+    fn clone(&self) -> Self {
+        let locale = self.locale.lock().ok().and_then(|locale| locale.clone());
+        Self {
+            locale: Mutex::new(locale),
+        }
+    }
+}
+
+impl LocaleCache {
+    /// Returns the locale selected by the variables in the given environment, reusing the
+    /// previously returned one if it's still selected. As with other shells, `LC_ALL` takes
+    /// precedence over category-specific variables (e.g., `LC_COLLATE`), which in turn take
+    /// precedence over `LANG`.
+    ///
+    /// # Arguments
+    ///
+    /// * `env` - The environment to read locale variables from.
+    pub fn get(&self, env: &env::ShellEnvironment) -> Locale {
+        let collate = resolve_category_name(env, "LC_COLLATE");
+        let ctype = resolve_category_name(env, "LC_CTYPE");
+
+        let Ok(mut cached) = self.locale.lock() else {
+            return Locale::new(collate, ctype);
+        };
+
+        match cached.as_ref() {
+            Some(locale) if locale.collate == collate && locale.ctype == ctype => locale.clone(),
+            _ => {
+                let locale = Locale::new(collate, ctype);
+                *cached = Some(locale.clone());
+                locale
+            }
+        }
+    }
+}
+
+/// Code points considered as members of bracket expression ranges in locales that don't
+/// collate as the C locale does: printable ASCII and Latin-1, along with the Latin
+/// Extended blocks.
+const RANGE_CANDIDATES: std::ops::RangeInclusive<u32> = 0x20..=0x24F;
+
+fn resolve_category_name(env: &env::ShellEnvironment, category_var_name: &str) -> String {
+    for name in ["LC_ALL", category_var_name, "LANG"] {
+        if let Some(value) = env.get_str(name) {
+            if !value.is_empty() {
+                return value.to_string();
+            }
+        }
+    }
+
+    String::new()
+}
+
+fn name_collates_as_c(name: &str) -> bool {
+    matches!(
+        name,
+        "" | "C" | "POSIX" | "C.UTF-8" | "C.utf8" | "C.UTF8" | "C.utf-8"
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn)]
+mod tests {
+    use super::*;
+    use crate::variables::{ShellValue, ShellVariable};
+    use anyhow::Result;
+
+    fn set_var(env: &mut env::ShellEnvironment, name: &str, value: &str) -> Result<()> {
+        env.set_global(
+            name,
+            ShellVariable::new(ShellValue::String(value.to_owned())),
+        )?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_locale_cache() -> Result<()> {
+        let mut env = env::ShellEnvironment::new();
+        let cache = LocaleCache::default();
+
+        set_var(&mut env, "LANG", "C")?;
+        let first = cache.get(&env);
+
+        set_var(&mut env, "PATH", "/bin")?;
+        let second = cache.get(&env);
+        assert!(Arc::ptr_eq(&first.ranges, &second.ranges));
+
+        set_var(&mut env, "LC_ALL", "POSIX")?;
+        let third = cache.get(&env);
+        assert!(!Arc::ptr_eq(&second.ranges, &third.ranges));
+        assert_eq!(third.collate, "POSIX");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chars_in_range() -> Result<()> {
+        let locale = LocaleCache::default().get(&env::ShellEnvironment::new());
+        assert_eq!(
+            locale.chars_in_range('a', 'e'),
+            vec!['a', 'b', 'c', 'd', 'e']
+        );
+
+        let ranges = locale
+            .ranges
+            .lock()
+            .map_err(|_| anyhow::anyhow!("lock poisoned"))?;
+        assert!(ranges.contains_key(&('a', 'e')));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chars_in_range_outside_candidates() {
+        let locale = LocaleCache::default().get(&env::ShellEnvironment::new());
+
+        let greek = locale.chars_in_range('α', 'ω');
+        assert_eq!(greek.len(), 25);
+        assert!(greek.contains(&'λ'));
+
+        assert_eq!(locale.chars_in_range('a', 'я').last(), Some(&'я'));
+    }
+}
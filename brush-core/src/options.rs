@@ -1,7 +1,7 @@
 use crate::CreateOptions;
 
 /// Runtime changeable options for a shell instance.
-#[derive(Clone, Default)]
+#[derive(Clone, Debug, Default)]
 #[allow(clippy::module_name_repetitions)]
 pub struct RuntimeOptions {
     //
@@ -177,6 +177,15 @@ pub struct RuntimeOptions {
     pub read_commands_from_stdin: bool,
     /// Whether or not the shell is in maximal `sh` compatibility mode.    
     pub sh_mode: bool,
+
+    //
+    // Options specific to brush.
+    /// Whether to offer autosuggestions while editing input interactively.
+    pub autosuggestions: bool,
+    /// Whether autosuggestions may be drawn from command history.
+    pub autosuggestions_from_history: bool,
+    /// Whether autosuggestions may be drawn from basic (non-programmable) completion.
+    pub autosuggestions_from_completion: bool,
 }
 
 impl RuntimeOptions {
@@ -210,6 +219,9 @@ impl RuntimeOptions {
             quote_all_metachars_in_completion: true,
             programmable_completion: true,
             glob_ranges_use_c_locale: true,
+            autosuggestions: true,
+            autosuggestions_from_history: true,
+            autosuggestions_from_completion: true,
             ..Self::default()
         };
 
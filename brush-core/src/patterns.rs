@@ -1,4 +1,4 @@
-use crate::{error, regex, trace_categories};
+use crate::{error, locale, regex, trace_categories};
 use std::{
     collections::VecDeque,
     path::{Path, PathBuf},
@@ -29,8 +29,12 @@ type PatternWord = Vec<PatternPiece>;
 pub struct Pattern {
     pieces: PatternWord,
     enable_extended_globbing: bool,
+    enable_star_star_glob: bool,
+    match_dotfiles: bool,
     multiline: bool,
     case_insensitive: bool,
+    locale: locale::Locale,
+    ranges_use_c_locale: bool,
 }
 
 impl Default for Pattern {
@@ -38,8 +42,12 @@ impl Default for Pattern {
         Self {
             pieces: vec![],
             enable_extended_globbing: false,
+            enable_star_star_glob: false,
+            match_dotfiles: false,
             multiline: true,
             case_insensitive: false,
+            locale: locale::Locale::default(),
+            ranges_use_c_locale: true,
         }
     }
 }
@@ -91,6 +99,29 @@ impl Pattern {
         self
     }
 
+    /// Enables (or disables) recursive matching of `**` path components when this pattern
+    /// is expanded into file paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Whether or not to enable `**` matching (globstar).
+    pub fn set_star_star_glob(mut self, value: bool) -> Pattern {
+        self.enable_star_star_glob = value;
+        self
+    }
+
+    /// Enables (or disables) matching of files whose names start with a '.' without
+    /// the pattern explicitly starting with a '.', when this pattern is expanded into
+    /// file paths.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Whether or not to match dotfiles (dotglob).
+    pub fn set_match_dotfiles(mut self, value: bool) -> Pattern {
+        self.match_dotfiles = value;
+        self
+    }
+
     /// Enables (or disables) multiline support for this pattern.
     ///
     /// # Arguments
@@ -112,11 +143,41 @@ impl Pattern {
         self
     }
 
+    /// Sets the locale used for matching character classes and ranges in bracket
+    /// expressions, and for ordering the file paths this pattern expands into.
+    ///
+    /// # Arguments
+    ///
+    /// * `locale` - The locale to use.
+    pub fn set_locale(mut self, locale: locale::Locale) -> Pattern {
+        self.locale = locale;
+        self
+    }
+
+    /// Enables (or disables) matching ranges in bracket expressions (e.g., `[a-z]`) as
+    /// the C locale would, i.e., by code point, regardless of the pattern's locale.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - Whether or not ranges use the C locale (globasciiranges).
+    pub fn set_ranges_use_c_locale(mut self, value: bool) -> Pattern {
+        self.ranges_use_c_locale = value;
+        self
+    }
+
     /// Returns whether or not the pattern is empty.
     pub fn is_empty(&self) -> bool {
         self.pieces.iter().all(|p| p.as_str().is_empty())
     }
 
+    /// Returns whether or not the pattern contains any special characters that require
+    /// it to be expanded.
+    pub(crate) fn requires_expansion(&self) -> bool {
+        self.pieces.iter().any(|piece| {
+            matches!(piece, PatternPiece::Pattern(_)) && requires_expansion(piece.as_str())
+        })
+    }
+
     /// Placeholder function that always returns true.
     pub(crate) fn accept_all_expand_filter(_path: &Path) -> bool {
         true
@@ -145,9 +206,7 @@ impl Pattern {
 
         // Similarly, if we're *confident* the pattern doesn't require expansion, then we
         // know there's a single expansion (before filtering).
-        } else if !self.pieces.iter().any(|piece| {
-            matches!(piece, PatternPiece::Pattern(_)) && requires_expansion(piece.as_str())
-        }) {
+        } else if !self.requires_expansion() {
             let concatenated: String = self.pieces.iter().map(|piece| piece.as_str()).collect();
 
             if let Some(filter) = path_filter {
@@ -210,7 +269,29 @@ impl Pattern {
             vec![working_dir.to_path_buf()]
         };
 
-        for component in components {
+        let component_count = components.len();
+        for (component_index, component) in components.into_iter().enumerate() {
+            let is_last_component = component_index + 1 == component_count;
+
+            // With globstar enabled, a component consisting solely of `**` matches all files
+            // and directories under the current path; if it's followed by more components,
+            // then it matches zero or more directories instead.
+            if self.enable_star_star_glob && is_star_star_component(&component) {
+                let current_paths = std::mem::take(&mut paths_so_far);
+                for current_path in current_paths {
+                    if !is_last_component {
+                        paths_so_far.push(current_path.clone());
+                    }
+
+                    self.collect_paths_recursively(
+                        current_path.as_path(),
+                        is_last_component,
+                        &mut paths_so_far,
+                    );
+                }
+                continue;
+            }
+
             if !component.iter().any(|piece| {
                 matches!(piece, PatternPiece::Pattern(_)) && requires_expansion(piece.as_str())
             }) {
@@ -228,14 +309,25 @@ impl Pattern {
             for current_path in current_paths {
                 let subpattern = Pattern::from(&component)
                     .set_extended_globbing(self.enable_extended_globbing)
-                    .set_case_insensitive(self.case_insensitive);
+                    .set_case_insensitive(self.case_insensitive)
+                    .set_locale(self.locale.clone())
+                    .set_ranges_use_c_locale(self.ranges_use_c_locale);
 
                 let regex = subpattern.to_regex(true, true)?;
 
+                // Files starting with a '.' are only matched if the component explicitly
+                // starts with a '.' or if we've been asked to match them.
+                let match_dotfiles = self.match_dotfiles || starts_with_dot(&component);
+
                 let matches_regex = |dir_entry: &std::fs::DirEntry| {
-                    regex
-                        .is_match(dir_entry.file_name().to_string_lossy().as_ref())
-                        .unwrap_or(false)
+                    let file_name = dir_entry.file_name();
+                    let file_name = file_name.to_string_lossy();
+
+                    if !match_dotfiles && file_name.starts_with('.') {
+                        return false;
+                    }
+
+                    regex.is_match(file_name.as_ref()).unwrap_or(false)
                 };
 
                 let mut matching_paths_in_dir: Vec<_> = current_path
@@ -247,15 +339,25 @@ impl Pattern {
                     .map(|entry| entry.path())
                     .collect();
 
-                matching_paths_in_dir.sort();
-
                 paths_so_far.append(&mut matching_paths_in_dir);
             }
         }
 
+        // Order the results as the locale collates them.
+        paths_so_far.sort_by(|left, right| {
+            self.locale
+                .compare(&left.to_string_lossy(), &right.to_string_lossy())
+        });
+
         let results: Vec<_> = paths_so_far
             .into_iter()
             .filter_map(|path| {
+                // Components without patterns were appended as-is; make sure that the
+                // resulting path actually exists.
+                if path.symlink_metadata().is_err() {
+                    return None;
+                }
+
                 if let Some(filter) = path_filter {
                     if !filter(path.as_path()) {
                         return None;
@@ -269,6 +371,11 @@ impl Pattern {
                     path_ref = path_ref.strip_prefix(prefix_to_remove).unwrap();
                 }
 
+                // N.B. A `**` component may have matched the working directory itself.
+                if path_ref.is_empty() {
+                    return None;
+                }
+
                 Some(path_ref.to_string())
             })
             .collect();
@@ -278,6 +385,51 @@ impl Pattern {
         Ok(results)
     }
 
+    /// Appends to `results` the paths of all entries (recursively) under the given directory,
+    /// in sorted order. Symbolic links to directories are not traversed.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - The directory to traverse.
+    /// * `include_files` - Whether to include non-directory entries in the results.
+    /// * `results` - The list to append results to.
+    fn collect_paths_recursively(
+        &self,
+        dir: &Path,
+        include_files: bool,
+        results: &mut Vec<PathBuf>,
+    ) {
+        let mut entries: Vec<_> = dir
+            .read_dir()
+            .map_or_else(|_| vec![], |dir| dir.into_iter().collect())
+            .into_iter()
+            .filter_map(|result| result.ok())
+            .collect();
+
+        entries.sort_by(|left, right| {
+            self.locale.compare(
+                &left.file_name().to_string_lossy(),
+                &right.file_name().to_string_lossy(),
+            )
+        });
+
+        for entry in entries {
+            if !self.match_dotfiles && entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            // N.B. The entry's file type does not follow symbolic links.
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            if is_dir || include_files {
+                results.push(entry.path());
+            }
+
+            if is_dir {
+                self.collect_paths_recursively(entry.path().as_path(), include_files, results);
+            }
+        }
+    }
+
     /// Converts the pattern to a regular expression string.
     ///
     /// # Arguments
@@ -318,8 +470,19 @@ impl Pattern {
             }
         }
 
+        let range_chars = |start, end| self.locale.chars_in_range(start, end);
+        let options = brush_parser::pattern::PatternTranslationOptions {
+            enable_extended_globbing: self.enable_extended_globbing,
+            unicode_char_classes: self.locale.ctype_is_utf8(),
+            range_chars: if self.ranges_use_c_locale || self.locale.collates_as_c() {
+                None
+            } else {
+                Some(&range_chars)
+            },
+        };
+
         let regex_piece =
-            pattern_to_regex_str(current_pattern.as_str(), self.enable_extended_globbing)?;
+            brush_parser::pattern::pattern_to_regex_str_with_options(&current_pattern, &options)?;
         regex_str.push_str(regex_piece.as_str());
 
         if strict_suffix_match {
@@ -361,21 +524,28 @@ impl Pattern {
     }
 }
 
+fn is_star_star_component(component: &PatternWord) -> bool {
+    let mut non_empty_pieces = component.iter().filter(|piece| !piece.as_str().is_empty());
+
+    matches!(
+        (non_empty_pieces.next(), non_empty_pieces.next()),
+        (Some(PatternPiece::Pattern(s)), None) if s == "**"
+    )
+}
+
+fn starts_with_dot(component: &PatternWord) -> bool {
+    component
+        .iter()
+        .map(|piece| piece.as_str())
+        .find(|s| !s.is_empty())
+        .is_some_and(|s| s.starts_with('.'))
+}
+
 fn requires_expansion(s: &str) -> bool {
     // TODO: Make this more accurate.
     s.contains(['*', '?', '[', ']', '(', ')'])
 }
 
-fn pattern_to_regex_str(
-    pattern: &str,
-    enable_extended_globbing: bool,
-) -> Result<String, error::Error> {
-    Ok(brush_parser::pattern::pattern_to_regex_str(
-        pattern,
-        enable_extended_globbing,
-    )?)
-}
-
 /// Removes the largest matching prefix from a string that matches the given pattern.
 ///
 /// # Arguments
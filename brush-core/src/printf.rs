@@ -0,0 +1,1188 @@
+use std::fmt::Display;
+use std::time::SystemTime;
+
+use crate::{datetime, escape};
+
+/// The largest field width or precision supported. As in bash, larger values given as
+/// arguments (via `*`) are clamped to this; as with `printf(3)`, larger values in the format
+/// string itself are reported as errors.
+const MAX_FIELD_WIDTH: usize = i32::MAX as usize;
+
+/// The most output that may be produced. As with `printf(3)`, an error is reported instead of
+/// producing more.
+const MAX_OUTPUT_LEN: usize = i32::MAX as usize;
+
+/// A problem encountered while formatting, to be reported to the user.
+pub(crate) enum FormatDiagnostic {
+    /// An argument couldn't be fully converted to a number; its partially-converted value
+    /// was used.
+    InvalidNumber(String),
+    /// A numeric argument was out of range; the nearest representable value was used.
+    OutOfRange(String),
+    /// The format string contained an unsupported conversion character.
+    InvalidFormatCharacter(char),
+    /// The format string ended in the middle of a conversion specification.
+    MissingFormatCharacter,
+    /// The output would have exceeded the maximum supported length.
+    OutputTooLarge,
+}
+
+impl FormatDiagnostic {
+    /// Returns whether the diagnostic should cause the command to fail.
+    pub fn is_error(&self) -> bool {
+        !matches!(self, FormatDiagnostic::OutOfRange(_))
+    }
+}
+
+impl Display for FormatDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatDiagnostic::InvalidNumber(arg) => {
+                let mut chars = arg.chars();
+                let description = match (chars.next(), chars.next()) {
+                    (Some('0'), Some(c)) if c.is_ascii_digit() => "invalid octal number",
+                    (Some('0'), Some('x')) => "invalid hex number",
+                    _ => "invalid number",
+                };
+                write!(f, "{arg}: {description}")
+            }
+            FormatDiagnostic::OutOfRange(arg) => {
+                write!(f, "warning: {arg}: Numerical result out of range")
+            }
+            FormatDiagnostic::InvalidFormatCharacter(c) => {
+                write!(f, "`{c}': invalid format character")
+            }
+            FormatDiagnostic::MissingFormatCharacter => write!(f, "`%': missing format character"),
+            FormatDiagnostic::OutputTooLarge => write!(f, "Value too large for defined data type"),
+        }
+    }
+}
+
+/// The result of formatting.
+pub(crate) struct FormatResult {
+    /// The formatted output.
+    pub output: Vec<u8>,
+    /// Problems encountered while formatting.
+    pub diagnostics: Vec<FormatDiagnostic>,
+    /// Names of variables to assign (via `%n`), along with the number of bytes that had been
+    /// output when they were encountered.
+    pub byte_counts: Vec<(String, usize)>,
+}
+
+/// Formats the given arguments according to the given format string, as `printf` does. The
+/// format string is reused as many times as needed to consume all arguments; conversions
+/// without a corresponding argument behave as if given an empty string (or zero).
+///
+/// N.B. Numbers are always formatted as in the C locale; `LC_NUMERIC` is not consulted.
+///
+/// # Arguments
+///
+/// * `format` - The format string.
+/// * `args` - The arguments to format.
+/// * `shell_start_time` - When the shell was started; used by `%(...)T` for an argument of -2.
+/// * `time_zone` - The time zone for `%(...)T` to use, as a `TZ` value; if not provided, the
+///   system's default time zone is used.
+pub(crate) fn format(
+    format: &str,
+    args: &[String],
+    shell_start_time: SystemTime,
+    time_zone: Option<&str>,
+) -> FormatResult {
+    let mut formatter = Formatter {
+        args,
+        next_arg: 0,
+        shell_start_time,
+        time_zone,
+        skip_field: false,
+        result: FormatResult {
+            output: vec![],
+            diagnostics: vec![],
+            byte_counts: vec![],
+        },
+    };
+
+    loop {
+        let args_consumed_before = formatter.next_arg;
+        if !formatter.format_once(format) {
+            break;
+        }
+
+        if formatter.next_arg == args_consumed_before || formatter.next_arg >= args.len() {
+            break;
+        }
+    }
+
+    formatter.result
+}
+
+/// The parsed flags, width, and precision of a conversion specification.
+#[derive(Default)]
+struct ConversionSpec {
+    /// Left-justify within the field (`-`).
+    left_justify: bool,
+    /// Always include a sign for signed conversions (`+`).
+    force_sign: bool,
+    /// Prefix non-negative signed conversions with a space (` `).
+    space_for_sign: bool,
+    /// Use the alternate form (`#`).
+    alternate_form: bool,
+    /// Pad numeric conversions with leading zeros (`0`).
+    zero_pad: bool,
+    /// Minimum field width.
+    width: Option<usize>,
+    /// Precision.
+    precision: Option<usize>,
+}
+
+struct Formatter<'a> {
+    args: &'a [String],
+    next_arg: usize,
+    shell_start_time: SystemTime,
+    time_zone: Option<&'a str>,
+    /// Set when the field for the current conversion can't be output.
+    skip_field: bool,
+    result: FormatResult,
+}
+
+impl<'a> Formatter<'a> {
+    /// Processes the format string once. Returns false if all output should stop.
+    fn format_once(&mut self, format: &str) -> bool {
+        let mut remaining = format;
+
+        while !remaining.is_empty() {
+            // Expand escape sequences in literal text up to the next conversion.
+            let literal_len = remaining.find('%').unwrap_or(remaining.len());
+            if literal_len > 0 {
+                self.push_literal(&remaining[..literal_len]);
+                remaining = &remaining[literal_len..];
+                continue;
+            }
+
+            remaining = &remaining[1..];
+            if let Some(after) = remaining.strip_prefix('%') {
+                self.result.output.push(b'%');
+                remaining = after;
+                continue;
+            }
+
+            match self.format_conversion(remaining) {
+                Some(after) => remaining = after,
+                None => return false,
+            }
+        }
+
+        true
+    }
+
+    fn push_literal(&mut self, s: &str) {
+        let expanded =
+            escape::expand_backslash_escapes(s, escape::EscapeExpansionMode::PrintfFormat)
+                .map_or_else(|_| s.as_bytes().to_vec(), |(expanded, _)| expanded);
+        self.result.output.extend(expanded);
+    }
+
+    /// Formats the conversion at the start of the given string (just past its `%`).
+    /// Returns the rest of the string, or `None` if all output should stop.
+    #[allow(clippy::too_many_lines)]
+    fn format_conversion<'b>(&mut self, s: &'b str) -> Option<&'b str> {
+        let mut spec = ConversionSpec::default();
+        self.skip_field = false;
+
+        let mut chars = s.char_indices().peekable();
+
+        // Flags
+        while let Some((_, c)) = chars.next_if(|(_, c)| "#'-+ 0".contains(*c)) {
+            match c {
+                '-' => spec.left_justify = true,
+                '+' => spec.force_sign = true,
+                ' ' => spec.space_for_sign = true,
+                '#' => spec.alternate_form = true,
+                '0' => spec.zero_pad = true,
+                // N.B. Thousands grouping has no effect in the C locale.
+                _ => (),
+            }
+        }
+
+        // Width
+        if chars.next_if(|(_, c)| *c == '*').is_some() {
+            let width = self.next_field_width_arg();
+            spec.left_justify |= width < 0;
+            spec.width = Some(clamp_field_width(width.unsigned_abs()));
+        } else if let Some(width) = parse_decimal(&mut chars) {
+            spec.width = self.checked_field_width(width);
+        }
+
+        // Precision
+        if chars.next_if(|(_, c)| *c == '.').is_some() {
+            if chars.next_if(|(_, c)| *c == '*').is_some() {
+                spec.precision = u64::try_from(self.next_field_width_arg())
+                    .ok()
+                    .map(clamp_field_width);
+            } else {
+                let precision = parse_decimal(&mut chars).unwrap_or(0);
+                spec.precision = self.checked_field_width(precision);
+            }
+        }
+
+        // Length modifiers are accepted but have no effect.
+        while chars.next_if(|(_, c)| "hjlLtz".contains(*c)).is_some() {}
+
+        let Some((index, conversion)) = chars.next() else {
+            self.result
+                .diagnostics
+                .push(FormatDiagnostic::MissingFormatCharacter);
+            return None;
+        };
+
+        let mut rest = &s[index + conversion.len_utf8()..];
+
+        // Numeric conversions are padded with zeros to their precision before being padded to
+        // their width, so make sure that won't produce too much output.
+        if "diouxXeEfFgGaA".contains(conversion) && !self.has_room_for(spec.precision.unwrap_or(0))
+        {
+            self.next_arg();
+            return Some(rest);
+        }
+
+        match conversion {
+            'd' | 'i' => {
+                let value = self.next_integer_arg();
+                let formatted = format_signed_integer(value, &spec);
+                self.push_field(formatted.as_bytes(), &spec);
+            }
+            'o' | 'u' | 'x' | 'X' => {
+                let value = self.next_unsigned_integer_arg();
+                let formatted = format_unsigned_integer(value, conversion, &spec);
+                self.push_field(formatted.as_bytes(), &spec);
+            }
+            'e' | 'E' | 'f' | 'F' | 'g' | 'G' | 'a' | 'A' => {
+                let value = self.next_float_arg();
+                let formatted = format_float(value, conversion, &spec);
+                self.push_field(formatted.as_bytes(), &spec);
+            }
+            'c' => {
+                let value = self
+                    .next_arg()
+                    .map_or(0, |arg| arg.bytes().next().unwrap_or(0));
+                self.push_field(
+                    &[value],
+                    &ConversionSpec {
+                        precision: None,
+                        ..spec
+                    },
+                );
+            }
+            's' => {
+                let value = self.next_arg().unwrap_or_default();
+                self.push_truncated_field(value.as_bytes(), &spec);
+            }
+            'b' => {
+                let value = self.next_arg().unwrap_or_default();
+                let (expanded, keep_going) = escape::expand_backslash_escapes(
+                    value,
+                    escape::EscapeExpansionMode::PrintfEscapeArg,
+                )
+                .unwrap_or_else(|_| (value.as_bytes().to_vec(), true));
+
+                self.push_truncated_field(expanded.as_slice(), &spec);
+
+                if !keep_going {
+                    return None;
+                }
+            }
+            'q' => {
+                let value = self.next_arg().unwrap_or_default();
+                let quoted = quote(value);
+                self.push_truncated_field(quoted.as_bytes(), &spec);
+            }
+            'Q' => {
+                let value = self.next_arg().unwrap_or_default();
+                let truncated = truncate(value.as_bytes(), spec.precision);
+                let quoted = quote(String::from_utf8_lossy(truncated).as_ref());
+                self.push_field(quoted.as_bytes(), &spec);
+            }
+            'n' => {
+                if let Some(name) = self.next_arg().filter(|name| !name.is_empty()) {
+                    let count = self.result.output.len();
+                    self.result.byte_counts.push((name.to_owned(), count));
+                }
+            }
+            '(' => {
+                let Some(end) = rest.find(")T") else {
+                    self.result
+                        .diagnostics
+                        .push(FormatDiagnostic::InvalidFormatCharacter('('));
+                    return None;
+                };
+
+                let time_format = &rest[..end];
+                rest = &rest[end + 2..];
+
+                let time = match self.has_more_args().then(|| self.next_integer_arg()) {
+                    None | Some(-1) => seconds_since_epoch(SystemTime::now()),
+                    Some(-2) => seconds_since_epoch(self.shell_start_time),
+                    Some(seconds) => seconds,
+                };
+
+                let formatted = format_time(time, time_format, self.time_zone);
+                self.push_truncated_field(formatted.as_bytes(), &spec);
+            }
+            c => {
+                self.result
+                    .diagnostics
+                    .push(FormatDiagnostic::InvalidFormatCharacter(c));
+                return None;
+            }
+        }
+
+        Some(rest)
+    }
+
+    fn has_more_args(&self) -> bool {
+        self.next_arg < self.args.len()
+    }
+
+    fn next_arg(&mut self) -> Option<&'a str> {
+        let arg = self.args.get(self.next_arg)?;
+        self.next_arg += 1;
+        Some(arg.as_str())
+    }
+
+    fn next_integer_arg(&mut self) -> i64 {
+        let Some(arg) = self.next_arg() else {
+            return 0;
+        };
+
+        if let Some(value) = char_value_of(arg) {
+            return value.into();
+        }
+
+        let arg = arg.to_owned();
+        let parsed = parse_integer(&arg);
+
+        let value = if parsed.negative {
+            0i64.checked_sub_unsigned(parsed.magnitude)
+        } else {
+            i64::try_from(parsed.magnitude).ok()
+        };
+
+        let value = match value {
+            Some(value) if !parsed.overflowed => value,
+            _ => {
+                self.report_out_of_range(&arg, parsed.complete);
+                if parsed.negative {
+                    i64::MIN
+                } else {
+                    i64::MAX
+                }
+            }
+        };
+
+        if !parsed.complete {
+            self.report_invalid_number(arg);
+        }
+
+        value
+    }
+
+    fn next_unsigned_integer_arg(&mut self) -> u64 {
+        let Some(arg) = self.next_arg() else {
+            return 0;
+        };
+
+        if let Some(value) = char_value_of(arg) {
+            return value.into();
+        }
+
+        let arg = arg.to_owned();
+        let parsed = parse_integer(&arg);
+
+        let value = if parsed.overflowed {
+            self.report_out_of_range(&arg, parsed.complete);
+            u64::MAX
+        } else if parsed.negative {
+            parsed.magnitude.wrapping_neg()
+        } else {
+            parsed.magnitude
+        };
+
+        if !parsed.complete {
+            self.report_invalid_number(arg);
+        }
+
+        value
+    }
+
+    /// Retrieves the next argument as a field width or precision, clamping it (with a warning)
+    /// to the range supported by `printf(3)`.
+    fn next_field_width_arg(&mut self) -> i64 {
+        let arg = self.args.get(self.next_arg).cloned();
+        let value = self.next_integer_arg();
+
+        let clamped = value.clamp(i32::MIN.into(), i32::MAX.into());
+
+        // N.B. Values that didn't even fit in 64 bits have already been reported.
+        if clamped != value && value != i64::MIN && value != i64::MAX {
+            if let Some(arg) = arg {
+                self.report_out_of_range(&arg, true);
+            }
+        }
+
+        clamped
+    }
+
+    fn next_float_arg(&mut self) -> f64 {
+        let Some(arg) = self.next_arg() else {
+            return 0.0;
+        };
+
+        if let Some(value) = char_value_of(arg) {
+            return value.into();
+        }
+
+        let arg = arg.to_owned();
+        let (value, len) = parse_float(&arg);
+        let complete = len == arg.len();
+
+        if value.is_infinite() && !arg.to_ascii_lowercase().contains("inf") {
+            self.report_out_of_range(&arg, complete);
+        }
+
+        if !complete {
+            self.report_invalid_number(arg);
+        }
+
+        value
+    }
+
+    fn report_invalid_number(&mut self, arg: String) {
+        self.result
+            .diagnostics
+            .push(FormatDiagnostic::InvalidNumber(arg));
+    }
+
+    fn report_out_of_range(&mut self, arg: &str, complete: bool) {
+        // Range problems aren't reported for arguments that weren't valid numbers.
+        if complete {
+            self.result
+                .diagnostics
+                .push(FormatDiagnostic::OutOfRange(arg.to_owned()));
+        }
+    }
+
+    /// Outputs the given value, truncated to the precision in the spec (if any).
+    fn push_truncated_field(&mut self, value: &[u8], spec: &ConversionSpec) {
+        self.push_field(truncate(value, spec.precision), spec);
+    }
+
+    /// Checks that a field width or precision given in the format string is supported. If not,
+    /// reports an error and arranges for the current conversion's field to be skipped.
+    fn checked_field_width(&mut self, width: u64) -> Option<usize> {
+        let width = usize::try_from(width)
+            .ok()
+            .filter(|width| *width <= MAX_FIELD_WIDTH);
+
+        if width.is_none() {
+            self.skip_field();
+        }
+
+        width
+    }
+
+    /// Checks whether the given number of bytes can be added to the output for the current
+    /// conversion. If not, reports an error and arranges for its field to be skipped.
+    fn has_room_for(&mut self, len: usize) -> bool {
+        if self.result.output.len().saturating_add(len) > MAX_OUTPUT_LEN {
+            self.skip_field();
+        }
+
+        !self.skip_field
+    }
+
+    /// Arranges for the current conversion's field to be skipped, as `printf(3)` fails when
+    /// asked to produce too much output.
+    fn skip_field(&mut self) {
+        // Only report the problem once per field.
+        if !self.skip_field {
+            self.skip_field = true;
+            self.result
+                .diagnostics
+                .push(FormatDiagnostic::OutputTooLarge);
+        }
+    }
+
+    /// Outputs the given value, padded with spaces to the width in the spec (if any).
+    fn push_field(&mut self, value: &[u8], spec: &ConversionSpec) {
+        let padding = spec.width.unwrap_or(0).saturating_sub(value.len());
+        if !self.has_room_for(value.len() + padding) {
+            return;
+        }
+
+        if !spec.left_justify {
+            self.result
+                .output
+                .resize(self.result.output.len() + padding, b' ');
+        }
+
+        self.result.output.extend_from_slice(value);
+
+        if spec.left_justify {
+            self.result
+                .output
+                .resize(self.result.output.len() + padding, b' ');
+        }
+    }
+}
+
+fn parse_decimal(chars: &mut std::iter::Peekable<std::str::CharIndices<'_>>) -> Option<u64> {
+    let mut value: Option<u64> = None;
+    while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+        let digit = c.to_digit(10).unwrap_or(0);
+        value = Some(
+            value
+                .unwrap_or(0)
+                .saturating_mul(10)
+                .saturating_add(digit.into()),
+        );
+    }
+
+    value
+}
+
+fn clamp_field_width(width: u64) -> usize {
+    usize::try_from(width).map_or(MAX_FIELD_WIDTH, |width| width.min(MAX_FIELD_WIDTH))
+}
+
+fn truncate(value: &[u8], precision: Option<usize>) -> &[u8] {
+    match precision {
+        Some(precision) if precision < value.len() => &value[..precision],
+        _ => value,
+    }
+}
+
+/// Quotes the given string so it can be reused as shell input, as `%q` does.
+fn quote(s: &str) -> String {
+    if s.is_empty() {
+        String::from("''")
+    } else if s.chars().any(|c| c.is_control()) {
+        escape::quote_if_needed(s, escape::QuoteMode::AnsiC).to_string()
+    } else {
+        escape::quote_if_needed(s, escape::QuoteMode::BackslashEscape).to_string()
+    }
+}
+
+/// Returns the character value of a numeric argument of the form `'c` or `"c`.
+fn char_value_of(arg: &str) -> Option<u32> {
+    let mut chars = arg.chars();
+    match chars.next() {
+        Some('\'' | '"') => Some(chars.next().map_or(0, u32::from)),
+        _ => None,
+    }
+}
+
+struct ParsedInteger {
+    negative: bool,
+    magnitude: u64,
+    overflowed: bool,
+    /// Whether the entire string was consumed.
+    complete: bool,
+}
+
+/// Parses an integer the way `strtoimax(3)` does with a base of 0: leading whitespace and
+/// sign are allowed, a `0x` prefix indicates hexadecimal, and a leading `0` indicates octal.
+fn parse_integer(s: &str) -> ParsedInteger {
+    let bytes = s.as_bytes();
+    let mut index = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+
+    let negative = bytes.get(index) == Some(&b'-');
+    if matches!(bytes.get(index), Some(b'-' | b'+')) {
+        index += 1;
+    }
+
+    let radix = match (bytes.get(index), bytes.get(index + 1), bytes.get(index + 2)) {
+        (Some(b'0'), Some(b'x' | b'X'), Some(c)) if c.is_ascii_hexdigit() => {
+            index += 2;
+            16
+        }
+        (Some(b'0'), _, _) => 8,
+        _ => 10,
+    };
+
+    let digits_start = index;
+    let mut magnitude: u64 = 0;
+    let mut overflowed = false;
+    while let Some(digit) = bytes
+        .get(index)
+        .and_then(|b| char::from(*b).to_digit(radix))
+    {
+        match magnitude
+            .checked_mul(u64::from(radix))
+            .and_then(|m| m.checked_add(u64::from(digit)))
+        {
+            Some(m) => magnitude = m,
+            None => overflowed = true,
+        }
+        index += 1;
+    }
+
+    // If no digits were found, then nothing was consumed (not even whitespace).
+    let consumed = if index == digits_start { 0 } else { index };
+
+    ParsedInteger {
+        negative,
+        magnitude,
+        overflowed,
+        complete: consumed == s.len(),
+    }
+}
+
+/// Parses the longest prefix of the given string that's a floating-point number, the way
+/// `strtod(3)` does. Returns the value and the number of bytes consumed.
+fn parse_float(s: &str) -> (f64, usize) {
+    let bytes = s.as_bytes();
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+
+    let mut index = start;
+    let negative = bytes.get(index) == Some(&b'-');
+    if matches!(bytes.get(index), Some(b'-' | b'+')) {
+        index += 1;
+    }
+
+    let apply_sign = |value: f64| if negative { -value } else { value };
+    let rest = s[index..].to_ascii_lowercase();
+
+    if rest.starts_with("infinity") {
+        return (apply_sign(f64::INFINITY), index + 8);
+    } else if rest.starts_with("inf") {
+        return (apply_sign(f64::INFINITY), index + 3);
+    } else if let Some(after_nan) = rest.strip_prefix("nan") {
+        let mut len = 3;
+        if let Some(paren_len) = after_nan
+            .strip_prefix('(')
+            .and_then(|r| r.find(')'))
+            .filter(|len| {
+                after_nan[1..=*len]
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            })
+        {
+            len += paren_len + 2;
+        }
+        return (apply_sign(f64::NAN), index + len);
+    }
+
+    let digits_in = |from: usize, radix: u32| {
+        bytes[from..]
+            .iter()
+            .take_while(|b| char::from(**b).is_digit(radix))
+            .count()
+    };
+
+    let is_hex = rest.starts_with("0x")
+        && (bytes.get(index + 2).is_some_and(u8::is_ascii_hexdigit)
+            || (bytes.get(index + 2) == Some(&b'.')
+                && bytes.get(index + 3).is_some_and(u8::is_ascii_hexdigit)));
+    let (radix, exponent_char) = if is_hex {
+        index += 2;
+        (16, b'p')
+    } else {
+        (10, b'e')
+    };
+
+    let int_start = index;
+    let int_digits = digits_in(index, radix);
+    index += int_digits;
+
+    let mut frac_digits = 0;
+    if bytes.get(index) == Some(&b'.') {
+        frac_digits = digits_in(index + 1, radix);
+        if int_digits > 0 || frac_digits > 0 {
+            index += 1 + frac_digits;
+        }
+    }
+
+    if int_digits == 0 && frac_digits == 0 {
+        return (0.0, 0);
+    }
+
+    let int_part = &s[int_start..int_start + int_digits];
+    let frac_part = &s[index - frac_digits..index];
+
+    let mut exponent: i32 = 0;
+    if bytes.get(index).map(u8::to_ascii_lowercase) == Some(exponent_char) {
+        let mut exponent_index = index + 1;
+        let exponent_negative = bytes.get(exponent_index) == Some(&b'-');
+        if matches!(bytes.get(exponent_index), Some(b'-' | b'+')) {
+            exponent_index += 1;
+        }
+
+        let exponent_digits = digits_in(exponent_index, 10);
+        if exponent_digits > 0 {
+            exponent = s[exponent_index..exponent_index + exponent_digits]
+                .parse::<i32>()
+                .unwrap_or(i32::MAX);
+            if exponent_negative {
+                exponent = -exponent;
+            }
+            index = exponent_index + exponent_digits;
+        }
+    }
+
+    let value = if radix == 16 {
+        let mantissa = int_part
+            .chars()
+            .chain(frac_part.chars())
+            .filter_map(|c| c.to_digit(16))
+            .fold(0.0, |mantissa, digit| mantissa * 16.0 + f64::from(digit));
+        let frac_bits = i32::try_from(frac_digits)
+            .unwrap_or(i32::MAX)
+            .saturating_mul(4);
+        mantissa * 2f64.powi(exponent.saturating_sub(frac_bits))
+    } else {
+        let int_part = if int_part.is_empty() { "0" } else { int_part };
+        let frac_part = if frac_part.is_empty() { "0" } else { frac_part };
+        std::format!("{int_part}.{frac_part}e{exponent}")
+            .parse::<f64>()
+            .unwrap_or(0.0)
+    };
+
+    (apply_sign(value), index)
+}
+
+fn format_signed_integer(value: i64, spec: &ConversionSpec) -> String {
+    let sign = if value < 0 {
+        "-"
+    } else if spec.force_sign {
+        "+"
+    } else if spec.space_for_sign {
+        " "
+    } else {
+        ""
+    };
+
+    let digits = apply_integer_precision(value.unsigned_abs().to_string(), spec);
+    pad_number(sign, "", &digits, spec, spec.precision.is_none())
+}
+
+fn format_unsigned_integer(value: u64, conversion: char, spec: &ConversionSpec) -> String {
+    let mut prefix = "";
+    let digits = match conversion {
+        'o' => {
+            let mut digits = apply_integer_precision(std::format!("{value:o}"), spec);
+            if spec.alternate_form && !digits.starts_with('0') {
+                digits.insert(0, '0');
+            }
+            digits
+        }
+        'x' => {
+            if spec.alternate_form && value != 0 {
+                prefix = "0x";
+            }
+            apply_integer_precision(std::format!("{value:x}"), spec)
+        }
+        'X' => {
+            if spec.alternate_form && value != 0 {
+                prefix = "0X";
+            }
+            apply_integer_precision(std::format!("{value:X}"), spec)
+        }
+        _ => apply_integer_precision(value.to_string(), spec),
+    };
+
+    pad_number("", prefix, &digits, spec, spec.precision.is_none())
+}
+
+/// Applies an integer conversion's precision, i.e., the minimum number of digits.
+fn apply_integer_precision(digits: String, spec: &ConversionSpec) -> String {
+    match spec.precision {
+        Some(0) if digits == "0" => String::new(),
+        Some(precision) if precision > digits.len() => {
+            std::format!("{}{digits}", "0".repeat(precision - digits.len()))
+        }
+        _ => digits,
+    }
+}
+
+/// Pads a formatted number to the width in the spec; zero-padding goes between the sign (and
+/// any prefix) and the digits.
+fn pad_number(
+    sign: &str,
+    prefix: &str,
+    digits: &str,
+    spec: &ConversionSpec,
+    allow_zero_pad: bool,
+) -> String {
+    let len = sign.len() + prefix.len() + digits.len();
+    let padding = spec.width.unwrap_or(0).saturating_sub(len);
+
+    if spec.zero_pad && allow_zero_pad && !spec.left_justify {
+        std::format!("{sign}{prefix}{}{digits}", "0".repeat(padding))
+    } else {
+        // Space padding is applied when the field is output.
+        std::format!("{sign}{prefix}{digits}")
+    }
+}
+
+fn format_float(value: f64, conversion: char, spec: &ConversionSpec) -> String {
+    let uppercase = conversion.is_ascii_uppercase();
+
+    let sign = if value.is_sign_negative() {
+        "-"
+    } else if spec.force_sign {
+        "+"
+    } else if spec.space_for_sign {
+        " "
+    } else {
+        ""
+    };
+
+    if !value.is_finite() {
+        let body = match (value.is_nan(), uppercase) {
+            (true, false) => "nan",
+            (true, true) => "NAN",
+            (false, false) => "inf",
+            (false, true) => "INF",
+        };
+        return pad_number(sign, "", body, spec, false);
+    }
+
+    let value = value.abs();
+    let precision = spec.precision;
+
+    let (prefix, body) = match conversion.to_ascii_lowercase() {
+        'f' => (
+            "",
+            format_fixed(value, precision.unwrap_or(6), spec.alternate_form),
+        ),
+        'e' => (
+            "",
+            format_exponential(value, precision.unwrap_or(6), spec.alternate_form),
+        ),
+        'g' => (
+            "",
+            format_general(value, precision.unwrap_or(6), spec.alternate_form),
+        ),
+        _ => (
+            "0x",
+            format_hex_float(value, precision, spec.alternate_form),
+        ),
+    };
+
+    let (prefix, body) = if uppercase {
+        (prefix.to_ascii_uppercase(), body.to_ascii_uppercase())
+    } else {
+        (prefix.to_owned(), body)
+    };
+
+    pad_number(sign, &prefix, &body, spec, true)
+}
+
+fn format_fixed(value: f64, precision: usize, alternate_form: bool) -> String {
+    let mut result = std::format!("{value:.precision$}");
+    if alternate_form && precision == 0 {
+        result.push('.');
+    }
+    result
+}
+
+fn format_exponential(value: f64, precision: usize, alternate_form: bool) -> String {
+    let formatted = std::format!("{value:.precision$e}");
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    let point = if alternate_form && precision == 0 {
+        "."
+    } else {
+        ""
+    };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+
+    std::format!(
+        "{mantissa}{point}e{exponent_sign}{:02}",
+        exponent.unsigned_abs()
+    )
+}
+
+fn format_general(value: f64, precision: usize, alternate_form: bool) -> String {
+    let precision = precision.max(1);
+
+    // Determine the exponent the value would have after rounding to the precision.
+    let exponent = if value == 0.0 {
+        0
+    } else {
+        let exponential_precision = precision - 1;
+        let formatted = std::format!("{value:.exponential_precision$e}");
+        formatted
+            .split_once('e')
+            .and_then(|(_, exponent)| exponent.parse::<i64>().ok())
+            .unwrap_or(0)
+    };
+
+    let precision_i64 = i64::try_from(precision).unwrap_or(i64::MAX);
+    let mut result = if exponent >= -4 && exponent < precision_i64 {
+        let fixed_precision = usize::try_from(precision_i64 - 1 - exponent).unwrap_or(0);
+        format_fixed(value, fixed_precision, alternate_form)
+    } else {
+        format_exponential(value, precision - 1, alternate_form)
+    };
+
+    if !alternate_form {
+        let (mantissa, exponent) = match result.find('e') {
+            Some(index) => result.split_at(index),
+            None => (result.as_str(), ""),
+        };
+
+        if mantissa.contains('.') {
+            let mantissa = mantissa.trim_end_matches('0').trim_end_matches('.');
+            result = std::format!("{mantissa}{exponent}");
+        }
+    }
+
+    result
+}
+
+/// Formats the given value in hexadecimal floating-point notation (excluding the `0x` prefix).
+/// N.B. The value is normalized the way it would be for an x87 `long double` (i.e., with a
+/// leading hex digit from 8 to f), since that's what bash uses on common platforms.
+fn format_hex_float(value: f64, precision: Option<usize>, alternate_form: bool) -> String {
+    const FRACTION_DIGITS: usize = 15;
+
+    let (significand, mut exponent) = if value == 0.0 {
+        (0u64, 0i64)
+    } else {
+        let bits = value.to_bits();
+        let biased_exponent = i64::try_from((bits >> 52) & 0x7ff).unwrap_or(0);
+        let mantissa = bits & ((1 << 52) - 1);
+
+        // Normalize the significand so its leading 1 bit is the most significant bit.
+        let (significand, exponent) = if biased_exponent == 0 {
+            let shift = mantissa.leading_zeros();
+            (mantissa << shift, -1011 - i64::from(shift))
+        } else {
+            ((mantissa | (1 << 52)) << 11, biased_exponent - 1023)
+        };
+
+        // The leading hex digit holds 4 bits of the significand.
+        (significand, exponent - 3)
+    };
+
+    let (leading, digits) = match precision {
+        Some(precision) if precision < FRACTION_DIGITS => {
+            // Round to the requested number of digits (ties to even).
+            let dropped_bits = 4 * u32::try_from(FRACTION_DIGITS - precision).unwrap_or(0);
+            let mut kept = significand >> dropped_bits;
+            let remainder = significand & ((1 << dropped_bits) - 1);
+            let half = 1 << (dropped_bits - 1);
+            if remainder > half || (remainder == half && (kept & 1) == 1) {
+                kept += 1;
+            }
+
+            let fraction_bits = 4 * u32::try_from(precision).unwrap_or(0);
+            let mut leading = kept >> fraction_bits;
+            let fraction = kept & ((1 << fraction_bits) - 1);
+
+            // Rounding may carry into a second leading digit.
+            if leading > 0xf {
+                leading >>= 4;
+                exponent += 4;
+            }
+
+            let digits = if precision > 0 {
+                std::format!("{fraction:0precision$x}")
+            } else {
+                String::new()
+            };
+
+            (leading, digits)
+        }
+        _ => {
+            let fraction = significand & ((1 << 60) - 1);
+            let mut digits = std::format!("{fraction:015x}");
+            match precision {
+                Some(precision) => {
+                    digits.push_str("0".repeat(precision - FRACTION_DIGITS).as_str());
+                }
+                None => digits = digits.trim_end_matches('0').to_owned(),
+            }
+
+            (significand >> 60, digits)
+        }
+    };
+
+    let point = if !digits.is_empty() || alternate_form {
+        "."
+    } else {
+        ""
+    };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+
+    std::format!(
+        "{leading:x}{point}{digits}p{exponent_sign}{}",
+        exponent.unsigned_abs()
+    )
+}
+
+fn seconds_since_epoch(time: SystemTime) -> i64 {
+    time.duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| i64::try_from(d.as_secs()).unwrap_or(i64::MAX))
+}
+
+/// Formats the given time (in seconds since the Unix epoch) in the given time zone, using a
+/// `strftime(3)`-style format. An empty format is treated as `%X`.
+fn format_time(time: i64, time_format: &str, time_zone: Option<&str>) -> String {
+    let time_format = if time_format.is_empty() {
+        "%X"
+    } else {
+        time_format
+    };
+
+    datetime::format_time(time, time_format, time_zone)
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn format_str(format_str: &str, args: &[&str]) -> Result<String> {
+        let args: Vec<_> = args.iter().map(|arg| (*arg).to_owned()).collect();
+        let result = format(format_str, &args, SystemTime::now(), Some("UTC0"));
+        Ok(String::from_utf8(result.output)?)
+    }
+
+    #[test]
+    fn test_integers() -> Result<()> {
+        assert_eq!(
+            format_str("[%d][%5d][%-5d][%05d]", &["1", "2", "3", "-4"])?,
+            "[1][    2][3    ][-0004]"
+        );
+        assert_eq!(
+            format_str("[%+d][% d][%.3d][%.0d]", &["1", "2", "3", "0"])?,
+            "[+1][ 2][003][]"
+        );
+        assert_eq!(
+            format_str("[%x][%#X][%#o][%u]", &["255", "255", "8", "-1"])?,
+            "[ff][0XFF][010][18446744073709551615]"
+        );
+        assert_eq!(
+            format_str("[%d][%d][%d]", &["0x1f", "017", "'A"])?,
+            "[31][15][65]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_floats() -> Result<()> {
+        assert_eq!(
+            format_str("[%f][%.2f][%.0f][%.0f]", &["1.5", "3.14159", "2.5", "3.5"])?,
+            "[1.500000][3.14][2][4]"
+        );
+        assert_eq!(
+            format_str("[%e][%.2E][%#.0e]", &["31415.9", "0.000123", "5"])?,
+            "[3.141590e+04][1.23E-04][5.e+00]"
+        );
+        assert_eq!(
+            format_str("[%g][%g][%g][%#g]", &["100000", "1000000", "0.0001", "2"])?,
+            "[100000][1e+06][0.0001][2.00000]"
+        );
+        assert_eq!(
+            format_str("[%a][%a][%A][%.2a][%a]", &["1", "3.5", "10", "1", "0"])?,
+            "[0x8p-3][0xep-2][0XAP+0][0x8.00p-3][0x0p+0]"
+        );
+        assert_eq!(
+            format_str("[%f][%5.1F]", &["-inf", "nan"])?,
+            "[-inf][  NAN]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_strings() -> Result<()> {
+        assert_eq!(
+            format_str(
+                "[%s][%5s][%-5s][%.2s][%*s]",
+                &["a", "b", "c", "def", "3", "g"]
+            )?,
+            "[a][    b][c    ][de][  g]"
+        );
+        assert_eq!(
+            format_str("[%c][%b][%q][%q]", &["xyz", "a\\tb", "a b", ""])?,
+            "[x][a\tb][a\\ b]['']"
+        );
+        assert_eq!(format_str("%b|%s", &["a\\cb", "c"])?, "a");
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_reuse() -> Result<()> {
+        assert_eq!(format_str("%s-%s\n", &["a", "b", "c"])?, "a-b\nc-\n");
+        assert_eq!(format_str("x\n", &["a", "b"])?, "x\n");
+        assert_eq!(format_str("[%d %s]", &[])?, "[0 ]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics() {
+        let args = vec![String::from("12abc"), String::from("99999999999999999999")];
+        let result = format("%d %d %y", &args, SystemTime::now(), None);
+        assert_eq!(
+            String::from_utf8_lossy(&result.output),
+            "12 9223372036854775807 "
+        );
+
+        let messages: Vec<_> = result.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "12abc: invalid number",
+                "warning: 99999999999999999999: Numerical result out of range",
+                "`y': invalid format character"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_field_width_limits() {
+        let args = vec![String::from("1"), String::from("2"), String::from("3")];
+        let result = format(
+            "[%99999999999999999999d][%.3000000000d][%d]",
+            &args,
+            SystemTime::now(),
+            None,
+        );
+        assert_eq!(String::from_utf8_lossy(&result.output), "[][][3]");
+
+        let messages: Vec<_> = result.diagnostics.iter().map(|d| d.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Value too large for defined data type",
+                "Value too large for defined data type"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_time_zones() -> Result<()> {
+        let args = vec![String::from("1721044800")];
+        let format_in = |time_zone| {
+            let result = format("%(%H:%M %Z %z)T", &args, SystemTime::now(), time_zone);
+            String::from_utf8(result.output)
+        };
+
+        assert_eq!(format_in(Some("UTC0"))?, "12:00 UTC +0000");
+        assert_eq!(
+            format_in(Some("EST5EDT,M3.2.0,M11.1.0"))?,
+            "08:00 EDT -0400"
+        );
+        assert_eq!(format_in(Some("<+0530>-5:30"))?, "17:30 +0530 +0530");
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    error,
+    datetime, error,
     shell::Shell,
     sys::{self, users},
 };
@@ -42,17 +42,18 @@ pub(crate) fn format_prompt_piece(
         brush_parser::prompt::PromptPiece::BellCharacter => "\x07".to_owned(),
         brush_parser::prompt::PromptPiece::CarriageReturn => "\r".to_owned(),
         brush_parser::prompt::PromptPiece::CurrentCommandNumber => {
-            return error::unimp("prompt: current command number")
+            shell.current_command_number.to_string()
         }
         brush_parser::prompt::PromptPiece::CurrentHistoryNumber => {
-            return error::unimp("prompt: current history number")
+            let history_len = shell.history.as_ref().map_or(0, |history| history.len());
+            (history_len + 1).to_string()
         }
         brush_parser::prompt::PromptPiece::CurrentUser => users::get_current_username()?,
         brush_parser::prompt::PromptPiece::CurrentWorkingDirectory {
             tilde_replaced,
             basename,
         } => format_current_working_directory(shell, tilde_replaced, basename),
-        brush_parser::prompt::PromptPiece::Date(_) => return error::unimp("prompt: date"),
+        brush_parser::prompt::PromptPiece::Date(format) => format_date(shell, &format),
         brush_parser::prompt::PromptPiece::DollarOrPound => {
             if users::is_root() {
                 "#".to_owned()
@@ -77,9 +78,7 @@ pub(crate) fn format_prompt_piece(
             hn
         }
         brush_parser::prompt::PromptPiece::Newline => "\n".to_owned(),
-        brush_parser::prompt::PromptPiece::NumberOfManagedJobs => {
-            return error::unimp("prompt: number of managed jobs")
-        }
+        brush_parser::prompt::PromptPiece::NumberOfManagedJobs => shell.jobs.jobs.len().to_string(),
         brush_parser::prompt::PromptPiece::ShellBaseName => {
             if let Some(shell_name) = &shell.shell_name {
                 Path::new(shell_name)
@@ -98,14 +97,50 @@ pub(crate) fn format_prompt_piece(
         }
         brush_parser::prompt::PromptPiece::StartNonPrintingSequence => String::new(),
         brush_parser::prompt::PromptPiece::TerminalDeviceBaseName => {
-            return error::unimp("prompt: terminal device base name")
+            sys::terminal::get_terminal_device_path()
+                .and_then(|path| {
+                    path.file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                })
+                .unwrap_or_else(|| "tty".to_owned())
         }
-        brush_parser::prompt::PromptPiece::Time(_) => return error::unimp("prompt: time"),
+        brush_parser::prompt::PromptPiece::Time(format) => format_time(shell, &format),
     };
 
     Ok(formatted)
 }
 
+fn format_date(shell: &Shell, format: &brush_parser::prompt::PromptDateFormat) -> String {
+    match format {
+        brush_parser::prompt::PromptDateFormat::WeekdayMonthDate => format_now(shell, "%a %b %d"),
+        brush_parser::prompt::PromptDateFormat::Custom(custom_format) => {
+            // An empty format results in a locale-specific representation of the time.
+            if custom_format.is_empty() {
+                format_now(shell, "%X")
+            } else {
+                format_now(shell, custom_format)
+            }
+        }
+    }
+}
+
+fn format_time(shell: &Shell, format: &brush_parser::prompt::PromptTimeFormat) -> String {
+    let strftime_format = match format {
+        brush_parser::prompt::PromptTimeFormat::TwelveHourAM => "%I:%M %p",
+        brush_parser::prompt::PromptTimeFormat::TwelveHourHHMMSS => "%I:%M:%S",
+        brush_parser::prompt::PromptTimeFormat::TwentyFourHourHHMMSS => "%H:%M:%S",
+        brush_parser::prompt::PromptTimeFormat::TwentyFourHourHHMM => "%H:%M",
+    };
+
+    format_now(shell, strftime_format)
+}
+
+/// Formats the current time in the shell's time zone using the given strftime-style format
+/// string. If the format string isn't valid, it's returned as-is.
+fn format_now(shell: &Shell, strftime_format: &str) -> String {
+    datetime::format_now(strftime_format, shell.time_zone().as_deref())
+}
+
 fn format_current_working_directory(shell: &Shell, tilde_replaced: bool, basename: bool) -> String {
     let mut working_dir_str = shell.working_dir.to_string_lossy().to_string();
 
@@ -117,6 +152,8 @@ fn format_current_working_directory(shell: &Shell, tilde_replaced: bool, basenam
         if let Some(filename) = Path::new(&working_dir_str).file_name() {
             working_dir_str = filename.to_string_lossy().to_string();
         }
+    } else if let Some(max_components) = get_prompt_dirtrim(shell) {
+        working_dir_str = trim_dir_components(working_dir_str, max_components);
     }
 
     if cfg!(windows) {
@@ -125,3 +162,36 @@ fn format_current_working_directory(shell: &Shell, tilde_replaced: bool, basenam
 
     working_dir_str
 }
+
+/// Returns the number of trailing directory components to retain when displaying
+/// the working directory, per `PROMPT_DIRTRIM`.
+fn get_prompt_dirtrim(shell: &Shell) -> Option<usize> {
+    let (_, var) = shell.env.get("PROMPT_DIRTRIM")?;
+    let max_components = var.value().to_cow_string().parse::<usize>().ok()?;
+    (max_components > 0).then_some(max_components)
+}
+
+/// Replaces all but the last `max_components` components of the given directory path
+/// with an ellipsis (e.g., `/usr/local/share/man` becomes `.../share/man`). A leading
+/// tilde prefix is preserved.
+fn trim_dir_components(dir: String, max_components: usize) -> String {
+    let prefix_len = if dir.starts_with('~') {
+        dir.find('/').map_or(dir.len(), |i| i + 1)
+    } else {
+        0
+    };
+
+    let (prefix, rest) = dir.split_at(prefix_len);
+    if rest.matches('/').count() < max_components {
+        return dir;
+    }
+
+    // Find the separator preceding the components to retain; trimming is skipped if the
+    // ellipsis wouldn't be any shorter than what it replaces.
+    match rest.rmatch_indices('/').nth(max_components - 1) {
+        Some((tail_start, _)) if tail_start > 3 => {
+            std::format!("{prefix}...{}", &rest[tail_start..])
+        }
+        _ => dir,
+    }
+}
@@ -10,10 +10,10 @@ use crate::env::{EnvironmentLookup, EnvironmentScope, ShellEnvironment};
 use crate::interp::{self, Execute, ExecutionParameters, ExecutionResult};
 use crate::options::RuntimeOptions;
 use crate::sys::fs::PathExt;
-use crate::variables::{self, ShellValue, ShellVariable};
+use crate::variables::{self, DynamicValue, ShellValue, ShellVariable};
 use crate::{
-    builtins, commands, completion, env, error, expansion, functions, jobs, keywords, openfiles,
-    patterns, prompt, sys::users, traps,
+    builtins, commands, completion, env, error, expansion, functions, history, hooks, jobs,
+    keywords, locale, openfiles, patterns, prompt, sys, sys::users, traps,
 };
 use crate::{pathcache, trace_categories};
 
@@ -67,6 +67,9 @@ pub struct Shell {
     /// Current line number being processed.
     pub current_line_number: u32,
 
+    /// Number of the command currently being executed, as displayed in prompts (`\#`).
+    pub current_command_number: u32,
+
     /// Completion configuration.
     pub completion_config: completion::Config,
 
@@ -75,6 +78,22 @@ pub struct Shell {
 
     /// Shell program location cache.
     pub program_location_cache: pathcache::PathCache,
+
+    /// Command history, if it's been loaded.
+    pub history: Option<history::History>,
+
+    /// Hooks registered by code embedding the shell.
+    pub hooks: hooks::HookRegistry,
+
+    /// The locale most recently selected by the shell's variables.
+    locale_cache: locale::LocaleCache,
+
+    /// Number of enclosing contexts in which `set -e` is being ignored (e.g., the
+    /// condition of an `if` statement).
+    pub(crate) errexit_suppression_depth: usize,
+
+    /// When the shell was started.
+    pub(crate) start_time: std::time::SystemTime,
 }
 
 impl Clone for Shell {
@@ -96,9 +115,15 @@ impl Clone for Shell {
             script_call_stack: self.script_call_stack.clone(),
             directory_stack: self.directory_stack.clone(),
             current_line_number: self.current_line_number,
+            current_command_number: self.current_command_number,
             completion_config: self.completion_config.clone(),
             builtins: self.builtins.clone(),
             program_location_cache: self.program_location_cache.clone(),
+            history: self.history.clone(),
+            hooks: self.hooks.clone(),
+            locale_cache: self.locale_cache.clone(),
+            errexit_suppression_depth: self.errexit_suppression_depth,
+            start_time: self.start_time,
             depth: self.depth + 1,
         }
     }
@@ -160,6 +185,9 @@ pub struct FunctionCall {
     function_name: String,
     /// The definition of the invoked function.
     function_definition: Arc<brush_parser::ast::FunctionDefinition>,
+    /// The shell's options as of when `local -` was invoked in the function (if it was);
+    /// the `set` options are restored from these when the function returns.
+    saved_options: Option<RuntimeOptions>,
 }
 
 impl Shell {
@@ -169,12 +197,15 @@ impl Shell {
     ///
     /// * `options` - The options to use when creating the shell.
     pub async fn new(options: &CreateOptions) -> Result<Shell, error::Error> {
+        let mut env = Self::initialize_vars(options)?;
+        let working_dir = Self::initialize_working_dir(&mut env)?;
+
         // Instantiate the shell with some defaults.
         let mut shell = Shell {
             traps: traps::TrapHandlerConfig::default(),
             open_files: openfiles::OpenFiles::default(),
-            working_dir: std::env::current_dir()?,
-            env: Self::initialize_vars(options)?,
+            working_dir,
+            env,
             funcs: functions::FunctionEnv::default(),
             options: RuntimeOptions::defaults_from(options),
             jobs: jobs::JobManager::new(),
@@ -187,9 +218,15 @@ impl Shell {
             script_call_stack: VecDeque::new(),
             directory_stack: vec![],
             current_line_number: 0,
+            current_command_number: 1,
             completion_config: completion::Config::default(),
             builtins: builtins::get_default_builtins(options),
             program_location_cache: pathcache::PathCache::default(),
+            history: None,
+            hooks: hooks::HookRegistry::default(),
+            locale_cache: locale::LocaleCache::default(),
+            errexit_suppression_depth: 0,
+            start_time: std::time::SystemTime::now(),
             depth: 0,
         };
 
@@ -203,6 +240,42 @@ impl Shell {
         Ok(shell)
     }
 
+    /// Determines the shell's initial working directory, and updates `PWD` and `OLDPWD`
+    /// accordingly. An inherited `PWD` is retained if it names the current directory.
+    fn initialize_working_dir(env: &mut ShellEnvironment) -> Result<PathBuf, error::Error> {
+        let physical_dir = std::env::current_dir()?;
+
+        let working_dir = env
+            .get_str("PWD")
+            .map(|pwd| PathBuf::from(pwd.as_ref()))
+            .filter(|pwd| {
+                pwd.is_absolute()
+                    && normalize_path(pwd) == *pwd
+                    && pwd.canonicalize().is_ok_and(|p| p == physical_dir)
+            })
+            .unwrap_or(physical_dir);
+
+        let mut pwd_var = ShellVariable::new(working_dir.to_string_lossy().to_string().into());
+        pwd_var.export();
+        env.set_global("PWD", pwd_var)?;
+
+        let mut dirstack_var =
+            ShellVariable::new(ShellValue::Dynamic(DynamicValue::DirectoryStack(vec![
+                working_dir.to_string_lossy().to_string(),
+            ])));
+        dirstack_var.hide_from_enumeration();
+        env.set_global("DIRSTACK", dirstack_var)?;
+
+        if env.get("OLDPWD").is_none() {
+            let mut oldpwd_var =
+                ShellVariable::new(ShellValue::Unset(variables::ShellValueUnsetType::Untyped));
+            oldpwd_var.export();
+            env.set_global("OLDPWD", oldpwd_var)?;
+        }
+
+        Ok(working_dir)
+    }
+
     fn initialize_vars(options: &CreateOptions) -> Result<ShellEnvironment, error::Error> {
         let mut env = ShellEnvironment::new();
 
@@ -215,21 +288,8 @@ impl Shell {
             }
         }
 
-        // Set some additional ones.
-        #[cfg(unix)]
-        {
-            let mut euid_var = ShellVariable::new(ShellValue::String(format!(
-                "{}",
-                uzers::get_effective_uid()
-            )));
-            euid_var.set_readonly();
-            env.set_global("EUID", euid_var)?;
-        }
-
-        let mut random_var = ShellVariable::new(ShellValue::Random);
-        random_var.hide_from_enumeration();
-        random_var.treat_as_integer();
-        env.set_global("RANDOM", random_var)?;
+        Self::add_process_vars(&mut env)?;
+        Self::add_dynamic_vars(&mut env)?;
 
         // Parsing and completion vars
         env.set_global("IFS", ShellVariable::new(" \t\n".into()))?;
@@ -312,6 +372,82 @@ impl Shell {
         Ok(env)
     }
 
+    /// Adds variables describing the shell process: its user and parent process IDs, and
+    /// its nesting level.
+    fn add_process_vars(env: &mut ShellEnvironment) -> Result<(), error::Error> {
+        #[cfg(unix)]
+        {
+            let mut euid_var = ShellVariable::new(ShellValue::String(format!(
+                "{}",
+                uzers::get_effective_uid()
+            )));
+            euid_var.treat_as_integer();
+            euid_var.set_readonly();
+            env.set_global("EUID", euid_var)?;
+        }
+
+        if let Ok(uid) = users::get_current_uid() {
+            let mut uid_var = ShellVariable::new(uid.to_string().into());
+            uid_var.treat_as_integer();
+            uid_var.set_readonly();
+            env.set_global("UID", uid_var)?;
+        }
+
+        if let Some(ppid) = sys::terminal::get_parent_process_id() {
+            let mut ppid_var = ShellVariable::new(ppid.to_string().into());
+            ppid_var.treat_as_integer();
+            ppid_var.set_readonly();
+            env.set_global("PPID", ppid_var)?;
+        }
+
+        // Increment the inherited shell level.
+        let shell_level = env
+            .get_str("SHLVL")
+            .and_then(|level| level.parse::<i64>().ok())
+            .unwrap_or(0)
+            .saturating_add(1);
+        let shell_level = match shell_level {
+            i64::MIN..=0 => 0,
+            1..=999 => shell_level,
+            _ => {
+                tracing::warn!("shell level ({shell_level}) too high, resetting to 1");
+                1
+            }
+        };
+        let mut shlvl_var = ShellVariable::new(shell_level.to_string().into());
+        shlvl_var.export();
+        env.set_global("SHLVL", shlvl_var)?;
+
+        Ok(())
+    }
+
+    /// Adds variables whose values are computed on each access (e.g., `RANDOM`, `SECONDS`).
+    fn add_dynamic_vars(env: &mut ShellEnvironment) -> Result<(), error::Error> {
+        let mut random_var = ShellVariable::new(ShellValue::Random);
+        random_var.hide_from_enumeration();
+        random_var.treat_as_integer();
+        env.set_global("RANDOM", random_var)?;
+
+        // Dynamic vars
+        for (name, value, treat_as_integer) in [
+            ("SECONDS", DynamicValue::seconds(), true),
+            ("EPOCHSECONDS", DynamicValue::EpochSeconds, false),
+            ("EPOCHREALTIME", DynamicValue::EpochRealTime, false),
+            ("SRANDOM", DynamicValue::SecureRandom, true),
+            ("BASHPID", DynamicValue::ProcessId, true),
+            ("GROUPS", DynamicValue::GroupIds, false),
+        ] {
+            let mut var = ShellVariable::new(ShellValue::Dynamic(value));
+            var.hide_from_enumeration();
+            if treat_as_integer {
+                var.treat_as_integer();
+            }
+            env.set_global(name, var)?;
+        }
+
+        Ok(())
+    }
+
     async fn load_config(&mut self, options: &CreateOptions) -> Result<(), error::Error> {
         let mut params = self.default_exec_params();
         params.process_group_policy = interp::ProcessGroupPolicy::SameProcessGroup;
@@ -456,9 +592,6 @@ impl Shell {
         let mut parser =
             brush_parser::Parser::new(&mut reader, &self.parser_options(), source_info);
 
-        tracing::debug!(target: trace_categories::PARSE, "Parsing sourced file: {}", source_info.source);
-        let parse_result = parser.parse();
-
         let mut other_positional_parameters = args.iter().map(|s| s.as_ref().to_owned()).collect();
         let mut other_shell_name = Some(source_info.source.clone());
 
@@ -473,9 +606,31 @@ impl Shell {
             .push_front(source_info.source.clone());
         self.update_bash_source_var()?;
 
-        let result = self
-            .run_parsed_result(parse_result, source_info, params)
-            .await;
+        // Each command is parsed only after the preceding ones have executed, so that any
+        // aliases they define apply to it.
+        tracing::debug!(target: trace_categories::PARSE, "Parsing sourced file: {}", source_info.source);
+        let mut result = Ok(ExecutionResult::success());
+        loop {
+            let aliases = self.options.expand_aliases.then_some(&self.aliases);
+            let parse_result = match parser.parse_next_command(aliases) {
+                Ok(Some(program)) => Ok(program),
+                Ok(None) => break,
+                Err(e) => Err(e),
+            };
+
+            let parse_failed = parse_result.is_err();
+            result = self
+                .run_parsed_result(parse_result, source_info, params)
+                .await;
+
+            if parse_failed
+                || result.as_ref().map_or(true, |result| {
+                    result.exit_shell || result.return_from_function_or_script
+                })
+            {
+                break;
+            }
+        }
 
         self.script_call_stack.pop_front();
         self.update_bash_source_var()?;
@@ -568,7 +723,18 @@ impl Shell {
         &self,
         s: String,
     ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
-        parse_string_impl(s, self.parser_options())
+        // N.B. Aliases can affect parsing, so they're part of what's cached.
+        let mut aliases: Vec<(String, String)> = if self.options.expand_aliases {
+            self.aliases
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect()
+        } else {
+            vec![]
+        };
+        aliases.sort_unstable();
+
+        parse_string_impl(s, self.parser_options(), aliases)
     }
 
     /// Applies basic shell expansion to the provided string.
@@ -639,7 +805,14 @@ impl Shell {
                 Err(e) => {
                     tracing::error!("error: {:#}", e);
                     self.last_exit_status = 1;
-                    ExecutionResult::new(1)
+
+                    // Some errors (e.g., expanding an unset variable with `set -u` in effect)
+                    // abort only the current command in interactive shells, but cause
+                    // non-interactive shells to exit.
+                    let mut result = ExecutionResult::new(1);
+                    result.exit_shell =
+                        !self.options.interactive && e.is_fatal_to_noninteractive_shell();
+                    result
                 }
             },
             Err(brush_parser::ParseError::ParsingNearToken(token_near_error)) => {
@@ -801,6 +974,22 @@ impl Shell {
         }
     }
 
+    /// Returns the locale selected by the shell's current variables; reflects the current
+    /// state of the shell and may change over time. The locale is only rebuilt when the
+    /// variables that select it change.
+    pub fn locale(&self) -> locale::Locale {
+        self.locale_cache.get(&self.env)
+    }
+
+    /// Returns the value of the shell's `TZ` variable, which selects the time zone used when
+    /// formatting times. As in bash, the variable only takes effect if it's exported.
+    pub(crate) fn time_zone(&self) -> Option<String> {
+        self.env
+            .get("TZ")
+            .filter(|(_, var)| var.is_exported() && !matches!(var.value(), ShellValue::Unset(_)))
+            .map(|(_, var)| var.value().to_cow_string().to_string())
+    }
+
     /// Returns whether or not the shell is actively executing in a shell function.
     pub(crate) fn in_function(&self) -> bool {
         !self.function_call_stack.is_empty()
@@ -821,6 +1010,7 @@ impl Shell {
         self.function_call_stack.push_front(FunctionCall {
             function_name: name.to_owned(),
             function_definition: function_def.clone(),
+            saved_options: None,
         });
         self.env.push_scope(env::EnvironmentScope::Local);
         self.update_funcname_var()?;
@@ -831,11 +1021,43 @@ impl Shell {
     /// has exited the top-most function on its call stack.
     pub(crate) fn leave_function(&mut self) -> Result<(), error::Error> {
         self.env.pop_scope(env::EnvironmentScope::Local)?;
-        self.function_call_stack.pop_front();
+
+        if let Some(saved_options) = self
+            .function_call_stack
+            .pop_front()
+            .and_then(|call| call.saved_options)
+        {
+            self.restore_set_options(&saved_options);
+        }
+
         self.update_funcname_var()?;
         Ok(())
     }
 
+    /// Saves the current values of the shell's `set` options so they're restored when
+    /// the currently executing function returns (as requested via `local -`). Returns
+    /// false if no function is executing.
+    pub(crate) fn save_set_options_for_current_function(&mut self) -> bool {
+        let Some(call) = self.function_call_stack.front_mut() else {
+            return false;
+        };
+
+        if call.saved_options.is_none() {
+            call.saved_options = Some(self.options.clone());
+        }
+
+        true
+    }
+
+    fn restore_set_options(&mut self, saved_options: &RuntimeOptions) {
+        for option in crate::namedoptions::SET_OPTIONS
+            .values()
+            .chain(crate::namedoptions::SET_O_OPTIONS.values())
+        {
+            (option.setter)(&mut self.options, (option.getter)(saved_options));
+        }
+    }
+
     fn update_funcname_var(&mut self) -> Result<(), error::Error> {
         //
         // Fill out FUNCNAME[*]
@@ -891,6 +1113,60 @@ impl Shell {
         })
     }
 
+    /// Loads the shell's command history from its history file, if one is set; otherwise,
+    /// starts an empty in-memory history.
+    pub fn load_history(&mut self) -> Result<(), error::Error> {
+        // Multi-line entries are only written when multi-line commands are saved with
+        // embedded newlines.
+        let multiline_entries = self.options.save_multiline_cmds_in_history
+            && self.options.embed_newlines_in_multiline_cmds_in_history;
+
+        let mut history = match self.get_history_file_path() {
+            Some(history_file_path) => {
+                history::History::load(history_file_path.as_path(), multiline_entries)?
+            }
+            None => history::History::new(),
+        };
+
+        if let Some(max_items) = self.get_history_limit("HISTSIZE") {
+            history.truncate(max_items);
+        }
+
+        self.history = Some(history);
+        Ok(())
+    }
+
+    /// Writes any unsaved changes in the shell's command history to its history file,
+    /// first trimming the history to the sizes given by `HISTSIZE` (in memory) and
+    /// `HISTFILESIZE` (on disk).
+    pub fn save_history(&mut self) -> Result<(), error::Error> {
+        let max_items = self.get_history_limit("HISTSIZE");
+        let max_file_items = self.get_history_limit("HISTFILESIZE").or(max_items);
+
+        if let Some(history) = self.history.as_mut() {
+            if let Some(max_items) = max_items {
+                history.truncate(max_items);
+            }
+
+            history.save(max_file_items)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the history size limit given by the named variable, or `None` if unlimited.
+    /// Like bash, negative values mean no limit. Bash initializes these variables to 500 in
+    /// interactive shells; we apply the same default when they're unset or invalid.
+    fn get_history_limit(&self, var_name: &str) -> Option<usize> {
+        const DEFAULT_HISTORY_LIMIT: usize = 500;
+
+        match self.env.get_str(var_name).map(|value| value.parse::<i64>()) {
+            Some(Ok(limit)) if limit < 0 => None,
+            Some(Ok(limit)) => Some(usize::try_from(limit).unwrap_or(usize::MAX)),
+            _ => Some(DEFAULT_HISTORY_LIMIT),
+        }
+    }
+
     /// Returns the number of the line being executed in the currently executing program.
     pub(crate) fn get_current_input_line_number(&self) -> u32 {
         self.current_line_number
@@ -926,6 +1202,24 @@ impl Shell {
             .await
     }
 
+    /// Generates basic command completions for the shell, without invoking any programmable
+    /// completion specs. This is cheap enough to run on every keystroke.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input string to generate completions for.
+    /// * `position` - The position in the input string to generate completions at.
+    pub async fn get_basic_completions(
+        &mut self,
+        input: &str,
+        position: usize,
+    ) -> Result<completion::Completions, error::Error> {
+        let completion_config = self.completion_config.clone();
+        completion_config
+            .get_basic_completions(self, input, position)
+            .await
+    }
+
     /// Finds executables in the shell's current default PATH, matching the given glob pattern.
     ///
     /// # Arguments
@@ -940,6 +1234,8 @@ impl Shell {
             let pattern =
                 patterns::Pattern::from(std::format!("{dir_str}/{required_glob_pattern}"))
                     .set_extended_globbing(self.options.extended_globbing)
+                    .set_locale(self.locale())
+                    .set_ranges_use_c_locale(self.options.glob_ranges_use_c_locale)
                     .set_case_insensitive(self.options.case_insensitive_pathname_expansion);
 
             // TODO: Pass through quoting.
@@ -963,13 +1259,25 @@ impl Shell {
         &self,
         candidate_name: S,
     ) -> Option<PathBuf> {
-        for dir_str in self.env.get_str("PATH").unwrap_or_default().split(':') {
-            let candidate_path = Path::new(dir_str).join(candidate_name.as_ref());
-            if candidate_path.executable() {
-                return Some(candidate_path);
-            }
-        }
-        None
+        find_first_executable_in_dirs(
+            self.env.get_str("PATH").unwrap_or_default().split(':'),
+            candidate_name.as_ref(),
+        )
+    }
+
+    /// Determines whether the given filename is the name of an executable in one of the
+    /// directories in the system's default search path for standard utilities, ignoring
+    /// the shell's current PATH. If found, returns the path.
+    ///
+    /// # Arguments
+    ///
+    /// * `candidate_name` - The name of the file to look for.
+    pub fn find_first_executable_in_default_path<S: AsRef<str>>(
+        &self,
+        candidate_name: S,
+    ) -> Option<PathBuf> {
+        let dirs = sys::fs::get_default_executable_search_paths();
+        find_first_executable_in_dirs(dirs.iter().map(String::as_str), candidate_name.as_ref())
     }
 
     /// Uses the shell's hash-based path cache to check whether the given filename is the name
@@ -1038,14 +1346,35 @@ impl Shell {
         Ok(std::fs::File::open(path_to_open)?.into())
     }
 
-    /// Sets the shell's current working directory to the given path.
+    /// Sets the shell's current working directory to the given path. Symbolic links in
+    /// the path are only resolved if the `physical` option (`set -P`) is enabled.
     ///
     /// # Arguments
     ///
     /// * `target_dir` - The path to set as the working directory.
     pub fn set_working_dir(&mut self, target_dir: &Path) -> Result<(), error::Error> {
+        let physical = self.options.do_not_resolve_symlinks_when_changing_dir;
+        self.set_working_dir_with_options(target_dir, physical)
+    }
+
+    /// Sets the shell's current working directory to the given path.
+    ///
+    /// # Arguments
+    ///
+    /// * `target_dir` - The path to set as the working directory.
+    /// * `physical` - Whether to resolve symbolic links in the path. If not, `.` and `..`
+    ///   components are removed lexically, and the resulting logical path is retained
+    ///   in `PWD`.
+    pub fn set_working_dir_with_options(
+        &mut self,
+        target_dir: &Path,
+        physical: bool,
+    ) -> Result<(), error::Error> {
         let abs_path = self.get_absolute_path(target_dir);
 
+        // Make sure pending changes to `DIRSTACK` aren't lost when it's updated below.
+        self.apply_dirstack_var_updates();
+
         match std::fs::metadata(&abs_path) {
             Ok(m) => {
                 if !m.is_dir() {
@@ -1057,8 +1386,14 @@ impl Shell {
             }
         }
 
-        // TODO: Don't canonicalize, just normalize.
-        let cleaned_path = abs_path.canonicalize()?;
+        // If the logical path doesn't name a directory (e.g., because a `..` component
+        // followed a symbolic link), then we fall back to the physical path.
+        let logical_path = normalize_path(&abs_path);
+        let cleaned_path = if !physical && logical_path.is_dir() {
+            logical_path
+        } else {
+            abs_path.canonicalize()?
+        };
 
         let pwd = cleaned_path.to_string_lossy().to_string();
 
@@ -1085,9 +1420,109 @@ impl Shell {
             EnvironmentScope::Global,
         )?;
 
+        self.update_dirstack_var();
+
+        Ok(())
+    }
+
+    /// Sets the shell's current working directory to the given path, as with
+    /// [`Shell::set_working_dir_with_options`], and then invokes the hooks for the directory
+    /// change (if the directory actually changed).
+    ///
+    /// # Arguments
+    ///
+    /// * `target_dir` - The path to set as the working directory.
+    /// * `physical` - Whether to resolve symbolic links in the path.
+    pub async fn set_working_dir_and_run_hooks(
+        &mut self,
+        target_dir: &Path,
+        physical: bool,
+    ) -> Result<(), error::Error> {
+        let old_dir = self.working_dir.clone();
+        self.set_working_dir_with_options(target_dir, physical)?;
+
+        if old_dir != self.working_dir {
+            let event = hooks::HookEvent::DirectoryChange {
+                old_dir,
+                new_dir: self.working_dir.clone(),
+            };
+
+            // The directory change has already taken effect; a failing hook shouldn't make
+            // it look otherwise to callers.
+            if let Err(e) = self.run_hooks(&event).await {
+                tracing::error!("directory change hook failed: {e}");
+            }
+        }
+
         Ok(())
     }
 
+    /// Invokes the hooks for the given event: first those registered in [`Shell::hooks`], then
+    /// any shell functions listed in the event's array variable (e.g.,
+    /// `BRUSH_PREEXEC_FUNCTIONS`). The exit status of the last command is preserved.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event that occurred.
+    pub async fn run_hooks(&mut self, event: &hooks::HookEvent) -> Result<(), error::Error> {
+        for hook in self.hooks.hooks() {
+            hook(self, event);
+        }
+
+        let function_names = self
+            .env
+            .get(event.functions_var_name())
+            .map(|(_, var)| var.value().get_element_values())
+            .unwrap_or_default();
+
+        if function_names.is_empty() {
+            return Ok(());
+        }
+
+        let args = event.function_args();
+        let args: Vec<_> = args.iter().map(|arg| arg.as_str()).collect();
+
+        let prev_last_result = self.last_exit_status;
+        for function_name in function_names {
+            // Names of functions that aren't defined are ignored.
+            if self.funcs.get(function_name.as_str()).is_some() {
+                self.invoke_function(function_name.as_str(), &args).await?;
+            }
+        }
+        self.last_exit_status = prev_last_result;
+
+        Ok(())
+    }
+
+    /// Updates the directory stack with any changes made to its entries by assigning
+    /// to elements of the `DIRSTACK` variable.
+    pub(crate) fn apply_dirstack_var_updates(&mut self) {
+        if let Some((_, var)) = self.env.get("DIRSTACK") {
+            if let ShellValue::Dynamic(DynamicValue::DirectoryStack(entries)) = var.value() {
+                if entries.len() == self.directory_stack.len() + 1 {
+                    for (dir, entry) in self.directory_stack.iter_mut().rev().zip(&entries[1..]) {
+                        *dir = PathBuf::from(entry);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Updates the `DIRSTACK` variable to reflect the current working directory and
+    /// the contents of the directory stack.
+    pub(crate) fn update_dirstack_var(&mut self) {
+        let entries = std::iter::once(&self.working_dir)
+            .chain(self.directory_stack.iter().rev())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+
+        if let Some((_, var)) = self.env.get_mut("DIRSTACK") {
+            if let ShellValue::Dynamic(DynamicValue::DirectoryStack(existing)) = var.value_mut() {
+                *existing = entries;
+            }
+        }
+    }
+
     /// Tilde-shortens the given string, replacing the user's home directory with a tilde.
     ///
     /// # Arguments
@@ -1095,8 +1530,13 @@ impl Shell {
     /// * `s` - The string to shorten.
     pub(crate) fn tilde_shorten(&self, s: String) -> String {
         if let Some(home_dir) = self.get_home_dir() {
-            if let Some(stripped) = s.strip_prefix(home_dir.to_string_lossy().as_ref()) {
-                return format!("~{stripped}");
+            let home_dir = home_dir.to_string_lossy();
+            if home_dir.len() > 1 {
+                if let Some(stripped) = s.strip_prefix(home_dir.as_ref()) {
+                    if stripped.is_empty() || stripped.starts_with('/') {
+                        return format!("~{stripped}");
+                    }
+                }
             }
         }
         s
@@ -1133,12 +1573,17 @@ impl Shell {
     /// # Arguments
     ///
     /// * `command` - The command to trace.
-    pub(crate) fn trace_command<S: AsRef<str>>(&self, command: S) -> Result<(), std::io::Error> {
-        // TODO: get prefix from PS4
-        const DEFAULT_PREFIX: &str = "+ ";
-
-        let mut prefix = DEFAULT_PREFIX.to_owned();
+    pub(crate) async fn trace_command<S: AsRef<str>>(
+        &mut self,
+        command: S,
+    ) -> Result<(), error::Error> {
+        // Commands run while expanding PS4 (e.g., via command substitution) aren't traced.
+        let saved_xtrace = std::mem::replace(&mut self.options.print_commands_and_arguments, false);
+        let prefix = self.prompt_from_var_or_default("PS4", "+ ").await;
+        self.options.print_commands_and_arguments = saved_xtrace;
+        let mut prefix = prefix?;
 
+        // The first character of the prefix is replicated to indicate the level of indirection.
         let additional_depth = self.script_call_stack.len() + self.depth;
         if let Some(c) = prefix.chars().next() {
             for _ in 0..additional_depth {
@@ -1146,7 +1591,8 @@ impl Shell {
             }
         }
 
-        writeln!(self.stderr(), "{prefix}{}", command.as_ref())
+        writeln!(self.stderr(), "{prefix}{}", command.as_ref())?;
+        Ok(())
     }
 
     /// Returns the keywords that are reserved by the shell.
@@ -1198,6 +1644,7 @@ impl Shell {
 fn parse_string_impl(
     s: String,
     parser_options: brush_parser::ParserOptions,
+    aliases: Vec<(String, String)>,
 ) -> Result<brush_parser::ast::Program, brush_parser::ParseError> {
     let mut reader = std::io::BufReader::new(s.as_bytes());
     let source_info = brush_parser::SourceInfo {
@@ -1205,7 +1652,40 @@ fn parse_string_impl(
     };
     let mut parser: brush_parser::Parser<&mut std::io::BufReader<&[u8]>> =
         brush_parser::Parser::new(&mut reader, &parser_options, &source_info);
+    if !aliases.is_empty() {
+        parser = parser.set_aliases(&aliases.into_iter().collect());
+    }
 
     tracing::debug!(target: trace_categories::PARSE, "Parsing string as program...");
     parser.parse()
 }
+
+fn find_first_executable_in_dirs<'a>(
+    dirs: impl Iterator<Item = &'a str>,
+    candidate_name: &str,
+) -> Option<PathBuf> {
+    for dir_str in dirs {
+        let candidate_path = Path::new(dir_str).join(candidate_name);
+        if candidate_path.executable() {
+            return Some(candidate_path);
+        }
+    }
+    None
+}
+
+/// Removes `.` and `..` components from the given absolute path, without consulting the
+/// file system (and hence without resolving symbolic links).
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => (),
+            std::path::Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
@@ -25,6 +25,8 @@ pub(crate) mod tokio_process;
 
 pub(crate) mod fs;
 
+pub(crate) use platform::dynlib;
+pub(crate) use platform::locale;
 pub(crate) use platform::network;
 pub(crate) use platform::pipes;
 pub(crate) use platform::process;
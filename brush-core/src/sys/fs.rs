@@ -19,3 +19,12 @@ pub(crate) trait PathExt {
     fn exists_and_is_setuid(&self) -> bool;
     fn exists_and_is_sticky_bit(&self) -> bool;
 }
+
+/// Returns whether the given path refers to a regular file that the current user may execute.
+///
+/// # Arguments
+///
+/// * `path` - The path to check.
+pub fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file() && path.executable()
+}
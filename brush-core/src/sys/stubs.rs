@@ -4,7 +4,9 @@
 #![allow(clippy::unused_self)]
 #![allow(clippy::unnecessary_wraps)]
 
+pub(crate) mod dynlib;
 pub(crate) mod fs;
+pub(crate) mod locale;
 pub(crate) mod network;
 pub(crate) mod pipes;
 pub(crate) mod process;
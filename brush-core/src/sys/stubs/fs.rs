@@ -49,6 +49,24 @@ pub(crate) trait StubMetadataExt {
     fn uid(&self) -> u32 {
         0
     }
+
+    fn dev(&self) -> u64 {
+        0
+    }
+
+    fn ino(&self) -> u64 {
+        0
+    }
 }
 
 impl StubMetadataExt for std::fs::Metadata {}
+
+pub(crate) fn get_default_executable_search_paths() -> Vec<String> {
+    vec![]
+}
+
+pub(crate) struct FileLock;
+
+pub(crate) fn lock_file_exclusive(_file: &std::fs::File) -> std::io::Result<FileLock> {
+    Ok(FileLock)
+}
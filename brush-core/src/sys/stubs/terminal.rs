@@ -1,4 +1,5 @@
 use crate::{error, sys};
+use std::path::PathBuf;
 
 #[derive(Clone)]
 pub(crate) struct TerminalSettings {}
@@ -32,6 +33,10 @@ pub(crate) fn is_stdin_a_terminal() -> Result<bool, error::Error> {
     Ok(false)
 }
 
+pub(crate) fn get_terminal_device_path() -> Option<PathBuf> {
+    None
+}
+
 pub(crate) fn get_parent_process_id() -> Option<sys::process::ProcessId> {
     None
 }
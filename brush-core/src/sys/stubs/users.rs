@@ -13,6 +13,10 @@ pub(crate) fn is_root() -> bool {
     false
 }
 
+pub(crate) fn get_current_uid() -> Result<u32, error::Error> {
+    error::unimp("get current uid")
+}
+
 pub(crate) fn get_effective_uid() -> Result<u32, error::Error> {
     error::unimp("get effective uid")
 }
@@ -21,6 +25,10 @@ pub(crate) fn get_effective_gid() -> Result<u32, error::Error> {
     error::unimp("get effective gid")
 }
 
+pub(crate) fn get_user_group_ids() -> Result<Vec<u32>, error::Error> {
+    Ok(vec![])
+}
+
 pub(crate) fn get_current_username() -> Result<String, error::Error> {
     error::unimp("get current username")
 }
@@ -1,5 +1,7 @@
+pub(crate) mod dynlib;
 pub(crate) use crate::sys::os_pipe as pipes;
 pub(crate) mod fs;
+pub(crate) mod locale;
 pub(crate) mod network;
 pub(crate) use crate::sys::tokio_process as process;
 pub(crate) mod signal;
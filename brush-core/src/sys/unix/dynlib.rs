@@ -0,0 +1,112 @@
+use std::ffi::{c_void, CStr, CString};
+use std::io::Write;
+use std::os::fd::RawFd;
+
+use crate::error;
+
+/// A shared object loaded into the shell process; it's unloaded when dropped.
+pub(crate) struct SharedObject {
+    handle: *mut c_void,
+}
+
+// SAFETY: The handle is only used with the dynamic loader's functions, which are thread-safe.
+unsafe impl Send for SharedObject {}
+unsafe impl Sync for SharedObject {}
+
+impl SharedObject {
+    /// Loads the shared object at the given path.
+    pub fn open(path: &str) -> Result<Self, error::Error> {
+        let c_path = CString::new(path).map_err(|_| error::Error::InvalidArguments)?;
+
+        // SAFETY: The path is a valid nul-terminated string.
+        let handle = unsafe { nix::libc::dlopen(c_path.as_ptr(), nix::libc::RTLD_NOW) };
+        if handle.is_null() {
+            return Err(error::Error::DynamicLoadError(last_dl_error()));
+        }
+
+        Ok(Self { handle })
+    }
+
+    /// Looks up the address of the named symbol in the shared object.
+    pub fn symbol(&self, name: &str) -> Result<*mut c_void, error::Error> {
+        let c_name = CString::new(name).map_err(|_| error::Error::InvalidArguments)?;
+
+        // SAFETY: The handle is open, and the name is a valid nul-terminated string.
+        let symbol = unsafe { nix::libc::dlsym(self.handle, c_name.as_ptr()) };
+        if symbol.is_null() {
+            return Err(error::Error::DynamicLoadError(last_dl_error()));
+        }
+
+        Ok(symbol)
+    }
+}
+
+impl Drop for SharedObject {
+    fn drop(&mut self) {
+        // SAFETY: The handle was returned by a successful call to dlopen and hasn't been
+        // closed yet.
+        unsafe { nix::libc::dlclose(self.handle) };
+    }
+}
+
+fn last_dl_error() -> String {
+    // SAFETY: dlerror returns either null or a valid nul-terminated string.
+    let message = unsafe { nix::libc::dlerror() };
+    if message.is_null() {
+        String::from("unknown error")
+    } else {
+        // SAFETY: See above.
+        unsafe { CStr::from_ptr(message) }
+            .to_string_lossy()
+            .to_string()
+    }
+}
+
+/// Runs the given function with the process's standard input, output, and error
+/// temporarily redirected to the given file descriptors (where provided). This allows
+/// code that writes directly to the standard file descriptors to honor the shell's
+/// redirections.
+pub(crate) fn with_stdio_redirected<R>(fds: [Option<RawFd>; 3], f: impl FnOnce() -> R) -> R {
+    // Make sure anything already buffered makes it to the original destination.
+    flush_stdio();
+
+    let mut saved_fds = vec![];
+    for (target_fd, source_fd) in (0..).zip(fds) {
+        let Some(source_fd) = source_fd else {
+            continue;
+        };
+
+        if source_fd == target_fd {
+            continue;
+        }
+
+        if let Ok(saved_fd) = nix::unistd::dup(target_fd) {
+            if nix::unistd::dup2(source_fd, target_fd).is_ok() {
+                saved_fds.push((target_fd, saved_fd));
+            } else {
+                let _ = nix::unistd::close(saved_fd);
+            }
+        }
+    }
+
+    let result = f();
+
+    // Likewise, make sure anything the function buffered makes it to the intended destination.
+    flush_stdio();
+
+    for (target_fd, saved_fd) in saved_fds {
+        let _ = nix::unistd::dup2(saved_fd, target_fd);
+        let _ = nix::unistd::close(saved_fd);
+    }
+
+    result
+}
+
+/// Flushes output buffered by both the Rust standard library and the C runtime.
+fn flush_stdio() {
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+
+    // SAFETY: Passing null flushes all open output streams.
+    unsafe { nix::libc::fflush(std::ptr::null_mut()) };
+}
@@ -56,3 +56,55 @@ fn try_get_file_type(path: &Path) -> Option<std::fs::FileType> {
 fn try_get_file_mode(path: &Path) -> Option<u32> {
     path.metadata().map(|metadata| metadata.mode()).ok()
 }
+
+/// An exclusive advisory lock on a file, released when dropped.
+pub(crate) struct FileLock {
+    _flock: nix::fcntl::Flock<std::fs::File>,
+}
+
+/// Acquires an exclusive advisory lock on the given file, blocking until it's available.
+pub(crate) fn lock_file_exclusive(file: &std::fs::File) -> std::io::Result<FileLock> {
+    nix::fcntl::Flock::lock(file.try_clone()?, nix::fcntl::FlockArg::LockExclusive)
+        .map(|flock| FileLock { _flock: flock })
+        .map_err(|(_, errno)| errno.into())
+}
+
+/// Returns the system's default search path for standard utilities, i.e., the value
+/// reported by `getconf PATH`.
+pub(crate) fn get_default_executable_search_paths() -> Vec<String> {
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    if let Some(path) = confstr_path() {
+        return path.split(':').map(|dir| dir.to_owned()).collect();
+    }
+
+    // Fall back to the directories that POSIX utilities are conventionally found in.
+    vec!["/bin".to_owned(), "/usr/bin".to_owned()]
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn confstr_path() -> Option<String> {
+    // Query the required buffer size (including the terminating nul) first.
+    // SAFETY: A null buffer with a length of 0 is explicitly allowed, and causes nothing to be
+    // written.
+    let len = unsafe { nix::libc::confstr(nix::libc::_CS_PATH, std::ptr::null_mut(), 0) };
+    if len == 0 {
+        return None;
+    }
+
+    let mut buffer = vec![0u8; len];
+    // SAFETY: The buffer is valid for writes of the given length, and confstr writes no more
+    // than that (truncating and nul-terminating the value if needed).
+    let written = unsafe {
+        nix::libc::confstr(
+            nix::libc::_CS_PATH,
+            buffer.as_mut_ptr().cast(),
+            buffer.len(),
+        )
+    };
+    if written == 0 || written > buffer.len() {
+        return None;
+    }
+
+    let path = std::ffi::CStr::from_bytes_until_nul(&buffer).ok()?;
+    path.to_str().ok().map(|s| s.to_owned())
+}
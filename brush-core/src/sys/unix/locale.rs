@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+use std::ffi::CString;
+
+/// Compares strings according to the collation rules of a named locale.
+pub(crate) struct Collator {
+    locale: nix::libc::locale_t,
+}
+
+// SAFETY: The locale object is never mutated after creation; it's only installed as the
+// calling thread's locale for the duration of a comparison.
+unsafe impl Send for Collator {}
+unsafe impl Sync for Collator {}
+
+impl Collator {
+    /// Creates a collator for the named locale; returns `None` if the locale isn't
+    /// available on this system.
+    pub fn new(name: &str) -> Option<Self> {
+        let c_name = CString::new(name).ok()?;
+
+        // SAFETY: The name is a valid nul-terminated string, and we're not basing the new
+        // locale on an existing one.
+        let locale = unsafe {
+            nix::libc::newlocale(
+                nix::libc::LC_COLLATE_MASK | nix::libc::LC_CTYPE_MASK,
+                c_name.as_ptr(),
+                std::ptr::null_mut(),
+            )
+        };
+
+        if locale.is_null() {
+            None
+        } else {
+            Some(Self { locale })
+        }
+    }
+
+    /// Compares two strings; returns `None` if either contains a nul character.
+    pub fn compare(&self, a: &str, b: &str) -> Option<Ordering> {
+        let a = CString::new(a).ok()?;
+        let b = CString::new(b).ok()?;
+
+        // SAFETY: The locale object is valid, and is only installed for the current thread;
+        // the previously installed locale is restored before returning.
+        let result = unsafe {
+            let previous = nix::libc::uselocale(self.locale);
+            let result = nix::libc::strcoll(a.as_ptr(), b.as_ptr());
+            nix::libc::uselocale(previous);
+            result
+        };
+
+        Some(result.cmp(&0))
+    }
+}
+
+impl Drop for Collator {
+    fn drop(&mut self) {
+        // SAFETY: The locale object was returned by a successful call to newlocale and
+        // hasn't been freed yet.
+        unsafe { nix::libc::freelocale(self.locale) };
+    }
+}
@@ -1,3 +1,89 @@
+use crate::error;
+use std::path::Path;
+
+const DEFAULT_HOSTS_FILE_PATH: &str = "/etc/hosts";
+const SERVICES_FILE_PATH: &str = "/etc/services";
+const SYSTEMD_UNIT_DIRS: &[&str] = &[
+    "/etc/systemd/system",
+    "/run/systemd/system",
+    "/usr/local/lib/systemd/system",
+    "/usr/lib/systemd/system",
+    "/lib/systemd/system",
+];
+const SYSTEMD_SERVICE_SUFFIX: &str = ".service";
+
 pub(crate) fn get_hostname() -> std::io::Result<std::ffi::OsString> {
     crate::sys::hostname::get()
 }
+
+/// Returns the names of hosts known to the local system. If a hosts file is
+/// provided and can be read, it's used in place of the system default.
+///
+/// # Arguments
+///
+/// * `hosts_file` - Optionally provides the path to a hosts-formatted file.
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_known_hostnames(hosts_file: Option<&Path>) -> Result<Vec<String>, error::Error> {
+    let contents = hosts_file
+        .and_then(|p| std::fs::read_to_string(p).ok())
+        .or_else(|| std::fs::read_to_string(DEFAULT_HOSTS_FILE_PATH).ok())
+        .unwrap_or_default();
+
+    Ok(parse_hosts_file(contents.as_str()))
+}
+
+/// Returns the names of services known to the local system; this includes
+/// systemd service units as well as network services listed in the services
+/// database.
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_all_services() -> Result<Vec<String>, error::Error> {
+    let mut services = vec![];
+
+    for dir in SYSTEMD_UNIT_DIRS {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let file_name = entry.file_name();
+            let file_name = file_name.to_string_lossy();
+            if let Some(service_name) = file_name.strip_suffix(SYSTEMD_SERVICE_SUFFIX) {
+                // Skip template units (e.g., getty@.service); they can't be named directly.
+                if !service_name.is_empty() && !service_name.ends_with('@') {
+                    services.push(service_name.to_owned());
+                }
+            }
+        }
+    }
+
+    if let Ok(contents) = std::fs::read_to_string(SERVICES_FILE_PATH) {
+        services.extend(parse_services_file(contents.as_str()));
+    }
+
+    Ok(services)
+}
+
+fn parse_hosts_file(contents: &str) -> Vec<String> {
+    let mut names = vec![];
+
+    for line in contents.lines() {
+        let line = strip_comment(line);
+
+        // The first field is the address; all remaining fields are names or aliases.
+        names.extend(line.split_whitespace().skip(1).map(|s| s.to_owned()));
+    }
+
+    names
+}
+
+fn parse_services_file(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .filter_map(|line| strip_comment(line).split_whitespace().next())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once('#').map_or(line, |(before, _)| before)
+}
@@ -1,5 +1,6 @@
 use crate::{error, sys};
 use std::os::fd::{AsFd, AsRawFd};
+use std::path::PathBuf;
 
 #[derive(Clone)]
 pub(crate) struct TerminalSettings {
@@ -47,6 +48,10 @@ pub(crate) fn is_stdin_a_terminal() -> Result<bool, error::Error> {
     Ok(result)
 }
 
+pub(crate) fn get_terminal_device_path() -> Option<PathBuf> {
+    nix::unistd::ttyname(std::io::stdin()).ok()
+}
+
 #[allow(clippy::unnecessary_wraps)]
 pub(crate) fn get_parent_process_id() -> Option<sys::process::ProcessId> {
     Some(nix::unistd::getppid().as_raw())
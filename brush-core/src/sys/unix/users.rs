@@ -3,6 +3,8 @@ use std::path::PathBuf;
 
 use uzers::os::unix::UserExt;
 
+const GROUP_FILE_PATH: &str = "/etc/group";
+
 pub(crate) fn is_root() -> bool {
     uzers::get_current_uid() == 0
 }
@@ -25,6 +27,11 @@ pub(crate) fn get_current_user_home_dir() -> Option<PathBuf> {
     None
 }
 
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_current_uid() -> Result<u32, error::Error> {
+    Ok(uzers::get_current_uid())
+}
+
 #[allow(clippy::unnecessary_wraps)]
 pub(crate) fn get_effective_uid() -> Result<u32, error::Error> {
     Ok(uzers::get_effective_uid())
@@ -35,6 +42,23 @@ pub(crate) fn get_effective_gid() -> Result<u32, error::Error> {
     Ok(uzers::get_effective_gid())
 }
 
+/// Returns the IDs of the groups the current user is a member of, with the user's
+/// primary group first (as bash orders `GROUPS`).
+pub(crate) fn get_user_group_ids() -> Result<Vec<u32>, error::Error> {
+    let primary_gid = uzers::get_current_gid();
+    let mut group_ids: Vec<u32> = nix::unistd::getgroups()?
+        .into_iter()
+        .map(|gid| gid.as_raw())
+        .collect();
+
+    match group_ids.iter().position(|gid| *gid == primary_gid) {
+        Some(index) => group_ids.swap(0, index),
+        None => group_ids.insert(0, primary_gid),
+    }
+
+    Ok(group_ids)
+}
+
 pub(crate) fn get_current_username() -> Result<String, error::Error> {
     let username = uzers::get_current_username().ok_or_else(|| error::Error::NoCurrentUser)?;
     Ok(username.to_string_lossy().to_string())
@@ -49,7 +73,19 @@ pub(crate) fn get_all_users() -> Result<Vec<String>, error::Error> {
 
 #[allow(clippy::unnecessary_wraps)]
 pub(crate) fn get_all_groups() -> Result<Vec<String>, error::Error> {
-    // TODO: implement this
-    tracing::debug!("UNIMPLEMENTED: get_all_groups");
-    Ok(vec![])
+    // N.B. We read the group database directly rather than enumerating via
+    // getgrent(3), which isn't thread-safe.
+    let Ok(contents) = std::fs::read_to_string(GROUP_FILE_PATH) else {
+        return Ok(vec![]);
+    };
+
+    let groups = contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split(':').next())
+        .filter(|name| !name.is_empty())
+        .map(|name| name.to_owned())
+        .collect();
+
+    Ok(groups)
 }
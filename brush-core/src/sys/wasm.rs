@@ -1,4 +1,6 @@
+pub(crate) use crate::sys::stubs::dynlib;
 pub(crate) use crate::sys::stubs::fs;
+pub(crate) use crate::sys::stubs::locale;
 pub(crate) use crate::sys::stubs::network;
 pub(crate) use crate::sys::stubs::pipes;
 pub(crate) use crate::sys::stubs::process;
@@ -1,5 +1,7 @@
 pub(crate) use crate::sys::os_pipe as pipes;
+pub(crate) use crate::sys::stubs::dynlib;
 pub(crate) use crate::sys::stubs::fs;
+pub(crate) use crate::sys::stubs::locale;
 pub(crate) mod network;
 
 pub(crate) mod signal {
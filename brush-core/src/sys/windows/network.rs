@@ -1,3 +1,18 @@
+use crate::error;
+use std::path::Path;
+
 pub(crate) fn get_hostname() -> std::io::Result<std::ffi::OsString> {
     crate::sys::hostname::get()
 }
+
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_known_hostnames(_hosts_file: Option<&Path>) -> Result<Vec<String>, error::Error> {
+    // TODO: implement some version of this for Windows
+    Ok(vec![])
+}
+
+#[allow(clippy::unnecessary_wraps)]
+pub(crate) fn get_all_services() -> Result<Vec<String>, error::Error> {
+    // TODO: implement some version of this for Windows
+    Ok(vec![])
+}
@@ -2,8 +2,9 @@ use rand::Rng;
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::{Display, Write};
+use std::time::{Instant, SystemTime};
 
-use crate::{error, escape};
+use crate::{error, escape, sys};
 
 /// A shell variable.
 #[derive(Clone, Debug)]
@@ -70,6 +71,11 @@ impl ShellVariable {
         &self.value
     }
 
+    /// Returns a mutable reference to the value associated with the variable.
+    pub(crate) fn value_mut(&mut self) -> &mut ShellValue {
+        &mut self.value
+    }
+
     /// Returns whether or not the variable is exported to child processes.
     pub fn is_exported(&self) -> bool {
         self.exported
@@ -213,32 +219,14 @@ impl ShellVariable {
             return Err(error::Error::ReadonlyVariable);
         }
 
+        // Let dynamic values decide how to handle updates.
+        if let ShellValue::Dynamic(dynamic) = &mut self.value {
+            dynamic.assign(value, append);
+            return Ok(());
+        }
+
         if append {
-            match (&self.value, &value) {
-                // If we're appending an array to a declared-but-unset variable (or appending
-                // anything to a declared-but-unset array), then fill it out first.
-                (ShellValue::Unset(_), ShellValueLiteral::Array(_))
-                | (
-                    ShellValue::Unset(
-                        ShellValueUnsetType::IndexedArray | ShellValueUnsetType::AssociativeArray,
-                    ),
-                    _,
-                ) => {
-                    self.assign(ShellValueLiteral::Array(ArrayLiteral(vec![])), false)?;
-                }
-                // If we're appending a scalar to a declared-but-unset variable, then
-                // start with the empty string. This will result in the right thing happening,
-                // even in treat-as-integer cases.
-                (ShellValue::Unset(_), ShellValueLiteral::Scalar(_)) => {
-                    self.assign(ShellValueLiteral::Scalar(String::new()), false)?;
-                }
-                // If we're trying to append an array to a string, we first promote the string to be
-                // an array with the string being present at index 0.
-                (ShellValue::String(_), ShellValueLiteral::Array(_)) => {
-                    self.convert_to_indexed_array()?;
-                }
-                _ => (),
-            }
+            self.prepare_for_append(&value)?;
 
             let treat_as_int = self.is_treated_as_integer();
 
@@ -280,6 +268,7 @@ impl ShellVariable {
                     }
                 },
                 ShellValue::Unset(_) => unreachable!("covered in conversion above"),
+                ShellValue::Dynamic(_) => unreachable!("covered above"),
                 ShellValue::Random => Ok(()),
             }
         } else {
@@ -325,6 +314,8 @@ impl ShellVariable {
                 // Drop other updates to random values.
                 (ShellValue::Random, _) => Ok(()),
 
+                (ShellValue::Dynamic(_), _) => unreachable!("covered above"),
+
                 // Assign a scalar value to a scalar or unset (and untyped) variable.
                 (ShellValue::String(_) | ShellValue::Unset(_), ShellValueLiteral::Scalar(s)) => {
                     self.value = ShellValue::String(s);
@@ -334,6 +325,39 @@ impl ShellVariable {
         }
     }
 
+    /// Converts the variable's value as needed before the given value is appended to it:
+    /// declared-but-unset variables are filled out, and strings are promoted to arrays when
+    /// an array is being appended.
+    fn prepare_for_append(&mut self, value: &ShellValueLiteral) -> Result<(), error::Error> {
+        match (&self.value, value) {
+            // If we're appending an array to a declared-but-unset variable (or appending
+            // anything to a declared-but-unset array), then fill it out first.
+            (ShellValue::Unset(_), ShellValueLiteral::Array(_))
+            | (
+                ShellValue::Unset(
+                    ShellValueUnsetType::IndexedArray | ShellValueUnsetType::AssociativeArray,
+                ),
+                _,
+            ) => {
+                self.assign(ShellValueLiteral::Array(ArrayLiteral(vec![])), false)?;
+            }
+            // If we're appending a scalar to a declared-but-unset variable, then
+            // start with the empty string. This will result in the right thing happening,
+            // even in treat-as-integer cases.
+            (ShellValue::Unset(_), ShellValueLiteral::Scalar(_)) => {
+                self.assign(ShellValueLiteral::Scalar(String::new()), false)?;
+            }
+            // If we're trying to append an array to a string, we first promote the string to be
+            // an array with the string being present at index 0.
+            (ShellValue::String(_), ShellValueLiteral::Array(_)) => {
+                self.convert_to_indexed_array()?;
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
     /// Assign the given value to the variable at the given index, conditionally appending to the
     /// preexisting value present at that element within the value.
     ///
@@ -408,6 +432,10 @@ impl ShellVariable {
                 }
                 Ok(())
             }
+            ShellValue::Dynamic(dynamic) => {
+                dynamic.assign_at_index(array_index.as_str(), value, append);
+                Ok(())
+            }
             _ => {
                 tracing::error!("assigning to index {array_index} of {:?}", self.value);
                 error::unimp("assigning to index of non-array variable")
@@ -430,6 +458,10 @@ impl ShellVariable {
                 }
             },
             ShellValue::String(_) | ShellValue::Random => Err(error::Error::NotArray),
+            ShellValue::Dynamic(DynamicValue::GroupIds | DynamicValue::DirectoryStack(_)) => {
+                Ok(false)
+            }
+            ShellValue::Dynamic(_) => Err(error::Error::NotArray),
             ShellValue::AssociativeArray(values) => Ok(values.remove(index).is_some()),
             ShellValue::IndexedArray(values) => {
                 let key = index.parse::<u64>().unwrap_or(0);
@@ -444,7 +476,9 @@ impl ShellVariable {
 
         if matches!(
             self.value(),
-            ShellValue::IndexedArray(_) | ShellValue::Unset(ShellValueUnsetType::IndexedArray)
+            ShellValue::IndexedArray(_)
+                | ShellValue::Unset(ShellValueUnsetType::IndexedArray)
+                | ShellValue::Dynamic(DynamicValue::GroupIds | DynamicValue::DirectoryStack(_))
         ) {
             result.push('a');
         }
@@ -494,6 +528,195 @@ pub enum ShellValue {
     IndexedArray(BTreeMap<u64, String>),
     /// A special value that yields a different random number each time its read.
     Random,
+    /// A special value that's computed each time it's read.
+    Dynamic(DynamicValue),
+}
+
+/// A shell value that's computed each time it's read.
+#[derive(Clone, Debug)]
+pub enum DynamicValue {
+    /// The number of seconds elapsed since the count was last reset (`SECONDS`).
+    Seconds {
+        /// When the count was last reset.
+        start: Instant,
+        /// The value the count was last reset to.
+        offset: i64,
+    },
+    /// The number of whole seconds since the Unix epoch (`EPOCHSECONDS`).
+    EpochSeconds,
+    /// The number of seconds since the Unix epoch, with microsecond
+    /// granularity (`EPOCHREALTIME`).
+    EpochRealTime,
+    /// A 32-bit random number drawn from a cryptographically secure
+    /// source (`SRANDOM`).
+    SecureRandom,
+    /// The ID of the current shell process (`BASHPID`).
+    ///
+    /// N.B. Subshells and command substitutions currently run in the same process as
+    /// their parent, so unlike in bash this doesn't change inside them; it only differs
+    /// from `$$` in shells started as separate processes.
+    ProcessId,
+    /// The IDs of the groups the current user is a member of (`GROUPS`).
+    GroupIds,
+    /// The contents of the directory stack (`DIRSTACK`), starting with the current
+    /// working directory. The shell keeps this up to date as the stack changes;
+    /// existing entries other than the first may be modified through assignment.
+    DirectoryStack(Vec<String>),
+    /// The index of the next argument to be processed by `getopts` (`OPTIND`), when
+    /// `getopts` has stopped partway through an argument containing multiple options
+    /// (e.g., `-abc`). Assigning to the variable discards the position within the argument.
+    OptionIndex {
+        /// The index of the argument being processed.
+        index: String,
+        /// The index of the next character to be processed within the argument.
+        next_char_index: usize,
+    },
+}
+
+impl DynamicValue {
+    /// Returns a new `SECONDS`-style value, counting up from zero.
+    pub fn seconds() -> Self {
+        DynamicValue::Seconds {
+            start: Instant::now(),
+            offset: 0,
+        }
+    }
+
+    /// Computes the current value.
+    pub fn get(&self) -> ShellValue {
+        match self {
+            DynamicValue::Seconds { start, offset } => {
+                let elapsed = i64::try_from(start.elapsed().as_secs()).unwrap_or(i64::MAX);
+                offset.saturating_add(elapsed).to_string().into()
+            }
+            DynamicValue::EpochSeconds => get_time_since_epoch().as_secs().to_string().into(),
+            DynamicValue::EpochRealTime => {
+                let now = get_time_since_epoch();
+                std::format!("{}.{:06}", now.as_secs(), now.subsec_micros()).into()
+            }
+            DynamicValue::SecureRandom => rand::random::<u32>().to_string().into(),
+            DynamicValue::ProcessId => std::process::id().to_string().into(),
+            DynamicValue::GroupIds => {
+                let group_ids = sys::users::get_user_group_ids().unwrap_or_default();
+                ShellValue::IndexedArray(
+                    group_ids
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, id)| (i as u64, id.to_string()))
+                        .collect(),
+                )
+            }
+            DynamicValue::DirectoryStack(entries) => {
+                ShellValue::IndexedArray(Self::directory_stack_elements(entries))
+            }
+            DynamicValue::OptionIndex { index, .. } => index.clone().into(),
+        }
+    }
+
+    /// Applies an assignment to the value. Assigning to `SECONDS` resets the count
+    /// to the assigned value, assigning to `DIRSTACK` updates the entries being
+    /// assigned to, and assigning to `OPTIND` restarts option processing at the
+    /// assigned index; assignments to all other dynamic values are ignored.
+    fn assign(&mut self, value: ShellValueLiteral, append: bool) {
+        if let DynamicValue::DirectoryStack(entries) = self {
+            // Scalar assignments target the first entry, which can't be modified.
+            let ShellValueLiteral::Array(literals) = value else {
+                return;
+            };
+
+            // When appending, new elements are indexed past the end of the stack and so
+            // are ignored, but explicitly indexed elements may still update entries.
+            let mut values = if append {
+                Self::directory_stack_elements(entries)
+            } else {
+                BTreeMap::new()
+            };
+            if ShellValue::update_indexed_array_from_literals(&mut values, literals).is_ok() {
+                for (index, value) in values {
+                    Self::update_directory_stack_entry(entries, index, value);
+                }
+            }
+
+            return;
+        }
+
+        if let (DynamicValue::OptionIndex { index, .. }, ShellValueLiteral::Scalar(s)) =
+            (&self, &value)
+        {
+            let new_index = if append {
+                let current = index.parse::<i64>().unwrap_or(0);
+                current.saturating_add(s.parse().unwrap_or(0)).to_string()
+            } else {
+                s.clone()
+            };
+
+            *self = DynamicValue::OptionIndex {
+                index: new_index,
+                next_char_index: 1,
+            };
+            return;
+        }
+
+        let (DynamicValue::Seconds { .. }, ShellValueLiteral::Scalar(s)) = (&self, value) else {
+            return;
+        };
+
+        let mut new_offset = s.parse::<i64>().unwrap_or(0);
+        if append {
+            let current: i64 = self.get().to_cow_string().parse().unwrap_or(0);
+            new_offset = new_offset.saturating_add(current);
+        }
+
+        *self = DynamicValue::Seconds {
+            start: Instant::now(),
+            offset: new_offset,
+        };
+    }
+
+    /// Applies an assignment to the element at the given index of the value.
+    fn assign_at_index(&mut self, index: &str, value: String, append: bool) {
+        let DynamicValue::DirectoryStack(entries) = self else {
+            self.assign(ShellValueLiteral::Scalar(value), append);
+            return;
+        };
+
+        let Ok(index) = index.parse::<u64>() else {
+            return;
+        };
+
+        let value = match usize::try_from(index).ok().and_then(|i| entries.get(i)) {
+            Some(existing) if append => existing.clone() + value.as_str(),
+            _ => value,
+        };
+
+        Self::update_directory_stack_entry(entries, index, value);
+    }
+
+    fn directory_stack_elements(entries: &[String]) -> BTreeMap<u64, String> {
+        entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (i as u64, entry.clone()))
+            .collect()
+    }
+
+    /// Replaces an entry in the directory stack, unless it's the current working directory
+    /// or doesn't exist; the stack can't be grown through assignment.
+    fn update_directory_stack_entry(entries: &mut [String], index: u64, value: String) {
+        if index == 0 {
+            return;
+        }
+
+        if let Some(entry) = usize::try_from(index).ok().and_then(|i| entries.get_mut(i)) {
+            *entry = value;
+        }
+    }
+}
+
+fn get_time_since_epoch() -> std::time::Duration {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
 }
 
 /// The type of an unset shell value.
@@ -590,6 +813,7 @@ impl ShellValue {
             self,
             ShellValue::IndexedArray(_)
                 | ShellValue::AssociativeArray(_)
+                | ShellValue::Dynamic(DynamicValue::GroupIds | DynamicValue::DirectoryStack(_))
                 | ShellValue::Unset(
                     ShellValueUnsetType::IndexedArray | ShellValueUnsetType::AssociativeArray
                 )
@@ -732,6 +956,7 @@ impl ShellValue {
                 Ok(result.into())
             }
             ShellValue::Random => Ok(std::format!("\"{}\"", get_random_str()).into()),
+            ShellValue::Dynamic(dynamic) => Ok(dynamic.get().format(style)?.into_owned().into()),
         }
     }
 
@@ -759,6 +984,10 @@ impl ShellValue {
                 Ok(values.get(&key).map(|s| Cow::Borrowed(s.as_str())))
             }
             ShellValue::Random => Ok(Some(Cow::Owned(get_random_str()))),
+            ShellValue::Dynamic(dynamic) => Ok(dynamic
+                .get()
+                .get_at(index)?
+                .map(|value| Cow::Owned(value.into_owned()))),
         }
     }
 
@@ -769,6 +998,7 @@ impl ShellValue {
             ShellValue::String(_) | ShellValue::Random => vec!["0".to_owned()],
             ShellValue::AssociativeArray(array) => array.keys().map(|k| k.to_owned()).collect(),
             ShellValue::IndexedArray(array) => array.keys().map(|k| k.to_string()).collect(),
+            ShellValue::Dynamic(dynamic) => dynamic.get().get_element_keys(),
         }
     }
 
@@ -780,6 +1010,7 @@ impl ShellValue {
             ShellValue::AssociativeArray(array) => array.values().map(|v| v.to_owned()).collect(),
             ShellValue::IndexedArray(array) => array.values().map(|v| v.to_owned()).collect(),
             ShellValue::Random => vec![get_random_str()],
+            ShellValue::Dynamic(dynamic) => dynamic.get().get_element_values(),
         }
     }
 
@@ -795,6 +1026,7 @@ impl ShellValue {
                 .get(&0)
                 .map_or_else(|| Cow::Borrowed(""), |s| Cow::Borrowed(s.as_str())),
             ShellValue::Random => Cow::Owned(get_random_str()),
+            ShellValue::Dynamic(dynamic) => Cow::Owned(dynamic.get().to_cow_string().into_owned()),
         }
     }
 
@@ -819,6 +1051,7 @@ impl ShellValue {
                 }
             }
             ShellValue::Random => quote_str_for_assignment(get_random_str().as_str()),
+            ShellValue::Dynamic(dynamic) => dynamic.get().to_assignable_str(index),
         }
     }
 }
@@ -25,6 +25,30 @@ pub(crate) async fn complete_async(
         },
     };
 
+    postprocess_completions(result, line, pos, working_dir.as_ref())
+}
+
+/// Generates basic completions for the given line, without invoking programmable completion.
+/// Suitable for use on every keystroke, e.g., to generate autosuggestions.
+pub(crate) async fn complete_basic_async(
+    shell: &mut brush_core::Shell,
+    line: &str,
+    pos: usize,
+) -> brush_core::completion::Completions {
+    let working_dir = shell.working_dir.clone();
+
+    // Intentionally ignore any errors that arise.
+    let result = shell.get_basic_completions(line, pos).await;
+
+    postprocess_completions(result, line, pos, working_dir.as_ref())
+}
+
+fn postprocess_completions(
+    result: Result<brush_core::completion::Completions, brush_core::Error>,
+    line: &str,
+    pos: usize,
+    working_dir: &Path,
+) -> brush_core::completion::Completions {
     let mut completions = result.unwrap_or_else(|_| brush_core::completion::Completions {
         insertion_index: pos,
         delete_count: 0,
@@ -41,7 +65,7 @@ pub(crate) async fn complete_async(
             postprocess_completion_candidate(
                 candidate,
                 &completions.options,
-                working_dir.as_ref(),
+                working_dir,
                 completing_end_of_line,
             )
         })
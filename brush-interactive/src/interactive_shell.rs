@@ -109,16 +109,28 @@ pub trait InteractiveShell {
             // Check for any completed jobs.
             shell_mut.as_mut().check_for_completed_jobs()?;
 
-            // If there's a variable called PROMPT_COMMAND, then run it first.
-            if let Some((_, prompt_cmd)) = shell_mut.as_mut().env.get("PROMPT_COMMAND") {
-                let prompt_cmd = prompt_cmd.value().to_cow_string().to_string();
+            // If there's a variable called PROMPT_COMMAND, then run it first. It may be an
+            // array, in which case each of its elements is run in turn.
+            let prompt_cmds = if shell_mut.as_mut().options.interactive {
+                shell_mut
+                    .as_mut()
+                    .env
+                    .get("PROMPT_COMMAND")
+                    .map(|(_, var)| var.value().get_element_values())
+                    .unwrap_or_default()
+            } else {
+                vec![]
+            };
 
+            if !prompt_cmds.is_empty() {
                 // Save (and later restore) the last exit status.
                 let prev_last_result = shell_mut.as_mut().last_exit_status;
 
-                let params = shell_mut.as_mut().default_exec_params();
+                for prompt_cmd in prompt_cmds {
+                    let params = shell_mut.as_mut().default_exec_params();
+                    shell_mut.as_mut().run_string(prompt_cmd, &params).await?;
+                }
 
-                shell_mut.as_mut().run_string(prompt_cmd, &params).await?;
                 shell_mut.as_mut().last_exit_status = prev_last_result;
             }
 
@@ -135,13 +147,67 @@ pub trait InteractiveShell {
                 ReadResult::Input(read_result) => {
                     let mut shell_mut = self.shell_mut();
 
-                    let precmd_prompt = shell_mut.as_mut().compose_precmd_prompt().await?;
-                    if !precmd_prompt.is_empty() {
-                        print!("{precmd_prompt}");
+                    // Interactive shells display the expansion of PS0 after reading a command
+                    // and before executing it.
+                    if shell_mut.as_mut().options.interactive {
+                        let precmd_prompt = shell_mut.as_mut().compose_precmd_prompt().await?;
+                        if !precmd_prompt.is_empty() {
+                            eprint!("{precmd_prompt}");
+                            let _ = std::io::stderr().flush();
+                        }
                     }
 
+                    let is_blank = read_result.trim().is_empty();
+
+                    // Hooks are only run for commands entered in interactive shells.
+                    let command_line = if shell_mut.as_mut().options.interactive && !is_blank {
+                        let command_line = read_result.trim_end_matches('\n').to_owned();
+                        let event = brush_core::hooks::HookEvent::PreExec {
+                            command_line: command_line.clone(),
+                        };
+                        if let Err(e) = shell_mut.as_mut().run_hooks(&event).await {
+                            tracing::error!("pre-exec hook failed: {e}");
+                        }
+                        Some(command_line)
+                    } else {
+                        None
+                    };
+
                     let params = shell_mut.as_mut().default_exec_params();
-                    match shell_mut.as_mut().run_string(read_result, &params).await {
+                    let start_time = std::time::Instant::now();
+                    let result = shell_mut.as_mut().run_string(read_result, &params).await;
+                    let duration = start_time.elapsed();
+
+                    if let Some(command_line) = command_line {
+                        let exit_status = shell_mut.as_mut().last_exit_status;
+                        let event = brush_core::hooks::HookEvent::PostExec {
+                            command_line,
+                            exit_status,
+                            duration,
+                        };
+                        if let Err(e) = shell_mut.as_mut().run_hooks(&event).await {
+                            tracing::error!("post-exec hook failed: {e}");
+                        }
+                    }
+
+                    let shell = shell_mut.as_mut();
+                    if !is_blank {
+                        shell.current_command_number += 1;
+                    }
+
+                    // Record how the command turned out, if it was added to history.
+                    let exit_status = shell.last_exit_status;
+                    if let Some(history) = shell.history.as_mut() {
+                        history.complete_pending_item(exit_status, duration);
+                    }
+
+                    drop(shell_mut);
+
+                    if let Err(e) = self.update_history() {
+                        tracing::debug!("couldn't save history: {e}");
+                    }
+
+                    match result {
                         Ok(result) => Ok(InteractiveExecutionResult::Executed(result)),
                         Err(e) => Ok(InteractiveExecutionResult::Failed(e)),
                     }
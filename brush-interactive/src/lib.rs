@@ -13,7 +13,7 @@ pub use interactive_shell::{
 mod options;
 pub use options::Options;
 
-#[cfg(any(windows, unix))]
+#[cfg(all(any(windows, unix), feature = "reedline"))]
 mod completion;
 
 // Reedline-based shell
@@ -1,73 +1,186 @@
-use std::str::Chars;
-
 use super::refs;
 use nu_ansi_term::{Color, Style};
 
-mod styles {
-    use super::{Color, Style};
-
-    pub fn default() -> Style {
-        Style::new().fg(Color::White)
-    }
-
-    pub fn comment() -> Style {
-        Style::new().fg(Color::DarkGray)
-    }
-
-    pub fn arithmetic() -> Style {
-        Style::new().fg(Color::LightBlue)
-    }
-
-    pub fn parameter() -> Style {
-        Style::new().fg(Color::LightMagenta)
-    }
-
-    pub fn command_substitution() -> Style {
-        Style::new().fg(Color::LightBlue)
-    }
-
-    pub fn quoted() -> Style {
-        Style::new().fg(Color::Yellow)
-    }
-
-    pub fn operator() -> Style {
-        Style::new().fg(Color::White).italic()
-    }
-
-    pub fn assignment() -> Style {
-        Style::new().fg(Color::LightGray).dimmed()
-    }
+/// Name of the shell variable that may be used to override highlighting styles.
+const STYLES_VAR_NAME: &str = "BRUSH_HIGHLIGHT_STYLES";
+
+/// Styles used for the different syntactic elements of the input line.
+#[derive(Clone)]
+struct Styles {
+    default: Style,
+    comment: Style,
+    arithmetic: Style,
+    number: Style,
+    parameter: Style,
+    command_substitution: Style,
+    quoted: Style,
+    unterminated_quote: Style,
+    operator: Style,
+    redirection: Style,
+    redirection_target: Style,
+    here_document: Style,
+    assignment: Style,
+    hyphen_option: Style,
+    function: Style,
+    keyword: Style,
+    builtin: Style,
+    alias: Style,
+    external_command: Style,
+    not_found_command: Style,
+    unknown_command: Style,
+    error: Style,
+}
 
-    pub fn hyphen_option() -> Style {
-        Style::new().fg(Color::White).italic()
+impl Default for Styles {
+    fn default() -> Self {
+        Self {
+            default: Style::new().fg(Color::White),
+            comment: Style::new().fg(Color::DarkGray),
+            arithmetic: Style::new().fg(Color::LightBlue),
+            number: Style::new().fg(Color::LightCyan),
+            parameter: Style::new().fg(Color::LightMagenta),
+            command_substitution: Style::new().fg(Color::LightBlue),
+            quoted: Style::new().fg(Color::Yellow),
+            unterminated_quote: Style::new().fg(Color::Yellow).underline(),
+            operator: Style::new().fg(Color::White).italic(),
+            redirection: Style::new().fg(Color::LightCyan),
+            redirection_target: Style::new().fg(Color::White).underline(),
+            here_document: Style::new().fg(Color::Yellow).dimmed(),
+            assignment: Style::new().fg(Color::LightGray).dimmed(),
+            hyphen_option: Style::new().fg(Color::White).italic(),
+            function: Style::new().bold().fg(Color::Yellow),
+            keyword: Style::new().bold().fg(Color::LightYellow).italic(),
+            builtin: Style::new().bold().fg(Color::Green),
+            alias: Style::new().bold().fg(Color::Cyan),
+            external_command: Style::new().bold().fg(Color::Green),
+            not_found_command: Style::new().bold().fg(Color::Red),
+            unknown_command: Style::new().bold().fg(Color::White),
+            error: Style::new().fg(Color::LightRed).underline(),
+        }
     }
+}
 
-    pub fn function() -> Style {
-        Style::new().bold().fg(Color::Yellow)
-    }
+impl Styles {
+    /// Constructs styles from the defaults, applying any overrides found in the
+    /// shell's `BRUSH_HIGHLIGHT_STYLES` variable. Overrides are specified as a
+    /// colon-separated list of `name=sgr` pairs, where `sgr` is a
+    /// semicolon-separated sequence of ANSI SGR parameters (e.g.,
+    /// `keyword=1;33:error=4;31`), in the same spirit as `GREP_COLORS`.
+    fn from_shell(shell: &brush_core::Shell) -> Self {
+        let mut styles = Self::default();
+
+        if let Some(spec) = shell.env.get_str(STYLES_VAR_NAME) {
+            for entry in spec.split(':') {
+                if let Some((name, sgr)) = entry.split_once('=') {
+                    if let Some(style) = styles.get_mut(name.trim()) {
+                        if let Some(parsed) = parse_sgr_style(sgr) {
+                            *style = parsed;
+                        }
+                    }
+                }
+            }
+        }
 
-    pub fn keyword() -> Style {
-        Style::new().bold().fg(Color::LightYellow).italic()
+        styles
     }
 
-    pub fn builtin() -> Style {
-        Style::new().bold().fg(Color::Green)
+    fn get_mut(&mut self, name: &str) -> Option<&mut Style> {
+        let style = match name {
+            "default" => &mut self.default,
+            "comment" => &mut self.comment,
+            "arithmetic" => &mut self.arithmetic,
+            "number" => &mut self.number,
+            "parameter" => &mut self.parameter,
+            "command_substitution" => &mut self.command_substitution,
+            "quoted" => &mut self.quoted,
+            "unterminated_quote" => &mut self.unterminated_quote,
+            "operator" => &mut self.operator,
+            "redirection" => &mut self.redirection,
+            "redirection_target" => &mut self.redirection_target,
+            "here_document" => &mut self.here_document,
+            "assignment" => &mut self.assignment,
+            "hyphen_option" => &mut self.hyphen_option,
+            "function" => &mut self.function,
+            "keyword" => &mut self.keyword,
+            "builtin" => &mut self.builtin,
+            "alias" => &mut self.alias,
+            "external_command" => &mut self.external_command,
+            "not_found_command" => &mut self.not_found_command,
+            "unknown_command" => &mut self.unknown_command,
+            "error" => &mut self.error,
+            _ => return None,
+        };
+
+        Some(style)
     }
+}
 
-    pub fn alias() -> Style {
-        Style::new().bold().fg(Color::Cyan)
+/// Parses a semicolon-separated sequence of ANSI SGR parameters into a style.
+fn parse_sgr_style(sgr: &str) -> Option<Style> {
+    let mut style = Style::new();
+    let mut params = sgr
+        .split(';')
+        .filter(|p| !p.is_empty())
+        .map(|p| p.trim().parse::<u8>());
+
+    while let Some(param) = params.next() {
+        match param.ok()? {
+            0 => style = Style::new(),
+            1 => style.is_bold = true,
+            2 => style.is_dimmed = true,
+            3 => style.is_italic = true,
+            4 => style.is_underline = true,
+            5 => style.is_blink = true,
+            7 => style.is_reverse = true,
+            8 => style.is_hidden = true,
+            9 => style.is_strikethrough = true,
+            n @ 30..=37 => style.foreground = Some(basic_color(n - 30, false)),
+            38 => style.foreground = Some(parse_extended_color(&mut params)?),
+            39 => style.foreground = None,
+            n @ 40..=47 => style.background = Some(basic_color(n - 40, false)),
+            48 => style.background = Some(parse_extended_color(&mut params)?),
+            49 => style.background = None,
+            n @ 90..=97 => style.foreground = Some(basic_color(n - 90, true)),
+            n @ 100..=107 => style.background = Some(basic_color(n - 100, true)),
+            _ => return None,
+        }
     }
 
-    pub fn external_command() -> Style {
-        Style::new().bold().fg(Color::Green)
-    }
+    Some(style)
+}
 
-    pub fn not_found_command() -> Style {
-        Style::new().bold().fg(Color::Red)
+fn parse_extended_color(
+    params: &mut impl Iterator<Item = Result<u8, std::num::ParseIntError>>,
+) -> Option<Color> {
+    match params.next()?.ok()? {
+        5 => Some(Color::Fixed(params.next()?.ok()?)),
+        2 => Some(Color::Rgb(
+            params.next()?.ok()?,
+            params.next()?.ok()?,
+            params.next()?.ok()?,
+        )),
+        _ => None,
     }
+}
 
-    pub fn unknown_command() -> Style {
-        Style::new().bold().fg(Color::White)
+const fn basic_color(index: u8, bright: bool) -> Color {
+    match (index, bright) {
+        (0, false) => Color::Black,
+        (1, false) => Color::Red,
+        (2, false) => Color::Green,
+        (3, false) => Color::Yellow,
+        (4, false) => Color::Blue,
+        (5, false) => Color::Purple,
+        (6, false) => Color::Cyan,
+        (7, false) => Color::White,
+        (0, true) => Color::DarkGray,
+        (1, true) => Color::LightRed,
+        (2, true) => Color::LightGreen,
+        (3, true) => Color::LightYellow,
+        (4, true) => Color::LightBlue,
+        (5, true) => Color::LightPurple,
+        (6, true) => Color::LightCyan,
+        _ => Color::LightGray,
     }
 }
 
@@ -77,11 +190,11 @@ pub(crate) struct ReedlineHighlighter {
 
 impl reedline::Highlighter for ReedlineHighlighter {
     fn highlight(&self, line: &str, cursor: usize) -> reedline::StyledText {
-        let shell = tokio::task::block_in_place(|| {
+        let mut shell = tokio::task::block_in_place(|| {
             tokio::runtime::Handle::current().block_on(self.shell.lock())
         });
 
-        let mut styled_input = StyledInputLine::new(shell.as_ref(), line, cursor);
+        let mut styled_input = StyledInputLine::new(shell.as_mut(), line, cursor);
         styled_input.style_and_append_program(line, 0);
 
         styled_input.styled
@@ -98,154 +211,404 @@ enum CommandType {
     Unknown,
 }
 
-struct StyledInputLine<'a> {
-    shell: &'a brush_core::Shell,
+/// The syntactic role played by a token in a program.
+#[derive(Clone, Copy)]
+enum TokenRole {
+    /// A control operator (or other non-redirection operator).
+    Operator,
+    /// A redirection operator, or the I/O number preceding one.
+    Redirection,
+    /// The target of a redirection (e.g., a filename or here-document tag).
+    RedirectionTarget,
+    /// The body of a here-document.
+    HereDocumentBody,
+    /// The end tag of a here-document, as synthesized by the tokenizer.
+    HereDocumentEndTag,
+    /// A regular word.
+    Word,
+    /// The token at which the parser reported an error.
+    Error,
+}
+
+/// Incrementally builds up the styled form of an input line. All offsets into the input line
+/// are byte offsets.
+struct StyledInputLine<'a, 'b> {
+    shell: &'a mut brush_core::Shell,
+    styles: Styles,
     cursor: usize,
     styled: reedline::StyledText,
-    remaining_chars: Chars<'a>,
-    current_char_index: usize,
+    input_line: &'b str,
+    current_index: usize,
     next_missing_style: Option<Style>,
 }
 
-impl<'a> StyledInputLine<'a> {
-    fn new(shell: &'a brush_core::Shell, input_line: &'a str, cursor: usize) -> Self {
+impl<'a, 'b> StyledInputLine<'a, 'b> {
+    fn new(shell: &'a mut brush_core::Shell, input_line: &'b str, cursor: usize) -> Self {
+        let styles = Styles::from_shell(shell);
+
         Self {
             shell,
+            styles,
             cursor,
             styled: reedline::StyledText::new(),
-            remaining_chars: input_line.chars(),
-            current_char_index: 0,
+            input_line,
+            current_index: 0,
             next_missing_style: None,
         }
     }
 
     fn style_and_append_program(&mut self, line: &str, global_offset: usize) {
-        #[allow(clippy::cast_sign_loss)]
-        if let Ok(tokens) = brush_parser::tokenize_str(line) {
-            let mut saw_command_token = false;
-            for token in tokens {
-                match token {
-                    brush_parser::Token::Operator(_op, token_location) => {
-                        self.append_style(
-                            styles::operator(),
-                            global_offset + token_location.start.index as usize,
-                            global_offset + token_location.end.index as usize,
-                        );
-                    }
-                    brush_parser::Token::Word(w, token_location) => {
-                        if let Ok(word_pieces) =
-                            brush_parser::word::parse(w.as_str(), &self.shell.parser_options())
-                        {
-                            let default_text_style = self.get_style_for_word(
-                                w.as_str(),
-                                &token_location,
-                                &mut saw_command_token,
-                            );
-
-                            for word_piece in word_pieces {
-                                self.style_and_append_word_piece(
-                                    word_piece,
-                                    default_text_style,
-                                    global_offset + token_location.start.index as usize,
-                                );
-                            }
-                        }
-                    }
+        let tokens = match brush_parser::tokenize_str(line) {
+            Ok(tokens) => tokens,
+            Err(err) => {
+                self.style_and_append_untokenizable_program(line, global_offset, &err);
+                return;
+            }
+        };
+
+        // See if the parser can make sense of the tokens. If it fails anywhere other than at the
+        // end of the input (i.e., for anything other than an incomplete program), we'll highlight
+        // the offending token.
+        let error_token_start = match brush_parser::parse_tokens(
+            &tokens,
+            &self.shell.parser_options(),
+            &brush_parser::SourceInfo::default(),
+        ) {
+            Err(brush_parser::ParseError::ParsingNearToken(token)) => {
+                Some(token.location().start.index)
+            }
+            _ => None,
+        };
+
+        let roles = classify_tokens(tokens.as_slice(), error_token_start);
+
+        // N.B. Token locations are expressed in characters, not bytes.
+        let char_offsets: Vec<_> = line
+            .char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(line.len()))
+            .collect();
+        let byte_offset = |char_index: i32| {
+            usize::try_from(char_index)
+                .ok()
+                .and_then(|i| char_offsets.get(i).copied())
+                .unwrap_or(line.len())
+        };
+
+        // N.B. The tokenizer yields here-document tokens ahead of any tokens that follow their
+        // tags on the same line; we need to style them in the order they appear in the input.
+        let mut tokens_and_roles: Vec<_> = tokens.iter().zip(roles).collect();
+        tokens_and_roles.sort_by_key(|(token, _)| token.location().start.index);
+
+        let mut saw_command_token = false;
+        for (token, role) in tokens_and_roles {
+            let token_location = token.location();
+            let start = global_offset + byte_offset(token_location.start.index);
+            let end = global_offset + byte_offset(token_location.end.index);
+
+            match (token, role) {
+                (_, TokenRole::Error) => {
+                    self.append_style(self.styles.error, start, end);
+                }
+                (_, TokenRole::Redirection) => {
+                    self.append_style(self.styles.redirection, start, end);
+                }
+                (_, TokenRole::HereDocumentBody) => {
+                    self.append_style(self.styles.here_document, start, end);
+                }
+                (_, TokenRole::HereDocumentEndTag) => (),
+                (brush_parser::Token::Operator(_op, _), _) => {
+                    self.append_style(self.styles.operator, start, end);
+
+                    // Whatever comes after a control operator is in command position.
+                    saw_command_token = false;
+                }
+                (brush_parser::Token::Word(w, _), TokenRole::RedirectionTarget) => {
+                    let target_style = self.styles.redirection_target;
+                    self.style_and_append_word(w.as_str(), target_style, start);
+                }
+                (brush_parser::Token::Word(w, _), _) => {
+                    let default_text_style =
+                        self.get_style_for_word(w.as_str(), start, end, &mut saw_command_token);
+                    self.style_and_append_word(w.as_str(), default_text_style, start);
                 }
             }
+        }
+
+        self.skip_ahead(global_offset + line.len());
+    }
+
+    fn style_and_append_untokenizable_program(
+        &mut self,
+        line: &str,
+        global_offset: usize,
+        err: &brush_parser::TokenizerError,
+    ) {
+        let unterminated_quote_position = match err {
+            brush_parser::TokenizerError::UnterminatedSingleQuote(pos)
+            | brush_parser::TokenizerError::UnterminatedDoubleQuote(pos)
+            | brush_parser::TokenizerError::UnterminatedBackquote(pos) => Some(pos),
+            _ => None,
+        };
+
+        if let Some(pos) = unterminated_quote_position {
+            // Style everything before the unbalanced quote as usual, and the rest of the line as
+            // unterminated quoted text. N.B. The position is expressed in characters.
+            let quote_index = usize::try_from(pos.index)
+                .ok()
+                .and_then(|i| line.char_indices().nth(i))
+                .map_or(line.len(), |(i, _)| i);
+            let prefix = &line[..quote_index];
+            if !prefix.is_empty() && prefix.len() < line.len() {
+                self.style_and_append_program(prefix, global_offset);
+            }
 
-            self.skip_ahead(global_offset + line.len());
+            self.append_style(
+                self.styles.unterminated_quote,
+                global_offset + quote_index,
+                global_offset + line.len(),
+            );
         } else {
-            self.append_style(styles::default(), global_offset, global_offset + line.len());
+            self.append_style(
+                self.styles.default,
+                global_offset,
+                global_offset + line.len(),
+            );
+        }
+    }
+
+    fn style_and_append_word(&mut self, w: &str, default_text_style: Style, global_offset: usize) {
+        if let Ok(word_pieces) = brush_parser::word::parse(w, &self.shell.parser_options()) {
+            for word_piece in word_pieces {
+                self.style_and_append_word_piece(w, word_piece, default_text_style, global_offset);
+            }
+        } else {
+            self.append_style(self.styles.error, global_offset, global_offset + w.len());
         }
     }
 
     fn style_and_append_word_piece(
         &mut self,
+        word: &str,
         word_piece: brush_parser::word::WordPieceWithSource,
         default_text_style: Style,
         global_offset: usize,
     ) {
-        self.skip_ahead(global_offset + word_piece.start_index);
+        let piece_start = global_offset + word_piece.start_index;
+        let piece_end = global_offset + word_piece.end_index;
+        let piece_text = word
+            .get(word_piece.start_index..word_piece.end_index)
+            .unwrap_or_default();
+
+        self.skip_ahead(piece_start);
 
         match word_piece.piece {
             brush_parser::word::WordPiece::SingleQuotedText(_)
             | brush_parser::word::WordPiece::AnsiCQuotedText(_)
             | brush_parser::word::WordPiece::EscapeSequence(_) => {
-                self.append_style(
-                    styles::quoted(),
-                    global_offset + word_piece.start_index,
-                    global_offset + word_piece.end_index,
-                );
+                self.append_style(self.styles.quoted, piece_start, piece_end);
             }
             brush_parser::word::WordPiece::DoubleQuotedSequence(subpieces) => {
-                self.set_next_missing_style(styles::quoted());
+                self.set_next_missing_style(self.styles.quoted);
                 for subpiece in subpieces {
-                    self.style_and_append_word_piece(subpiece, styles::quoted(), global_offset);
+                    self.style_and_append_word_piece(
+                        word,
+                        subpiece,
+                        self.styles.quoted,
+                        global_offset,
+                    );
                 }
-                self.set_next_missing_style(styles::quoted());
+                self.set_next_missing_style(self.styles.quoted);
             }
-            brush_parser::word::WordPiece::ParameterExpansion(_)
-            | brush_parser::word::WordPiece::TildePrefix(_) => {
-                self.append_style(
-                    styles::parameter(),
-                    global_offset + word_piece.start_index,
-                    global_offset + word_piece.end_index,
-                );
+            brush_parser::word::WordPiece::TildePrefix(_) => {
+                self.append_style(self.styles.parameter, piece_start, piece_end);
+            }
+            brush_parser::word::WordPiece::ParameterExpansion(_) => {
+                self.style_and_append_parameter_expr(piece_text, piece_start);
             }
             brush_parser::word::WordPiece::BackquotedCommandSubstitution(command) => {
-                self.set_next_missing_style(styles::command_substitution());
+                self.set_next_missing_style(self.styles.command_substitution);
                 self.style_and_append_program(
                     command.as_str(),
-                    global_offset + word_piece.start_index + 1, /* account for opening backtick */
+                    piece_start + 1, /* account for opening backtick */
                 );
-                self.set_next_missing_style(styles::command_substitution());
+                self.set_next_missing_style(self.styles.command_substitution);
             }
             brush_parser::word::WordPiece::CommandSubstitution(command) => {
-                self.set_next_missing_style(styles::command_substitution());
+                self.set_next_missing_style(self.styles.command_substitution);
                 self.style_and_append_program(
                     command.as_str(),
-                    global_offset + word_piece.start_index + 2, /* account for opening $( */
+                    piece_start + 2, /* account for opening $( */
                 );
-                self.set_next_missing_style(styles::command_substitution());
+                self.set_next_missing_style(self.styles.command_substitution);
             }
             brush_parser::word::WordPiece::ArithmeticExpression(_) => {
-                // TODO: Consider individually highlighting pieces of the expression itself.
-                self.append_style(
-                    styles::arithmetic(),
-                    global_offset + word_piece.start_index,
-                    global_offset + word_piece.end_index,
-                );
+                self.style_and_append_arithmetic_expr(piece_text, piece_start);
             }
             brush_parser::word::WordPiece::Text(_text) => {
-                self.append_style(
-                    default_text_style,
-                    global_offset + word_piece.start_index,
-                    global_offset + word_piece.end_index,
-                );
+                self.append_style(default_text_style, piece_start, piece_end);
+            }
+        }
+
+        self.skip_ahead(piece_end);
+    }
+
+    /// Styles a parameter expansion (e.g., `$x` or `${x:-default}`), separately styling the
+    /// parameter, any operator, and any nested word (which may itself contain expansions).
+    fn style_and_append_parameter_expr(&mut self, text: &str, global_offset: usize) {
+        let (Some(body), Some(_)) = (text.strip_prefix("${"), text.strip_suffix('}')) else {
+            self.append_style(
+                self.styles.parameter,
+                global_offset,
+                global_offset + text.len(),
+            );
+            return;
+        };
+
+        let body = &body[..body.len() - 1];
+        let name_len = parameter_name_len(body);
+        let op_len = body[name_len..]
+            .find(|c: char| !":-=+?#%/^,@".contains(c))
+            .unwrap_or(body.len() - name_len);
+        let nested_word = &body[name_len + op_len..];
+
+        let body_start = global_offset + 2;
+        let nested_word_start = body_start + name_len + op_len;
+
+        self.append_style(self.styles.parameter, global_offset, body_start + name_len);
+        self.append_style(
+            self.styles.operator,
+            body_start + name_len,
+            nested_word_start,
+        );
+
+        if !nested_word.is_empty() {
+            self.set_next_missing_style(self.styles.default);
+            self.style_and_append_word(nested_word, self.styles.default, nested_word_start);
+        }
+
+        self.append_style(
+            self.styles.parameter,
+            nested_word_start + nested_word.len(),
+            global_offset + text.len(),
+        );
+    }
+
+    /// Styles an arithmetic expansion (e.g., `$((x + 1))`), separately styling numbers,
+    /// variables, operators, and any nested expansions.
+    fn style_and_append_arithmetic_expr(&mut self, text: &str, global_offset: usize) {
+        let Some(expr) = text
+            .strip_prefix("$((")
+            .and_then(|body| body.strip_suffix("))"))
+        else {
+            self.append_style(
+                self.styles.arithmetic,
+                global_offset,
+                global_offset + text.len(),
+            );
+            return;
+        };
+
+        let expr_start = global_offset + 3;
+        self.append_style(self.styles.arithmetic, global_offset, expr_start);
+
+        match brush_parser::word::parse(expr, &self.shell.parser_options()) {
+            Ok(pieces) => {
+                for piece in pieces {
+                    if let brush_parser::word::WordPiece::Text(_) = &piece.piece {
+                        let piece_text = &expr[piece.start_index..piece.end_index];
+                        self.style_and_append_arithmetic_text(
+                            piece_text,
+                            expr_start + piece.start_index,
+                        );
+                    } else {
+                        self.style_and_append_word_piece(
+                            expr,
+                            piece,
+                            self.styles.default,
+                            expr_start,
+                        );
+                    }
+                }
+            }
+            Err(_) => {
+                self.append_style(self.styles.error, expr_start, expr_start + expr.len());
             }
         }
 
-        self.skip_ahead(global_offset + word_piece.end_index);
+        self.append_style(
+            self.styles.arithmetic,
+            expr_start + expr.len(),
+            global_offset + text.len(),
+        );
+    }
+
+    fn style_and_append_arithmetic_text(&mut self, text: &str, global_offset: usize) {
+        let is_alnum = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            let (style, len) = if is_alnum(c) {
+                let mut end = start + c.len_utf8();
+                while let Some((i, next_c)) = chars.peek() {
+                    if !is_alnum(*next_c) && *next_c != '#' {
+                        break;
+                    }
+                    end = i + next_c.len_utf8();
+                    chars.next();
+                }
+
+                let style = if c.is_ascii_digit() {
+                    self.styles.number
+                } else {
+                    self.styles.parameter
+                };
+
+                (style, end - start)
+            } else if c.is_whitespace() {
+                (self.styles.default, c.len_utf8())
+            } else {
+                (self.styles.operator, c.len_utf8())
+            };
+
+            self.append_style(style, global_offset + start, global_offset + start + len);
+        }
     }
 
     fn append_style(&mut self, style: Style, start: usize, end: usize) {
+        let start = self.ceil_char_boundary(start);
+        let end = self.ceil_char_boundary(end);
+
+        // Ignore anything we've already styled.
+        if end < self.current_index {
+            return;
+        }
+        let start = start.max(self.current_index);
+
         // See if we need to cover a gap between this substring and the one that preceded it.
-        if start > self.current_char_index {
-            let missing_style = self.next_missing_style.map_or_else(styles::comment, |s| s);
-            let missing_text: String = (&mut self.remaining_chars)
-                .take(start - self.current_char_index)
-                .collect();
-            self.styled.push((missing_style, missing_text));
-            self.current_char_index = start;
+        if start > self.current_index {
+            let missing_style = self.next_missing_style.unwrap_or(self.styles.comment);
+            let missing_text = &self.input_line[self.current_index..start];
+            self.styled.push((missing_style, missing_text.to_owned()));
+            self.current_index = start;
         }
 
         if end > start {
-            let text: String = (&mut self.remaining_chars).take(end - start).collect();
-            self.styled.push((style, text));
+            let text = &self.input_line[start..end];
+            self.styled.push((style, text.to_owned()));
         }
 
-        self.current_char_index = end;
+        self.current_index = end;
+    }
+
+    /// Returns the given offset, clamped to the input line and moved forward (if needed) to the
+    /// nearest character boundary.
+    fn ceil_char_boundary(&self, mut index: usize) -> usize {
+        index = index.min(self.input_line.len());
+        while !self.input_line.is_char_boundary(index) {
+            index += 1;
+        }
+        index
     }
 
     fn skip_ahead(&mut self, dest: usize) {
@@ -259,42 +622,48 @@ impl<'a> StyledInputLine<'a> {
     }
 
     fn get_style_for_word(
-        &self,
+        &mut self,
         w: &str,
-        token_location: &brush_parser::TokenLocation,
+        start: usize,
+        end: usize,
         saw_command_token: &mut bool,
     ) -> Style {
         if !*saw_command_token {
             if w.contains('=') {
-                styles::assignment()
+                self.styles.assignment
             } else {
                 *saw_command_token = true;
-                match self.classify_possible_command(w, token_location) {
-                    CommandType::Function => styles::function(),
-                    CommandType::Keyword => styles::keyword(),
-                    CommandType::Builtin => styles::builtin(),
-                    CommandType::Alias => styles::alias(),
-                    CommandType::External => styles::external_command(),
-                    CommandType::NotFound => styles::not_found_command(),
-                    CommandType::Unknown => styles::unknown_command(),
+                match self.classify_possible_command(w, start, end) {
+                    CommandType::Function => self.styles.function,
+                    CommandType::Keyword => {
+                        // Some keywords are followed by another command.
+                        if keyword_precedes_command(w) {
+                            *saw_command_token = false;
+                        }
+                        self.styles.keyword
+                    }
+                    CommandType::Builtin => self.styles.builtin,
+                    CommandType::Alias => self.styles.alias,
+                    CommandType::External => self.styles.external_command,
+                    CommandType::NotFound => self.styles.not_found_command,
+                    CommandType::Unknown => self.styles.unknown_command,
                 }
             }
         } else {
             if self.shell.is_keyword(w) {
-                styles::keyword()
+                if keyword_precedes_command(w) {
+                    *saw_command_token = false;
+                }
+                self.styles.keyword
             } else if w.starts_with('-') {
-                styles::hyphen_option()
+                self.styles.hyphen_option
             } else {
-                styles::default()
+                self.styles.default
             }
         }
     }
 
-    fn classify_possible_command(
-        &self,
-        name: &str,
-        token_location: &brush_parser::TokenLocation,
-    ) -> CommandType {
+    fn classify_possible_command(&mut self, name: &str, start: usize, end: usize) -> CommandType {
         if self.shell.is_keyword(name) {
             return CommandType::Keyword;
         } else if self.shell.aliases.contains_key(name) {
@@ -306,23 +675,23 @@ impl<'a> StyledInputLine<'a> {
         }
 
         // Short-circuit if the cursor is still in this token.
-        #[allow(clippy::cast_sign_loss)]
-        if (self.cursor >= token_location.start.index as usize)
-            && (self.cursor <= token_location.end.index as usize)
-        {
+        if (start..=end).contains(&self.cursor) {
             return CommandType::Unknown;
         }
 
         if name.contains(std::path::MAIN_SEPARATOR) {
-            // TODO: Should check for executable-ness.
             let candidate_path = self.shell.get_absolute_path(std::path::Path::new(name));
-            if candidate_path.exists() {
+            if brush_core::is_executable(&candidate_path) {
                 CommandType::External
             } else {
                 CommandType::NotFound
             }
         } else {
-            if self.shell.find_first_executable_in_path(name).is_some() {
+            if self
+                .shell
+                .find_first_executable_in_path_using_cache(name)
+                .is_some()
+            {
                 CommandType::External
             } else {
                 CommandType::NotFound
@@ -330,3 +699,238 @@ impl<'a> StyledInputLine<'a> {
         }
     }
 }
+
+/// Classifies each of the given tokens by the syntactic role it plays.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens to classify, in the order yielded by the tokenizer.
+/// * `error_token_start` - Optionally provides the start index of a token at which a parse error
+///   was reported.
+fn classify_tokens(
+    tokens: &[brush_parser::Token],
+    error_token_start: Option<i32>,
+) -> Vec<TokenRole> {
+    #[derive(PartialEq)]
+    enum HereDocumentState {
+        None,
+        ExpectingTag,
+        ExpectingBody,
+        ExpectingEndTag,
+    }
+
+    let mut roles = Vec::with_capacity(tokens.len());
+    let mut here_state = HereDocumentState::None;
+    let mut expecting_redirection_target = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        let role = match token {
+            brush_parser::Token::Operator(op, _) if is_redirection_operator(op) => {
+                expecting_redirection_target = true;
+                if op == "<<" || op == "<<-" {
+                    here_state = HereDocumentState::ExpectingTag;
+                }
+                TokenRole::Redirection
+            }
+            brush_parser::Token::Operator(..) => TokenRole::Operator,
+            brush_parser::Token::Word(w, location) => {
+                if here_state == HereDocumentState::ExpectingBody {
+                    here_state = HereDocumentState::ExpectingEndTag;
+                    TokenRole::HereDocumentBody
+                } else if here_state == HereDocumentState::ExpectingEndTag {
+                    here_state = HereDocumentState::None;
+                    TokenRole::HereDocumentEndTag
+                } else if expecting_redirection_target {
+                    expecting_redirection_target = false;
+                    if here_state == HereDocumentState::ExpectingTag {
+                        here_state = HereDocumentState::ExpectingBody;
+                    }
+                    TokenRole::RedirectionTarget
+                } else if is_io_number(w, location, tokens.get(i + 1)) {
+                    TokenRole::Redirection
+                } else {
+                    TokenRole::Word
+                }
+            }
+        };
+
+        if error_token_start.is_some_and(|start| token.location().start.index == start) {
+            roles.push(TokenRole::Error);
+        } else {
+            roles.push(role);
+        }
+    }
+
+    roles
+}
+
+fn is_redirection_operator(op: &str) -> bool {
+    matches!(
+        op,
+        "<" | ">" | ">>" | ">|" | "<>" | "<&" | ">&" | "&>" | "&>>" | "<<" | "<<-" | "<<<"
+    )
+}
+
+/// Checks whether the given word is an I/O number immediately preceding a redirection
+/// operator (e.g., the `2` in `2>/dev/null`).
+fn is_io_number(
+    w: &str,
+    location: &brush_parser::TokenLocation,
+    next_token: Option<&brush_parser::Token>,
+) -> bool {
+    if w.is_empty() || !w.chars().all(|c| c.is_ascii_digit()) {
+        return false;
+    }
+
+    matches!(
+        next_token,
+        Some(brush_parser::Token::Operator(op, next_location))
+            if is_redirection_operator(op) && next_location.start.index == location.end.index
+    )
+}
+
+/// Returns the length of the parameter name (including any prefix operator or array index) at
+/// the start of the body of a braced parameter expansion.
+fn parameter_name_len(body: &str) -> usize {
+    let mut len = 0;
+    let bytes = body.as_bytes();
+
+    // Account for length (#) or indirection (!) prefixes.
+    if bytes.len() > 1 && (bytes[0] == b'#' || bytes[0] == b'!') {
+        len += 1;
+    }
+
+    let name_start = len;
+    while len < bytes.len() && (bytes[len].is_ascii_alphanumeric() || bytes[len] == b'_') {
+        len += 1;
+    }
+
+    // Allow for special parameters.
+    if len == name_start && len < bytes.len() && b"@*#?$!-".contains(&bytes[len]) {
+        len += 1;
+    }
+
+    // Allow for an array index.
+    if len < bytes.len() && bytes[len] == b'[' {
+        if let Some(close) = body[len..].find(']') {
+            len += close + 1;
+        }
+    }
+
+    len
+}
+
+fn keyword_precedes_command(keyword: &str) -> bool {
+    matches!(
+        keyword,
+        "if" | "then" | "else" | "elif" | "do" | "while" | "until" | "!" | "{" | "time"
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn, clippy::unwrap_in_result)]
+mod tests {
+    use super::*;
+
+    async fn highlight(line: &str) -> Result<Vec<(Style, String)>, brush_core::Error> {
+        let mut shell = brush_core::Shell::new(&brush_core::CreateOptions::default()).await?;
+
+        let mut styled_input = StyledInputLine::new(&mut shell, line, line.len());
+        styled_input.style_and_append_program(line, 0);
+
+        Ok(styled_input.styled.buffer)
+    }
+
+    fn style_of(styled: &[(Style, String)], text: &str) -> Option<Style> {
+        styled
+            .iter()
+            .find(|(_, s)| s == text)
+            .map(|(style, _)| *style)
+    }
+
+    fn concatenated(styled: &[(Style, String)]) -> String {
+        styled.iter().map(|(_, s)| s.as_str()).collect()
+    }
+
+    #[test]
+    fn test_classify_tokens() -> Result<(), brush_parser::TokenizerError> {
+        let tokens = brush_parser::tokenize_str("echo hi 2>err | cat <<EOF\nbody\nEOF\n")?;
+        let roles: Vec<_> = classify_tokens(tokens.as_slice(), None)
+            .into_iter()
+            .map(|role| match role {
+                TokenRole::Operator => "op",
+                TokenRole::Redirection => "redir",
+                TokenRole::RedirectionTarget => "target",
+                TokenRole::HereDocumentBody => "body",
+                TokenRole::HereDocumentEndTag => "end",
+                TokenRole::Word => "word",
+                TokenRole::Error => "error",
+            })
+            .collect();
+
+        assert_eq!(
+            roles,
+            [
+                "word", "word", "redir", "redir", "target", "op", "word", "redir", "target",
+                "body", "end", "op"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parameter_name_len() {
+        assert_eq!(parameter_name_len("x:-y"), 1);
+        assert_eq!(parameter_name_len("#arr[@]"), 7);
+        assert_eq!(parameter_name_len("!prefix*"), 7);
+        assert_eq!(parameter_name_len("?"), 1);
+        assert_eq!(parameter_name_len("é"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_command_classification() -> Result<(), brush_core::Error> {
+        let styles = Styles::default();
+        let styled = highlight("if true; then echo hi -n; fi; x=1 nonexistent-cmd-1234 a").await?;
+
+        assert_eq!(style_of(&styled, "if"), Some(styles.keyword));
+        assert_eq!(style_of(&styled, "true"), Some(styles.builtin));
+        assert_eq!(style_of(&styled, "echo"), Some(styles.builtin));
+        assert_eq!(style_of(&styled, "-n"), Some(styles.hyphen_option));
+        assert_eq!(style_of(&styled, "x=1"), Some(styles.assignment));
+        assert_eq!(
+            style_of(&styled, "nonexistent-cmd-1234"),
+            Some(styles.not_found_command)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_non_ascii_input() -> Result<(), brush_core::Error> {
+        let styles = Styles::default();
+        let line = r#"echo "héllo ${x:-dé}" $((ü + 1)) 'ça' # ñ"#;
+        let styled = highlight(line).await?;
+
+        assert_eq!(concatenated(&styled), line);
+        assert_eq!(style_of(&styled, "echo"), Some(styles.builtin));
+        assert_eq!(style_of(&styled, "${x"), Some(styles.parameter));
+        assert_eq!(style_of(&styled, ":-"), Some(styles.operator));
+        assert_eq!(style_of(&styled, "dé"), Some(styles.default));
+        assert_eq!(style_of(&styled, "'ça'"), Some(styles.quoted));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_non_ascii_unterminated_quote() -> Result<(), brush_core::Error> {
+        let styles = Styles::default();
+        let line = "echo é 'ünterminated";
+        let styled = highlight(line).await?;
+
+        assert_eq!(concatenated(&styled), line);
+        assert_eq!(style_of(&styled, "é"), Some(styles.default));
+        assert_eq!(
+            style_of(&styled, "'ünterminated"),
+            Some(styles.unterminated_quote)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use nu_ansi_term::Style;
+use std::borrow::BorrowMut;
+
+use super::refs;
+use crate::completion;
+
+/// Maximum number of history entries considered when ranking suggestions.
+const MAX_HISTORY_CANDIDATES: i64 = 256;
+
+/// Implements fish-style autosuggestions: the most relevant prior command line starting with
+/// the current input is suggested, preferring commands that were run in the current working
+/// directory and that completed successfully. If history has nothing to offer, suggestions
+/// fall back to basic (non-programmable) completion; since hints are recomputed on every
+/// keystroke, completion specs (which may run arbitrary functions and commands) are never
+/// consulted.
+pub(crate) struct ReedlineHinter {
+    pub shell: refs::ShellRef,
+    pub style: Style,
+    current_hint: String,
+}
+
+impl ReedlineHinter {
+    pub fn new(shell: refs::ShellRef, style: Style) -> Self {
+        Self {
+            shell,
+            style,
+            current_hint: String::new(),
+        }
+    }
+}
+
+impl reedline::Hinter for ReedlineHinter {
+    fn handle(
+        &mut self,
+        line: &str,
+        pos: usize,
+        history: &dyn reedline::History,
+        use_ansi_coloring: bool,
+        _cwd: &str,
+    ) -> String {
+        self.current_hint = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.compute_hint(line, pos, history))
+        })
+        .unwrap_or_default();
+
+        if use_ansi_coloring && !self.current_hint.is_empty() {
+            self.style.paint(&self.current_hint).to_string()
+        } else {
+            self.current_hint.clone()
+        }
+    }
+
+    fn complete_hint(&self) -> String {
+        self.current_hint.clone()
+    }
+
+    fn next_hint_token(&self) -> String {
+        next_token(self.current_hint.as_str()).to_owned()
+    }
+}
+
+impl ReedlineHinter {
+    async fn compute_hint(
+        &self,
+        line: &str,
+        pos: usize,
+        history: &dyn reedline::History,
+    ) -> Option<String> {
+        // Only offer suggestions when the cursor is at the end of a non-empty line.
+        if line.trim().is_empty() || pos != line.len() {
+            return None;
+        }
+
+        let mut shell_guard = self.shell.lock().await;
+        let shell = shell_guard.borrow_mut().as_mut();
+
+        if !shell.options.autosuggestions {
+            return None;
+        }
+
+        if shell.options.autosuggestions_from_history {
+            let cwd = shell.working_dir.to_string_lossy().to_string();
+            if let Some(hint) = get_hint_from_history(line, cwd.as_str(), history) {
+                return Some(hint);
+            }
+        }
+
+        if shell.options.autosuggestions_from_completion {
+            if let Some(hint) = get_hint_from_completion(shell, line, pos).await {
+                return Some(hint);
+            }
+        }
+
+        None
+    }
+}
+
+fn get_hint_from_history(line: &str, cwd: &str, history: &dyn reedline::History) -> Option<String> {
+    let mut query = reedline::SearchQuery::last_with_prefix(line.to_owned(), None);
+    query.limit = Some(MAX_HISTORY_CANDIDATES);
+
+    let items = history.search(query).ok()?;
+
+    // Results come back most recent first; we prefer items run in the current directory, and
+    // after that, items that were successful. Anything else being equal, we prefer the most
+    // recent. N.B. Not all history backends record this metadata; for those, this reduces to
+    // simply picking the most recent matching item.
+    let best_item = items
+        .iter()
+        .filter(|item| item.command_line.len() > line.len())
+        .enumerate()
+        .max_by_key(|(index, item)| {
+            let same_dir = item.cwd.as_deref().is_some_and(|item_cwd| item_cwd == cwd);
+            let succeeded = item.exit_status.map_or(true, |status| status == 0);
+            (same_dir, succeeded, std::cmp::Reverse(*index))
+        })
+        .map(|(_, item)| item)?;
+
+    best_item
+        .command_line
+        .get(line.len()..)
+        .map(|s| s.to_owned())
+}
+
+async fn get_hint_from_completion(
+    shell: &mut brush_core::Shell,
+    line: &str,
+    pos: usize,
+) -> Option<String> {
+    // Don't try to complete words that would need to be expanded first; expansion could
+    // run command substitutions.
+    let current_word = line.rsplit(char::is_whitespace).next().unwrap_or_default();
+    if current_word.contains(['$', '`']) {
+        return None;
+    }
+
+    let completions = completion::complete_basic_async(shell, line, pos).await;
+
+    // Make sure that the token being completed ends at the cursor.
+    if completions.insertion_index + completions.delete_count != pos {
+        return None;
+    }
+
+    let typed = line.get(completions.insertion_index..pos)?;
+    let candidate = completions.candidates.first()?.trim_end();
+
+    candidate
+        .strip_prefix(typed)
+        .filter(|remainder| !remainder.is_empty())
+        .map(|remainder| remainder.to_owned())
+}
+
+/// Returns the first token of the given hint, including any leading whitespace, to support
+/// accepting a suggestion word-by-word.
+fn next_token(hint: &str) -> &str {
+    let trimmed = hint.trim_start();
+    let leading_ws_len = hint.len() - trimmed.len();
+
+    let token_len = trimmed
+        .find(|c: char| c.is_whitespace() || c == std::path::MAIN_SEPARATOR)
+        .map_or(trimmed.len(), |i| {
+            // Include a trailing path separator in the token.
+            if trimmed[i..].starts_with(std::path::MAIN_SEPARATOR) {
+                i + std::path::MAIN_SEPARATOR.len_utf8()
+            } else {
+                i
+            }
+        });
+
+    &hint[..leading_ws_len + token_len]
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn, clippy::unwrap_in_result)]
+mod tests {
+    use super::*;
+    use reedline::History;
+
+    type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+    fn history_with(lines: &[&str]) -> Result<reedline::FileBackedHistory> {
+        let mut history = reedline::FileBackedHistory::new(100)?;
+        for line in lines {
+            history.save(reedline::HistoryItem::from_command_line(*line))?;
+        }
+        Ok(history)
+    }
+
+    #[test]
+    fn test_next_token() {
+        assert_eq!(next_token(""), "");
+        assert_eq!(next_token("abc"), "abc");
+        assert_eq!(next_token("abc def"), "abc");
+        assert_eq!(next_token("  abc def"), "  abc");
+        assert_eq!(
+            next_token(std::format!("dir{}file", std::path::MAIN_SEPARATOR).as_str()),
+            std::format!("dir{}", std::path::MAIN_SEPARATOR)
+        );
+    }
+
+    #[test]
+    fn test_hint_from_history() -> Result<()> {
+        let history = history_with(&["echo", "echo one", "ls -l", "echo two"])?;
+
+        // The most recent strictly longer match wins.
+        assert_eq!(
+            get_hint_from_history("ec", "/", &history),
+            Some(String::from("ho two"))
+        );
+        assert_eq!(
+            get_hint_from_history("echo o", "/", &history),
+            Some(String::from("ne"))
+        );
+
+        // Exact matches and non-matches offer nothing.
+        assert_eq!(get_hint_from_history("ls -l", "/", &history), None);
+        assert_eq!(get_hint_from_history("cat", "/", &history), None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_hint_from_completion() -> Result<()> {
+        let mut shell = brush_core::Shell::new(&brush_core::CreateOptions::default()).await?;
+        let params = shell.default_exec_params();
+        shell
+            .run_string(
+                String::from("myfunc() { :; }; compfunc() { invoked=1; COMPREPLY=(zzz); }"),
+                &params,
+            )
+            .await?;
+        shell
+            .run_string(String::from("complete -F compfunc mycmd"), &params)
+            .await?;
+
+        // Commands are suggested.
+        assert_eq!(
+            get_hint_from_completion(&mut shell, "myfu", 4).await,
+            Some(String::from("nc"))
+        );
+
+        // Programmable completion is never invoked.
+        assert_eq!(
+            get_hint_from_completion(&mut shell, "mycmd z", 7).await,
+            None
+        );
+        assert!(shell.env.get("invoked").is_none());
+
+        // Words needing expansion aren't completed.
+        assert_eq!(
+            get_hint_from_completion(&mut shell, "echo $(invoked=1)", 17).await,
+            None
+        );
+        assert!(shell.env.get("invoked").is_none());
+
+        Ok(())
+    }
+}
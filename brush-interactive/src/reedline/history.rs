@@ -0,0 +1,375 @@
+use std::time::SystemTime;
+
+use brush_core::history;
+
+use super::refs;
+
+/// Exposes the shell's command history to reedline. Items are recorded along with metadata
+/// about their execution (working directory, start time, etc.), and the shell's
+/// history-related options (e.g., `cmdhist` and `lithist`) are applied to command lines
+/// as they are saved.
+pub(crate) struct ReedlineHistory {
+    pub shell: refs::ShellRef,
+}
+
+impl ReedlineHistory {
+    fn lock_shell(&self) -> tokio::sync::MutexGuard<'_, brush_core::Shell> {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.shell.lock())
+        })
+    }
+}
+
+impl reedline::History for ReedlineHistory {
+    fn save(&mut self, mut h: reedline::HistoryItem) -> reedline::Result<reedline::HistoryItem> {
+        let mut shell_guard = self.lock_shell();
+        let shell = shell_guard.as_mut();
+
+        if !shell.options.enable_command_history {
+            return Ok(h);
+        }
+
+        let command_lines = if !h.command_line.contains('\n') {
+            // Single-line commands are saved as-is.
+            vec![h.command_line.clone()]
+        } else if !shell.options.save_multiline_cmds_in_history {
+            // Without cmdhist, each line is saved as its own entry.
+            h.command_line
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| line.to_owned())
+                .collect()
+        } else if !shell.options.embed_newlines_in_multiline_cmds_in_history {
+            // With cmdhist but without lithist, the commands are joined onto a single line.
+            vec![join_multiline_command(shell, h.command_line.as_str())]
+        } else {
+            vec![h.command_line.clone()]
+        };
+
+        let working_dir = shell.working_dir.clone();
+        let Some(history) = shell.history.as_mut() else {
+            return Ok(h);
+        };
+
+        for command_line in command_lines {
+            let id = history.add(history::Item {
+                start_time: Some(SystemTime::now()),
+                working_dir: Some(working_dir.clone()),
+                ..history::Item::new(command_line.as_str())
+            });
+
+            h.id = Some(reedline::HistoryItemId::new(id));
+            h.command_line = command_line;
+        }
+
+        Ok(h)
+    }
+
+    fn load(&self, id: reedline::HistoryItemId) -> reedline::Result<reedline::HistoryItem> {
+        let shell = self.lock_shell();
+
+        shell
+            .history
+            .as_ref()
+            .and_then(|history| history.get(id.0))
+            .map(to_reedline_item)
+            .ok_or(reedline::ReedlineError(
+                reedline::ReedlineErrorVariants::OtherHistoryError("history item not found"),
+            ))
+    }
+
+    fn count(&self, query: reedline::SearchQuery) -> reedline::Result<i64> {
+        let count = self.search(query)?.len();
+        Ok(i64::try_from(count).unwrap_or(i64::MAX))
+    }
+
+    fn search(&self, query: reedline::SearchQuery) -> reedline::Result<Vec<reedline::HistoryItem>> {
+        let shell = self.lock_shell();
+        let Some(history) = shell.history.as_ref() else {
+            return Ok(vec![]);
+        };
+
+        let query = to_history_query(&query);
+        Ok(history
+            .search(&query)
+            .into_iter()
+            .map(to_reedline_item)
+            .collect())
+    }
+
+    fn update(
+        &mut self,
+        id: reedline::HistoryItemId,
+        updater: &dyn Fn(reedline::HistoryItem) -> reedline::HistoryItem,
+    ) -> reedline::Result<()> {
+        let mut shell = self.lock_shell();
+        let Some(history) = shell.history.as_mut() else {
+            return Ok(());
+        };
+
+        history.update(id.0, |item| {
+            let updated = updater(to_reedline_item(item));
+            item.command_line = updated.command_line;
+            item.working_dir = updated.cwd.map(std::path::PathBuf::from);
+            item.duration = updated.duration;
+            item.exit_status = updated
+                .exit_status
+                .and_then(|status| u8::try_from(status).ok());
+        });
+
+        Ok(())
+    }
+
+    fn clear(&mut self) -> reedline::Result<()> {
+        let mut shell = self.lock_shell();
+        if let Some(history) = shell.history.as_mut() {
+            history.clear();
+        }
+
+        Ok(())
+    }
+
+    fn delete(&mut self, h: reedline::HistoryItemId) -> reedline::Result<()> {
+        let mut shell = self.lock_shell();
+        if let Some(history) = shell.history.as_mut() {
+            history.delete(h.0);
+        }
+
+        Ok(())
+    }
+
+    fn sync(&mut self) -> std::io::Result<()> {
+        // N.B. reedline syncs right after saving a new item, but we don't yet know how its
+        // execution will turn out. The interactive shell saves history after each command
+        // completes instead.
+        Ok(())
+    }
+
+    fn session(&self) -> Option<reedline::HistorySessionId> {
+        None
+    }
+}
+
+fn to_reedline_item(item: &history::Item) -> reedline::HistoryItem {
+    let mut h = reedline::HistoryItem::from_command_line(item.command_line.as_str());
+    h.id = Some(reedline::HistoryItemId::new(item.id));
+    h.cwd = item
+        .working_dir
+        .as_ref()
+        .map(|dir| dir.to_string_lossy().to_string());
+    h.duration = item.duration;
+    h.exit_status = item.exit_status.map(i64::from);
+    h
+}
+
+fn to_history_query(query: &reedline::SearchQuery) -> history::Query {
+    // reedline's start and end IDs are exclusive bounds, relative to the search direction.
+    let (newest_first, after_id, before_id) = match query.direction {
+        reedline::SearchDirection::Backward => (true, query.end_id, query.start_id),
+        reedline::SearchDirection::Forward => (false, query.start_id, query.end_id),
+    };
+
+    let filter = &query.filter;
+    history::Query {
+        command_line: filter.command_line.as_ref().map(|search| match search {
+            reedline::CommandLineSearch::Prefix(s) => history::CommandLineFilter::Prefix(s.clone()),
+            reedline::CommandLineSearch::Substring(s) => {
+                history::CommandLineFilter::Substring(s.clone())
+            }
+            reedline::CommandLineSearch::Exact(s) => history::CommandLineFilter::Exact(s.clone()),
+        }),
+        working_dir: filter
+            .cwd_exact
+            .as_ref()
+            .map(|dir| history::DirectoryFilter::Exact(dir.into()))
+            .or_else(|| {
+                filter
+                    .cwd_prefix
+                    .as_ref()
+                    .map(|dir| history::DirectoryFilter::Within(dir.into()))
+            }),
+        exit_status: filter.exit_successful.map(|successful| {
+            if successful {
+                history::ExitStatusFilter::Succeeded
+            } else {
+                history::ExitStatusFilter::Failed
+            }
+        }),
+        session_id: None,
+        after_id: after_id.map(|id| id.0),
+        before_id: before_id.map(|id| id.0),
+        newest_first,
+        limit: query.limit.and_then(|limit| usize::try_from(limit).ok()),
+    }
+}
+
+/// Joins the lines of a multi-line command into a single line, separating them with `;`
+/// where that's syntactically valid and with a space otherwise (e.g., after `do` or `|`).
+/// Only newlines that separate commands are replaced; newlines inside quoted strings,
+/// here-documents, line continuations, and after comments are preserved, since they can't
+/// be removed without changing the meaning of the command.
+///
+/// # Arguments
+///
+/// * `shell` - The shell whose parser options should be used.
+/// * `command` - The multi-line command.
+fn join_multiline_command(shell: &brush_core::Shell, command: &str) -> String {
+    let mut joined = String::new();
+    let mut segment_start = 0;
+
+    for boundary in find_command_boundaries(command).chain(std::iter::once(command.len())) {
+        let segment = command[segment_start..boundary].trim_start();
+        segment_start = boundary + 1;
+
+        if segment.trim().is_empty() {
+            continue;
+        }
+
+        if !joined.is_empty() {
+            let separator = choose_line_separator(shell, joined.as_str(), segment);
+            joined.push_str(separator);
+        }
+
+        joined.push_str(segment);
+    }
+
+    joined
+}
+
+/// Returns the byte offsets of the newlines in the given command that separate commands
+/// and can be safely replaced.
+#[allow(clippy::cast_sign_loss)]
+fn find_command_boundaries(command: &str) -> impl Iterator<Item = usize> + '_ {
+    // If the command can't be tokenized, then we can't be sure of anything; keep it as-is.
+    let tokens = brush_parser::tokenize_str(command).unwrap_or_default();
+
+    // Token locations are character offsets; we need byte offsets.
+    let byte_offset = |char_index: i32| {
+        command
+            .char_indices()
+            .nth(char_index as usize)
+            .map_or(command.len(), |(i, _)| i)
+    };
+
+    // Here-document bodies start right after a newline, which needs to stay put.
+    let here_doc_body_starts: Vec<usize> = tokens
+        .windows(3)
+        .filter_map(|window| match window {
+            [brush_parser::Token::Operator(op, _), brush_parser::Token::Word(..), body]
+                if op == "<<" || op == "<<-" =>
+            {
+                Some(byte_offset(body.location().start.index))
+            }
+            _ => None,
+        })
+        .collect();
+
+    let mut boundaries = vec![];
+    for token in &tokens {
+        if let brush_parser::Token::Operator(op, location) = token {
+            if op != "\n" {
+                continue;
+            }
+
+            // N.B. A newline token's location includes any comment preceding it.
+            let start = byte_offset(location.start.index);
+            let end = byte_offset(location.end.index);
+            if &command[start..end] == "\n" && !here_doc_body_starts.contains(&end) {
+                boundaries.push(start);
+            }
+        }
+    }
+
+    boundaries.into_iter()
+}
+
+fn choose_line_separator(
+    shell: &brush_core::Shell,
+    preceding: &str,
+    next_line: &str,
+) -> &'static str {
+    for separator in ["; ", " "] {
+        // We only reject a separator if it yields a definite syntax error; incomplete
+        // input is fine, since there may be more lines to come.
+        let candidate = std::format!("{preceding}{separator}{next_line}");
+        if !matches!(
+            shell.parse_string(candidate),
+            Err(brush_parser::ParseError::ParsingNearToken(_))
+        ) {
+            return separator;
+        }
+    }
+
+    "\n"
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn, clippy::unwrap_in_result)]
+mod tests {
+    use super::*;
+
+    async fn join(command: &str) -> Result<String, brush_core::Error> {
+        let shell = brush_core::Shell::new(&brush_core::CreateOptions::default()).await?;
+        Ok(join_multiline_command(&shell, command))
+    }
+
+    #[tokio::test]
+    async fn join_loops() -> Result<(), brush_core::Error> {
+        assert_eq!(
+            join("for i in 1 2\ndo\n  echo $i\ndone").await?,
+            "for i in 1 2; do echo $i; done"
+        );
+        assert_eq!(
+            join("while true; do\n  break\ndone\n").await?,
+            "while true; do break; done"
+        );
+        assert_eq!(join("echo a |\n  cat").await?, "echo a | cat");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_functions() -> Result<(), brush_core::Error> {
+        assert_eq!(
+            join("f() {\n  echo hi\n\n  echo there\n}").await?,
+            "f() { echo hi; echo there; }"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_preserves_quoted_newlines() -> Result<(), brush_core::Error> {
+        assert_eq!(
+            join("echo \"a\nb\"\necho 'c\nd'").await?,
+            "echo \"a\nb\"; echo 'c\nd'"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_preserves_here_documents() -> Result<(), brush_core::Error> {
+        assert_eq!(
+            join("cat <<EOF\nbody\nEOF\necho x\necho y").await?,
+            "cat <<EOF\nbody\nEOF\necho x; echo y"
+        );
+        assert_eq!(
+            join("if true; then\ncat <<-EOF; echo z\n\tbody\n\tEOF\nfi").await?,
+            "if true; then cat <<-EOF; echo z\n\tbody\n\tEOF\nfi"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn join_preserves_comments_and_continuations() -> Result<(), brush_core::Error> {
+        assert_eq!(
+            join("echo a # comment\necho b").await?,
+            "echo a # comment\necho b"
+        );
+        assert_eq!(join("echo a \\\n  b").await?, "echo a \\\n  b");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use brush_core::history;
+
+use super::refs;
+
+/// Maximum number of history items listed in a search menu.
+const MAX_SEARCH_RESULTS: usize = 1000;
+
+/// Baseline filter applied by a history search menu.
+#[derive(Clone, Copy)]
+pub(crate) enum HistorySearchScope {
+    /// Search all history.
+    All,
+    /// Only search commands run in the current working directory.
+    WorkingDir,
+}
+
+/// Implements the completer behind the interactive history search menus. Items are matched
+/// by substring against the text typed since the menu was opened; that text may also
+/// contain any of the following filter words, which narrow down the results:
+///
+/// * `@cwd` - only commands run in the current working directory.
+/// * `@ok` - only commands that completed successfully.
+/// * `@failed` - only commands that failed.
+/// * `@session` - only commands run in the current shell session.
+pub(crate) struct HistorySearchCompleter {
+    pub shell: refs::ShellRef,
+    pub scope: HistorySearchScope,
+}
+
+impl reedline::Completer for HistorySearchCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<reedline::Suggestion> {
+        let shell = tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(self.shell.lock())
+        });
+
+        let Some(history) = shell.history.as_ref() else {
+            return vec![];
+        };
+
+        let mut query = parse_search_text(line, shell.working_dir.as_path(), history);
+        if matches!(self.scope, HistorySearchScope::WorkingDir) {
+            query.working_dir = Some(history::DirectoryFilter::Exact(shell.working_dir.clone()));
+        }
+
+        // Replace the search text with the selected command line.
+        let span = reedline::Span {
+            start: pos.saturating_sub(line.len()),
+            end: pos,
+        };
+
+        // Only list the most recent instance of each command line.
+        let mut seen = HashSet::new();
+        history
+            .search(&query)
+            .into_iter()
+            .filter(|item| seen.insert(item.command_line.as_str()))
+            .take(MAX_SEARCH_RESULTS)
+            .map(|item| reedline::Suggestion {
+                value: item.command_line.clone(),
+                description: describe_item(item),
+                style: None,
+                extra: None,
+                span,
+                append_whitespace: false,
+            })
+            .collect()
+    }
+}
+
+/// Translates the text typed into a search menu into a history query.
+fn parse_search_text(
+    text: &str,
+    working_dir: &std::path::Path,
+    history: &history::History,
+) -> history::Query {
+    let mut query = history::Query {
+        newest_first: true,
+        ..history::Query::default()
+    };
+
+    let mut search_words = vec![];
+    for word in text.split(' ') {
+        match word {
+            "@cwd" => {
+                query.working_dir = Some(history::DirectoryFilter::Exact(working_dir.to_owned()));
+            }
+            "@ok" => query.exit_status = Some(history::ExitStatusFilter::Succeeded),
+            "@failed" => query.exit_status = Some(history::ExitStatusFilter::Failed),
+            "@session" => query.session_id = Some(history.session_id()),
+            word => search_words.push(word),
+        }
+    }
+
+    let search_text = search_words.join(" ");
+    let search_text = search_text.trim();
+    if !search_text.is_empty() {
+        query.command_line = Some(history::CommandLineFilter::Substring(
+            search_text.to_owned(),
+        ));
+    }
+
+    query
+}
+
+/// Summarizes how a command turned out, for display alongside it.
+fn describe_item(item: &history::Item) -> Option<String> {
+    let status = match item.exit_status {
+        Some(0) => String::from("ok"),
+        Some(status) => std::format!("exit {status}"),
+        None => return None,
+    };
+
+    match item.duration {
+        Some(duration) => Some(std::format!("{status}, {}", format_duration(duration))),
+        None => Some(status),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    if duration.as_secs() >= 60 {
+        std::format!("{}m{}s", duration.as_secs() / 60, duration.as_secs() % 60)
+    } else if duration.as_millis() >= 1000 {
+        std::format!("{:.1}s", duration.as_secs_f64())
+    } else {
+        std::format!("{}ms", duration.as_millis())
+    }
+}
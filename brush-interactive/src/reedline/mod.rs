@@ -1,5 +1,8 @@
 mod completer;
 mod highlighter;
+mod hinter;
+mod history;
+mod history_search;
 mod prompt;
 mod reedline_shell;
 mod refs;
@@ -3,7 +3,7 @@ use reedline::MenuBuilder;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use super::{completer, highlighter, refs, validator};
+use super::{completer, highlighter, hinter, history, history_search, refs, validator};
 use crate::{interactive_shell::InteractivePrompt, InteractiveShell, ReadResult, ShellError};
 
 /// Represents an interactive shell capable of taking commands from standard input
@@ -14,6 +14,8 @@ pub struct ReedlineShell {
 }
 
 const COMPLETION_MENU_NAME: &str = "completion_menu";
+const HISTORY_MENU_NAME: &str = "history_menu";
+const HISTORY_CWD_MENU_NAME: &str = "history_cwd_menu";
 
 impl ReedlineShell {
     /// Returns a new interactive shell instance, created with the provided options.
@@ -24,8 +26,10 @@ impl ReedlineShell {
     pub async fn new(options: &crate::Options) -> Result<ReedlineShell, ShellError> {
         // Set up shell first. Its initialization may influence how the
         // editor needs to operate.
-        let shell = brush_core::Shell::new(&options.shell).await?;
-        let history_file_path = shell.get_history_file_path();
+        let mut shell = brush_core::Shell::new(&options.shell).await?;
+        if let Err(e) = shell.load_history() {
+            tracing::warn!("couldn't load history: {e}");
+        }
 
         let shell_ref = Arc::new(Mutex::new(shell));
 
@@ -57,14 +61,38 @@ impl ReedlineShell {
                 .with_selected_match_text_style(Color::Blue.bold().reverse()),
         );
 
+        // Set up history search menus: one searching all history, and one only searching
+        // commands run in the current directory. Both accept further filters in the search
+        // text.
+        let history_menus = [
+            (HISTORY_MENU_NAME, history_search::HistorySearchScope::All),
+            (
+                HISTORY_CWD_MENU_NAME,
+                history_search::HistorySearchScope::WorkingDir,
+            ),
+        ]
+        .map(|(name, scope)| reedline::ReedlineMenu::WithCompleter {
+            menu: Box::new(
+                reedline::ListMenu::default()
+                    .with_name(name)
+                    .with_selected_text_style(Color::Blue.bold().reverse()),
+            ),
+            completer: Box::new(history_search::HistorySearchCompleter {
+                shell: shell_ref.clone(),
+                scope,
+            }),
+        });
+
         // Set up key bindings.
         let key_bindings = compose_key_bindings(COMPLETION_MENU_NAME);
 
-        // Set up default history-based hinter.
-        let mut hinter = reedline::DefaultHinter::default();
-        if !options.disable_color {
-            hinter = hinter.with_style(nu_ansi_term::Style::new().italic().fg(Color::DarkGray));
-        }
+        // Set up hinter for autosuggestions, drawing from history and completion.
+        let hinter_style = if options.disable_color {
+            nu_ansi_term::Style::new()
+        } else {
+            nu_ansi_term::Style::new().italic().fg(Color::DarkGray)
+        };
+        let hinter = hinter::ReedlineHinter::new(shell_ref.clone(), hinter_style);
 
         // Instantiate reedline with some defaults and hand it ownership of
         // the helpers.
@@ -78,19 +106,20 @@ impl ReedlineShell {
             .with_menu(reedline::ReedlineMenu::EngineCompleter(completion_menu))
             .with_edit_mode(Box::new(reedline::Emacs::new(key_bindings)));
 
+        for history_menu in history_menus {
+            reedline = reedline.with_menu(history_menu);
+        }
+
         // If requested, apply some additional niceties.
         if !options.disable_highlighting && !options.disable_color {
             reedline = reedline.with_highlighter(Box::new(highlighter));
         }
 
-        // If we have a history file, wire it up.
-        if let Some(history_file_path) = history_file_path {
-            if let Ok(history) =
-                reedline::FileBackedHistory::with_file(reedline::HISTORY_SIZE, history_file_path)
-            {
-                reedline = reedline.with_history(Box::new(history));
-            }
-        }
+        // Wire up the shell's history.
+        let history = history::ReedlineHistory {
+            shell: shell_ref.clone(),
+        };
+        reedline = reedline.with_history(Box::new(history));
 
         Ok(ReedlineShell {
             reedline,
@@ -130,7 +159,9 @@ impl InteractiveShell for ReedlineShell {
 
     /// Update history, if relevant.
     fn update_history(&mut self) -> Result<(), ShellError> {
-        // N.B. With our current usage, reedline auto-updates the history file.
+        let mut shell = self.shell_mut();
+        shell.as_mut().save_history()?;
+
         Ok(())
     }
 }
@@ -176,5 +207,17 @@ fn compose_key_bindings(completion_menu_name: &str) -> reedline::Keybindings {
         ]),
     );
 
+    // Wire up history search menus, replacing the default incremental search.
+    key_bindings.add_binding(
+        reedline::KeyModifiers::CONTROL,
+        reedline::KeyCode::Char('r'),
+        reedline::ReedlineEvent::Menu(HISTORY_MENU_NAME.to_string()),
+    );
+    key_bindings.add_binding(
+        reedline::KeyModifiers::ALT,
+        reedline::KeyCode::Char('r'),
+        reedline::ReedlineEvent::Menu(HISTORY_CWD_MENU_NAME.to_string()),
+    );
+
     key_bindings
 }
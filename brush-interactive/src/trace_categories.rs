@@ -1 +1,2 @@
+#[cfg(feature = "reedline")]
 pub(crate) const COMPLETION: &str = "completion";
@@ -0,0 +1,314 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::tokenizer::{tokenize_str_with_options, Token, TokenizerError, TokenizerOptions};
+
+/// Reserved words that may be followed by a command.
+const COMMAND_PRECEDING_RESERVED_WORDS: &[&str] = &[
+    "!", "{", "do", "elif", "else", "if", "then", "time", "until", "while",
+];
+
+/// An entry in the queue of tokens pending alias substitution.
+enum PendingItem {
+    /// A token, along with the names of the aliases whose substitution produced it.
+    Token(Token, Vec<String>),
+    /// Marks the end of the substitution of an alias whose value ended in a blank; the
+    /// word following it should also be checked for aliases.
+    CheckNextWord,
+}
+
+/// Performs alias substitution on the given tokens. Per POSIX, a word is checked for an
+/// alias if it's in a position where a command name is expected, or if it immediately
+/// follows the substitution of an alias whose value ended in a blank. An alias is not
+/// substituted again while its own value is being processed, which prevents (for example)
+/// `alias ls='ls -F'` from recursing infinitely.
+///
+/// # Arguments
+///
+/// * `tokens` - The tokens to process.
+/// * `aliases` - The alias definitions, mapping alias names to values.
+/// * `options` - The options to use when tokenizing alias values.
+pub(crate) fn expand_aliases(
+    tokens: Vec<Token>,
+    aliases: &HashMap<String, String>,
+    options: &TokenizerOptions,
+) -> Result<Vec<Token>, TokenizerError> {
+    if aliases.is_empty() {
+        return Ok(tokens);
+    }
+
+    let mut pending: VecDeque<_> = tokens
+        .into_iter()
+        .map(|token| PendingItem::Token(token, vec![]))
+        .collect();
+
+    let mut results = vec![];
+    let mut command_expected = true;
+    let mut check_next_word = false;
+    let mut words_to_skip = 0;
+
+    // Case patterns are never substituted; we track how deeply nested we are in `case`
+    // commands, whether we're in a pattern list, and how many words of a `case WORD in`
+    // header remain.
+    let mut case_depth = 0;
+    let mut in_case_pattern = false;
+    let mut case_header_words_left = 0;
+
+    while let Some(item) = pending.pop_front() {
+        let (token, expanding_aliases) = match item {
+            PendingItem::Token(token, expanding_aliases) => (token, expanding_aliases),
+            PendingItem::CheckNextWord => {
+                check_next_word = true;
+                continue;
+            }
+        };
+
+        let word = match &token {
+            Token::Operator(operator, _) if in_case_pattern => {
+                // A pattern list may be preceded by `(` and separated by `|`; the commands
+                // of the case item follow the closing `)`.
+                if operator == ")" {
+                    in_case_pattern = false;
+                    command_expected = true;
+                }
+
+                results.push(token);
+                continue;
+            }
+            Token::Operator(operator, _) => {
+                match operator.as_str() {
+                    // Here-documents are followed by their tag, body, and closing tag.
+                    "<<" | "<<-" => words_to_skip = 3,
+                    op if is_redirection_operator(op) => words_to_skip = 1,
+                    // A case item's pattern follows its terminator.
+                    ";;" | ";&" | ";;&" => {
+                        command_expected = false;
+                        in_case_pattern = case_depth > 0;
+                    }
+                    _ => command_expected = true,
+                }
+
+                check_next_word = false;
+                results.push(token);
+                continue;
+            }
+            Token::Word(word, _) => word.as_str(),
+        };
+
+        // Redirection targets and here-documents are never substituted.
+        if words_to_skip > 0 {
+            words_to_skip -= 1;
+            results.push(token);
+            continue;
+        }
+
+        // Nor are the words of a `case` header, or patterns.
+        if case_header_words_left > 0 {
+            case_header_words_left -= 1;
+            if case_header_words_left == 0 && word == "in" {
+                case_depth += 1;
+                in_case_pattern = true;
+            }
+
+            results.push(token);
+            continue;
+        }
+
+        if (in_case_pattern || command_expected) && case_depth > 0 && word == "esac" {
+            case_depth -= 1;
+            in_case_pattern = false;
+            command_expected = false;
+            check_next_word = false;
+            results.push(token);
+            continue;
+        }
+
+        if in_case_pattern {
+            results.push(token);
+            continue;
+        }
+
+        if command_expected || check_next_word {
+            if let Some(value) = aliases.get(word) {
+                if is_valid_alias_name(word) && !expanding_aliases.iter().any(|a| a == word) {
+                    let mut nested_aliases = expanding_aliases.clone();
+                    nested_aliases.push(word.to_owned());
+
+                    // N.B. The substituted tokens are attributed to the location of the
+                    // word they replace.
+                    let mut substituted = VecDeque::new();
+                    for mut value_token in tokenize_str_with_options(value.as_str(), options)? {
+                        relocate_token(&mut value_token, &token);
+                        substituted
+                            .push_back(PendingItem::Token(value_token, nested_aliases.clone()));
+                    }
+
+                    if value.ends_with([' ', '\t']) {
+                        substituted.push_back(PendingItem::CheckNextWord);
+                    }
+
+                    // Process the substituted tokens before anything else.
+                    check_next_word = false;
+                    while let Some(item) = substituted.pop_back() {
+                        pending.push_front(item);
+                    }
+
+                    continue;
+                }
+            }
+        }
+
+        if command_expected && word == "case" {
+            case_header_words_left = 2;
+        }
+
+        if command_expected
+            && !COMMAND_PRECEDING_RESERVED_WORDS.contains(&word)
+            && !is_assignment_word(word)
+        {
+            command_expected = false;
+        }
+
+        check_next_word = false;
+        results.push(token);
+    }
+
+    Ok(results)
+}
+
+fn is_redirection_operator(operator: &str) -> bool {
+    matches!(
+        operator,
+        "<" | ">" | ">|" | ">>" | "<&" | ">&" | "<>" | "<<<" | "&>" | "&>>"
+    )
+}
+
+fn is_valid_alias_name(word: &str) -> bool {
+    !word.is_empty()
+        && !word
+            .chars()
+            .any(|c| c.is_whitespace() || "/$`='\"\\|&;()<>".contains(c))
+}
+
+/// Checks if the given word is a variable assignment (e.g., `x=1`, `arr[0]=1`, or `x+=1`).
+fn is_assignment_word(word: &str) -> bool {
+    let Some(equals_index) = word.find('=') else {
+        return false;
+    };
+
+    let name = word[..equals_index].trim_end_matches('+');
+    let name = match name.find('[') {
+        Some(bracket_index) if name.ends_with(']') => &name[..bracket_index],
+        Some(_) => return false,
+        None => name,
+    };
+
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && name.chars().all(|c| c == '_' || c.is_ascii_alphanumeric())
+}
+
+fn relocate_token(token: &mut Token, original: &Token) {
+    let location = original.location().clone();
+    match token {
+        Token::Operator(_, l) | Token::Word(_, l) => *l = location,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::panic_in_result_fn)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn expand_str(input: &str, aliases: &[(&str, &str)]) -> Result<Vec<String>> {
+        let aliases = aliases
+            .iter()
+            .map(|(name, value)| ((*name).to_owned(), (*value).to_owned()))
+            .collect();
+
+        let tokens = crate::tokenize_str(input)?;
+        let tokens = expand_aliases(tokens, &aliases, &TokenizerOptions::default())?;
+
+        Ok(tokens.iter().map(|t| t.to_str().to_owned()).collect())
+    }
+
+    #[test]
+    fn substitutes_command_words_only() -> Result<()> {
+        assert_eq!(
+            expand_str("ll ll; x=1 ll | ll", &[("ll", "ls -l")])?,
+            vec!["ls", "-l", "ll", ";", "x=1", "ls", "-l", "|", "ls", "-l"]
+        );
+        assert_eq!(
+            expand_str("if ll; then ll; fi", &[("ll", "ls")])?,
+            vec!["if", "ls", ";", "then", "ls", ";", "fi"]
+        );
+        assert_eq!(
+            expand_str("echo >ll ll", &[("ll", "ls")])?,
+            vec!["echo", ">", "ll", "ll"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_substitute_quoted_words() -> Result<()> {
+        assert_eq!(
+            expand_str("'ll' \\ll", &[("ll", "ls")])?,
+            vec!["'ll'", "\\ll"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_recurse_infinitely() -> Result<()> {
+        assert_eq!(expand_str("ls", &[("ls", "ls -F")])?, vec!["ls", "-F"]);
+        assert_eq!(expand_str("a", &[("a", "b"), ("b", "a")])?, vec!["a"]);
+        Ok(())
+    }
+
+    #[test]
+    fn chains_after_trailing_blank() -> Result<()> {
+        assert_eq!(
+            expand_str("sudo ll ll", &[("sudo", "sudo "), ("ll", "ls -l")])?,
+            vec!["sudo", "ls", "-l", "ll"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn does_not_substitute_case_patterns() -> Result<()> {
+        assert_eq!(
+            expand_str(
+                "case ll in\n(ll|ll) ll;;\nll) ll;;\nesac; ll",
+                &[("ll", "ls")]
+            )?,
+            vec![
+                "case", "ll", "in", "\n", "(", "ll", "|", "ll", ")", "ls", ";;", "\n", "ll", ")",
+                "ls", ";;", "\n", "esac", ";", "ls"
+            ]
+        );
+        assert_eq!(
+            expand_str(
+                "case x in a) case y in ll) ll;; esac;; ll) ll\nesac",
+                &[("ll", "ls")]
+            )?,
+            vec![
+                "case", "x", "in", "a", ")", "case", "y", "in", "ll", ")", "ls", ";;", "esac",
+                ";;", "ll", ")", "ls", "\n", "esac"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn substitutes_operators_and_keywords() -> Result<()> {
+        assert_eq!(
+            expand_str("both", &[("both", "echo a; echo b")])?,
+            vec!["echo", "a", ";", "echo", "b"]
+        );
+        assert_eq!(
+            expand_str("when true; then x; fi", &[("when", "if")])?,
+            vec!["if", "true", ";", "then", "x", ";", "fi"]
+        );
+        Ok(())
+    }
+}
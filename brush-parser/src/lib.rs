@@ -9,10 +9,13 @@ pub mod prompt;
 pub mod test_command;
 pub mod word;
 
+mod alias;
 mod error;
 mod parser;
 mod tokenizer;
 
 pub use error::{ParseError, TestCommandParseError, WordParseError};
 pub use parser::{parse_tokens, Parser, ParserOptions, SourceInfo};
-pub use tokenizer::{tokenize_str, unquote_str, SourcePosition, Token, TokenLocation};
+pub use tokenizer::{
+    tokenize_str, unquote_str, SourcePosition, Token, TokenLocation, TokenizerError,
+};
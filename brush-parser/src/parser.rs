@@ -1,3 +1,6 @@
+use std::collections::HashMap;
+
+use crate::alias;
 use crate::ast::{self, SeparatorOperator};
 use crate::error;
 use crate::tokenizer::{Token, TokenEndReason, Tokenizer, TokenizerOptions, Tokens};
@@ -31,6 +34,11 @@ pub struct Parser<R> {
     reader: R,
     options: ParserOptions,
     source_info: SourceInfo,
+    aliases: Option<HashMap<String, String>>,
+    /// The input's tokens, once it's been tokenized for incremental parsing.
+    tokens: Option<Vec<Token>>,
+    /// Index of the first token not yet consumed by incremental parsing.
+    next_token_index: usize,
 }
 
 impl<R: std::io::BufRead> Parser<R> {
@@ -46,9 +54,22 @@ impl<R: std::io::BufRead> Parser<R> {
             reader,
             options: options.clone(),
             source_info: source_info.clone(),
+            aliases: None,
+            tokens: None,
+            next_token_index: 0,
         }
     }
 
+    /// Enables alias substitution while parsing, using the given alias definitions.
+    ///
+    /// # Arguments
+    ///
+    /// * `aliases` - The alias definitions, mapping alias names to values.
+    pub fn set_aliases(mut self, aliases: &HashMap<String, String>) -> Self {
+        self.aliases = Some(aliases.clone());
+        self
+    }
+
     /// Parses the input into an abstract syntax tree (AST) of a shell program.
     pub fn parse(&mut self) -> Result<ast::Program, error::ParseError> {
         //
@@ -59,14 +80,95 @@ impl<R: std::io::BufRead> Parser<R> {
         //   * https://pubs.opengroup.org/onlinepubs/9699919799/utilities/V3_chap02.html
         //
 
-        // First we tokenize the input, according to the policy implied by provided options.
-        let mut tokenizer = Tokenizer::new(
-            &mut self.reader,
-            &TokenizerOptions {
-                enable_extended_globbing: self.options.enable_extended_globbing,
-                posix_mode: self.options.posix_mode,
-            },
-        );
+        let mut tokens = self.tokenize()?;
+
+        // Substitute aliases, if enabled.
+        if let Some(aliases) = &self.aliases {
+            tokens = expand_aliases(tokens, aliases, &self.tokenizer_options())?;
+        }
+
+        parse_tokens(&tokens, &self.options, &self.source_info)
+    }
+
+    /// Parses the next complete command in the input, returning `None` once the input has
+    /// been exhausted. This allows the caller to execute each command before the next one
+    /// is parsed, so that (for example) aliases it defines take effect for later commands.
+    /// The input is tokenized in full on first use.
+    ///
+    /// # Arguments
+    ///
+    /// * `aliases` - If alias substitution is enabled, the alias definitions to use for this
+    ///   command, mapping alias names to values.
+    pub fn parse_next_command(
+        &mut self,
+        aliases: Option<&HashMap<String, String>>,
+    ) -> Result<Option<ast::Program>, error::ParseError> {
+        if self.tokens.is_none() {
+            self.tokens = Some(self.tokenize()?);
+        }
+
+        let tokens = self.tokens.as_deref().unwrap_or_default();
+        let is_newline = |token: &Token| matches!(token, Token::Operator(op, _) if op == "\n");
+
+        // Skip any blank lines.
+        let start = tokens[self.next_token_index..]
+            .iter()
+            .position(|token| !is_newline(token))
+            .map_or(tokens.len(), |offset| self.next_token_index + offset);
+
+        if start == tokens.len() {
+            self.next_token_index = start;
+            return Ok(None);
+        }
+
+        // Try parsing through the end of each line in turn, until we have a complete command.
+        // There's no point trying while we're obviously still inside a compound command; this
+        // keeps parsing large functions linear.
+        let mut nesting = NestingTracker::default();
+        let mut end = start;
+        loop {
+            let line_end = tokens[end..]
+                .iter()
+                .position(is_newline)
+                .map_or(tokens.len(), |offset| end + offset + 1);
+
+            tokens[end..line_end]
+                .iter()
+                .for_each(|token| nesting.process(token));
+            end = line_end;
+
+            if nesting.is_nested() && end < tokens.len() {
+                continue;
+            }
+
+            let mut command_tokens = tokens[start..end].to_vec();
+            if let Some(aliases) = aliases {
+                command_tokens =
+                    expand_aliases(command_tokens, aliases, &self.tokenizer_options())?;
+            }
+
+            match parse_tokens(&command_tokens, &self.options, &self.source_info) {
+                Err(error::ParseError::ParsingAtEndOfInput) if end < tokens.len() => (),
+                result => {
+                    // Don't try to parse anything past an error.
+                    self.next_token_index = if result.is_ok() { end } else { tokens.len() };
+                    return result.map(Some);
+                }
+            }
+        }
+    }
+
+    fn tokenizer_options(&self) -> TokenizerOptions {
+        TokenizerOptions {
+            enable_extended_globbing: self.options.enable_extended_globbing,
+            posix_mode: self.options.posix_mode,
+        }
+    }
+
+    /// Tokenizes the input, according to the policy implied by provided options.
+    fn tokenize(&mut self) -> Result<Vec<Token>, error::ParseError> {
+        let tokenizer_options = self.tokenizer_options();
+        let mut tokenizer = Tokenizer::new(&mut self.reader, &tokenizer_options);
 
         tracing::debug!(target: "tokenize", "Tokenizing...");
 
@@ -95,10 +197,87 @@ impl<R: std::io::BufRead> Parser<R> {
 
         tracing::debug!(target: "tokenize", "  => {} token(s)", tokens.len());
 
-        parse_tokens(&tokens, &self.options, &self.source_info)
+        Ok(tokens)
+    }
+}
+
+/// Tracks which compound commands are open in a sequence of tokens. This is only a
+/// heuristic: it understands just enough to recognize input that's clearly incomplete.
+#[derive(Default)]
+struct NestingTracker {
+    /// The tokens expected to close each open compound command, innermost last.
+    expected_closers: Vec<&'static str>,
+    /// Whether we're partway through a simple command, where reserved words aren't
+    /// recognized.
+    in_command: bool,
+}
+
+impl NestingTracker {
+    fn is_nested(&self) -> bool {
+        !self.expected_closers.is_empty()
+    }
+
+    fn process(&mut self, token: &Token) {
+        match token {
+            Token::Operator(op, _) => {
+                match op.as_str() {
+                    "(" => self.expected_closers.push(")"),
+                    // N.B. Case patterns end with an unmatched `)`.
+                    ")" if self.expected_closers.last() == Some(&")") => {
+                        self.expected_closers.pop();
+                    }
+                    _ => (),
+                }
+
+                self.in_command = false;
+            }
+            Token::Word(word, _) => {
+                let reserved_word = !self.in_command;
+                if reserved_word {
+                    match word.as_str() {
+                        "if" => self.expected_closers.push("fi"),
+                        "case" => self.expected_closers.push("esac"),
+                        "for" | "select" | "while" | "until" => {
+                            self.expected_closers.push("done");
+                        }
+                        "{" => self.expected_closers.push("}"),
+                        closer if self.expected_closers.last() == Some(&closer) => {
+                            self.expected_closers.pop();
+                        }
+                        _ => (),
+                    }
+                }
+
+                // Some reserved words are followed by another command.
+                self.in_command = !(reserved_word
+                    && matches!(
+                        word.as_str(),
+                        "!" | "{"
+                            | "do"
+                            | "elif"
+                            | "else"
+                            | "if"
+                            | "then"
+                            | "time"
+                            | "until"
+                            | "while"
+                    ));
+            }
+        }
     }
 }
 
+fn expand_aliases(
+    tokens: Vec<Token>,
+    aliases: &HashMap<String, String>,
+    options: &TokenizerOptions,
+) -> Result<Vec<Token>, error::ParseError> {
+    alias::expand_aliases(tokens, aliases, options).map_err(|inner| error::ParseError::Tokenizing {
+        inner,
+        position: None,
+    })
+}
+
 /// Parses a sequence of tokens into the abstract syntax tree (AST) of a shell program.
 ///
 /// # Arguments
@@ -1012,6 +1191,47 @@ esac\
         Ok(())
     }
 
+    #[test]
+    fn parse_commands_incrementally() -> Result<()> {
+        let input = r"echo 1; echo 2
+
+if true; then
+  case x in
+    x) echo 3 ;;
+  esac
+fi
+cat <<EOF
+body
+EOF
+greet
+";
+
+        let mut reader = std::io::BufReader::new(input.as_bytes());
+        let mut parser = Parser::new(
+            &mut reader,
+            &ParserOptions::default(),
+            &SourceInfo::default(),
+        );
+
+        let mut command_counts = vec![];
+        while let Some(program) = parser.parse_next_command(None)? {
+            command_counts.push(program.complete_commands[0].0.len());
+            if command_counts.len() == 3 {
+                break;
+            }
+        }
+        assert_eq!(command_counts, [2, 1, 1]);
+
+        // Aliases in effect when a command is parsed are applied to it.
+        let aliases = HashMap::from([("greet".to_owned(), "echo hi; echo there".to_owned())]);
+        let program = parser.parse_next_command(Some(&aliases))?;
+        assert_matches!(program, Some(p) if p.complete_commands[0].0.len() == 2);
+
+        assert_matches!(parser.parse_next_command(None)?, None);
+
+        Ok(())
+    }
+
     #[test]
     fn test_parse_program() -> Result<()> {
         let input = r#"
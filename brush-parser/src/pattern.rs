@@ -16,6 +16,21 @@ pub enum ExtendedGlobKind {
     Star,
 }
 
+/// Options influencing how shell patterns are translated to regular expressions.
+#[derive(Clone, Copy, Default)]
+pub struct PatternTranslationOptions<'a> {
+    /// Whether to enable extended globbing (extglob).
+    pub enable_extended_globbing: bool,
+    /// Whether character classes in bracket expressions (e.g., `[:alpha:]`) should match
+    /// non-ASCII characters according to their Unicode properties; if not, only ASCII
+    /// characters are matched by them.
+    pub unicode_char_classes: bool,
+    /// Optionally provides the characters matched by a range expression in a bracket
+    /// expression (e.g., `a-z`), given its start and end characters. If not provided,
+    /// ranges match characters by code point.
+    pub range_chars: Option<&'a dyn Fn(char, char) -> Vec<char>>,
+}
+
 /// Converts a shell pattern to a regular expression string.
 ///
 /// # Arguments
@@ -26,13 +41,32 @@ pub fn pattern_to_regex_str(
     pattern: &str,
     enable_extended_globbing: bool,
 ) -> Result<String, error::WordParseError> {
-    let regex_str = pattern_to_regex_translator::pattern(pattern, enable_extended_globbing)
+    pattern_to_regex_str_with_options(
+        pattern,
+        &PatternTranslationOptions {
+            enable_extended_globbing,
+            ..PatternTranslationOptions::default()
+        },
+    )
+}
+
+/// Converts a shell pattern to a regular expression string, using the given options.
+///
+/// # Arguments
+///
+/// * `pattern` - The shell pattern to convert.
+/// * `options` - Options influencing the translation.
+pub fn pattern_to_regex_str_with_options(
+    pattern: &str,
+    options: &PatternTranslationOptions<'_>,
+) -> Result<String, error::WordParseError> {
+    let regex_str = pattern_to_regex_translator::pattern(pattern, options)
         .map_err(error::WordParseError::Pattern)?;
     Ok(regex_str)
 }
 
 peg::parser! {
-    grammar pattern_to_regex_translator(enable_extended_globbing: bool) for str {
+    grammar pattern_to_regex_translator(options: &PatternTranslationOptions<'_>) for str {
         pub(crate) rule pattern() -> String =
             pieces:(pattern_piece()*) {
                 pieces.join("")
@@ -70,13 +104,30 @@ peg::parser! {
             char_list()
 
         rule char_class_expression() -> String =
-            e:$("[:" char_class() ":]") { e.to_owned() }
+            "[:" class:char_class() ":]" {
+                if options.unicode_char_classes {
+                    if let Some(s) = unicode_char_class_regex_str(class) {
+                        return s.to_owned();
+                    }
+                }
+
+                std::format!("[:{class}:]")
+            }
 
-        rule char_class() =
-            "alnum" / "alpha" / "blank" / "cntrl" / "digit" / "graph" / "lower" / "print" / "punct" / "space" / "upper"/ "xdigit"
+        rule char_class() -> &'input str =
+            $("alnum" / "alpha" / "blank" / "cntrl" / "digit" / "graph" / "lower" / "print" / "punct" / "space" / "upper"/ "xdigit")
 
         rule char_range() -> String =
-            range:$([_] "-" [_]) { range.to_owned() }
+            start:[_] "-" end:[_] {
+                if let Some(range_chars) = options.range_chars {
+                    range_chars(start, end)
+                        .into_iter()
+                        .map(escape_char_class_char)
+                        .collect()
+                } else {
+                    std::format!("{start}-{end}")
+                }
+            }
 
         rule char_list() -> String =
             chars:$([c if c != ']']+) { escape_char_class_char_list(chars) }
@@ -86,7 +137,7 @@ peg::parser! {
             "*" { String::from(".*") }
 
         rule extglob_enabled() -> () =
-            &[_] {? if enable_extended_globbing { Ok(()) } else { Err("extglob disabled") } }
+            &[_] {? if options.enable_extended_globbing { Ok(()) } else { Err("extglob disabled") } }
 
         pub(crate) rule extended_glob_pattern() -> String =
             kind:extended_glob_prefix() "(" branches:extended_glob_body() ")" {
@@ -150,11 +201,44 @@ fn escape_char_class_char_list(s: &str) -> String {
     s.replace('[', r"\[")
 }
 
+fn escape_char_class_char(c: char) -> String {
+    if regex_char_needs_escaping(c) || matches!(c, '-' | '&' | '~') {
+        std::format!("\\{c}")
+    } else {
+        c.to_string()
+    }
+}
+
+/// Returns a regular expression fragment, usable within a character class, that matches
+/// the characters in the named POSIX character class according to their Unicode properties.
+/// Returns `None` for classes that only contain ASCII characters.
+fn unicode_char_class_regex_str(class: &str) -> Option<&'static str> {
+    match class {
+        "alnum" => Some(r"\p{Alphabetic}\p{Nd}"),
+        "alpha" => Some(r"\p{Alphabetic}"),
+        "blank" => Some(r"\t\p{Zs}"),
+        "cntrl" => Some(r"\p{Cc}"),
+        "graph" => Some(r"[^\p{White_Space}\p{C}]"),
+        "lower" => Some(r"\p{Lowercase}"),
+        "print" => Some(r"[^\p{C}\p{Zl}\p{Zp}]"),
+        "punct" => Some(r"\p{P}\p{S}"),
+        "space" => Some(r"\p{White_Space}"),
+        "upper" => Some(r"\p{Uppercase}"),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use anyhow::Result;
 
+    const EXTGLOB_OPTIONS: PatternTranslationOptions<'static> = PatternTranslationOptions {
+        enable_extended_globbing: true,
+        unicode_char_classes: false,
+        range_chars: None,
+    };
+
     #[test]
     fn test_bracket_exprs() -> Result<()> {
         assert_eq!(pattern_to_regex_str("[a-z]", true)?, "[a-z]");
@@ -167,20 +251,50 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_locale_sensitive_bracket_exprs() -> Result<()> {
+        let range_chars = |start: char, end: char| {
+            if start == 'a' && end == 'c' {
+                vec!['a', 'A', 'b', 'B', 'c']
+            } else {
+                vec![]
+            }
+        };
+        let options = PatternTranslationOptions {
+            enable_extended_globbing: false,
+            unicode_char_classes: true,
+            range_chars: Some(&range_chars),
+        };
+
+        assert_eq!(
+            pattern_to_regex_str_with_options("[a-c]", &options)?,
+            "[aAbBc]"
+        );
+        assert_eq!(
+            pattern_to_regex_str_with_options("[![:alpha:]]", &options)?,
+            r"[^\p{Alphabetic}]"
+        );
+        assert_eq!(
+            pattern_to_regex_str_with_options("[[:digit:]]", &options)?,
+            "[[:digit:]]"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_extended_glob() -> Result<()> {
         assert_eq!(
-            pattern_to_regex_translator::extended_glob_pattern("@(a|b)", true)?,
+            pattern_to_regex_translator::extended_glob_pattern("@(a|b)", &EXTGLOB_OPTIONS)?,
             "(a|b)"
         );
 
         assert_eq!(
-            pattern_to_regex_translator::extended_glob_body("ab|ac", true)?,
+            pattern_to_regex_translator::extended_glob_body("ab|ac", &EXTGLOB_OPTIONS)?,
             vec!["ab", "ac"],
         );
 
         assert_eq!(
-            pattern_to_regex_translator::extended_glob_pattern("*(ab|ac)", true)?,
+            pattern_to_regex_translator::extended_glob_pattern("*(ab|ac)", &EXTGLOB_OPTIONS)?,
             "(ab|ac)*"
         );
 
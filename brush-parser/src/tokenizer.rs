@@ -471,8 +471,21 @@ pub fn tokenize_str(input: &str) -> Result<Vec<Token>, TokenizerError> {
 
 #[cached::proc_macro::cached(size = 64, result = true)]
 pub fn cacheable_tokenize_str(input: String) -> Result<Vec<Token>, TokenizerError> {
+    tokenize_str_with_options(input.as_str(), &TokenizerOptions::default())
+}
+
+/// Break the given input shell script string into tokens, returning the tokens.
+///
+/// # Arguments
+///
+/// * `input` - The shell script to tokenize.
+/// * `options` - The options to use when tokenizing.
+pub(crate) fn tokenize_str_with_options(
+    input: &str,
+    options: &TokenizerOptions,
+) -> Result<Vec<Token>, TokenizerError> {
     let mut reader = std::io::BufReader::new(input.as_bytes());
-    let mut tokenizer = crate::tokenizer::Tokenizer::new(&mut reader, &TokenizerOptions::default());
+    let mut tokenizer = crate::tokenizer::Tokenizer::new(&mut reader, options);
 
     let mut tokens = vec![];
     loop {
@@ -19,8 +19,79 @@ struct BrushCtlCommand {
 
 #[derive(Subcommand)]
 enum CommandGroup {
+    #[clap(subcommand)]
+    Autosuggest(AutosuggestCommand),
     #[clap(subcommand)]
     Events(EventsCommand),
+    History(HistoryCommand),
+}
+
+/// Commands for configuring interactive autosuggestions.
+#[derive(Subcommand)]
+enum AutosuggestCommand {
+    /// Display status of autosuggestions and their sources.
+    Status,
+
+    /// Enable autosuggestions, or one of their sources.
+    Enable {
+        /// Source of suggestions to enable; if not specified, enables autosuggestions.
+        source: Option<AutosuggestionSource>,
+    },
+
+    /// Disable autosuggestions, or one of their sources.
+    Disable {
+        /// Source of suggestions to disable; if not specified, disables autosuggestions.
+        source: Option<AutosuggestionSource>,
+    },
+}
+
+/// Source of autosuggestions.
+#[derive(Clone, clap::ValueEnum)]
+enum AutosuggestionSource {
+    /// Suggest previously executed commands from history.
+    #[clap(name = "history")]
+    History,
+    /// Suggest candidates generated by basic (non-programmable) completion.
+    #[clap(name = "completion")]
+    Completion,
+}
+
+/// Query command history, along with metadata about each command's execution. Items
+/// are displayed oldest first, one per line, with tab-separated fields: id, start time
+/// (seconds since the epoch), duration (milliseconds), exit status, working directory,
+/// and command line. Fields that weren't recorded are displayed as `-`.
+#[derive(clap::Args)]
+struct HistoryCommand {
+    /// Only display commands run in the given directory.
+    #[arg(long = "cwd", value_name = "DIR", conflicts_with = "here")]
+    working_dir: Option<std::path::PathBuf>,
+
+    /// Only display commands run in the current directory.
+    #[arg(long = "here")]
+    here: bool,
+
+    /// Only display commands that completed successfully.
+    #[arg(long = "succeeded", conflicts_with = "failed")]
+    succeeded: bool,
+
+    /// Only display commands that failed.
+    #[arg(long = "failed")]
+    failed: bool,
+
+    /// Only display commands run in the current session.
+    #[arg(long = "session")]
+    session: bool,
+
+    /// Only display the most recent N matching commands.
+    #[arg(short = 'n', long = "limit", value_name = "N")]
+    limit: Option<usize>,
+
+    /// Only display command lines, omitting metadata.
+    #[arg(short = 'c', long = "commands-only")]
+    commands_only: bool,
+
+    /// Only display commands containing the given text.
+    text: Option<String>,
 }
 
 /// Commands for configuring tracing events.
@@ -45,11 +116,150 @@ enum EventsCommand {
 impl brush_core::builtins::Command for BrushCtlCommand {
     async fn execute(
         &self,
-        context: brush_core::ExecutionContext<'_>,
+        mut context: brush_core::ExecutionContext<'_>,
     ) -> Result<brush_core::builtins::ExitCode, brush_core::Error> {
         match self.command_group {
+            CommandGroup::Autosuggest(ref autosuggest) => autosuggest.execute(&mut context),
             CommandGroup::Events(ref events) => events.execute(&context),
+            CommandGroup::History(ref history) => history.execute(&context),
+        }
+    }
+}
+
+impl AutosuggestCommand {
+    fn execute(
+        &self,
+        context: &mut brush_core::ExecutionContext<'_>,
+    ) -> Result<brush_core::builtins::ExitCode, brush_core::Error> {
+        match self {
+            AutosuggestCommand::Status => {
+                let options = &context.shell.options;
+                let to_str = |enabled: bool| if enabled { "on" } else { "off" };
+
+                let mut stdout = context.stdout();
+                writeln!(
+                    stdout,
+                    "autosuggestions\t{}",
+                    to_str(options.autosuggestions)
+                )?;
+                writeln!(
+                    stdout,
+                    "history\t{}",
+                    to_str(options.autosuggestions_from_history)
+                )?;
+                writeln!(
+                    stdout,
+                    "completion\t{}",
+                    to_str(options.autosuggestions_from_completion)
+                )?;
+            }
+            AutosuggestCommand::Enable { source } => {
+                set_autosuggestion_option(&mut context.shell.options, source.as_ref(), true);
+            }
+            AutosuggestCommand::Disable { source } => {
+                set_autosuggestion_option(&mut context.shell.options, source.as_ref(), false);
+            }
+        }
+
+        Ok(brush_core::builtins::ExitCode::Success)
+    }
+}
+
+fn set_autosuggestion_option(
+    options: &mut brush_core::RuntimeOptions,
+    source: Option<&AutosuggestionSource>,
+    value: bool,
+) {
+    match source {
+        None => options.autosuggestions = value,
+        Some(AutosuggestionSource::History) => options.autosuggestions_from_history = value,
+        Some(AutosuggestionSource::Completion) => options.autosuggestions_from_completion = value,
+    }
+}
+
+impl HistoryCommand {
+    fn execute(
+        &self,
+        context: &brush_core::ExecutionContext<'_>,
+    ) -> Result<brush_core::builtins::ExitCode, brush_core::Error> {
+        // Non-interactive shells don't load history; in that case, we read it straight from
+        // the history file.
+        let loaded_history;
+        let history = if let Some(history) = &context.shell.history {
+            history
+        } else if let Some(history_file_path) = context.shell.get_history_file_path() {
+            let options = &context.shell.options;
+            loaded_history = brush_core::history::History::load(
+                history_file_path.as_path(),
+                options.save_multiline_cmds_in_history
+                    && options.embed_newlines_in_multiline_cmds_in_history,
+            )?;
+            &loaded_history
+        } else {
+            writeln!(context.stderr(), "brushctl: history is not available")?;
+            return Ok(brush_core::builtins::ExitCode::Custom(1));
+        };
+
+        let working_dir = if self.here {
+            Some(context.shell.working_dir.clone())
+        } else {
+            self.working_dir
+                .as_ref()
+                .map(|dir| context.shell.get_absolute_path(dir))
+        };
+
+        let exit_status = if self.succeeded {
+            Some(brush_core::history::ExitStatusFilter::Succeeded)
+        } else if self.failed {
+            Some(brush_core::history::ExitStatusFilter::Failed)
+        } else {
+            None
+        };
+
+        // Search newest first so the limit selects the most recent items.
+        let query = brush_core::history::Query {
+            command_line: self
+                .text
+                .clone()
+                .map(brush_core::history::CommandLineFilter::Substring),
+            working_dir: working_dir.map(brush_core::history::DirectoryFilter::Exact),
+            exit_status,
+            session_id: self.session.then(|| history.session_id()),
+            newest_first: true,
+            limit: self.limit,
+            ..brush_core::history::Query::default()
+        };
+
+        let mut stdout = context.stdout();
+        for item in history.search(&query).into_iter().rev() {
+            if self.commands_only {
+                writeln!(stdout, "{}", item.command_line)?;
+                continue;
+            }
+
+            let start_time = item
+                .start_time
+                .and_then(|t| t.duration_since(std::time::SystemTime::UNIX_EPOCH).ok())
+                .map_or_else(|| "-".to_owned(), |d| d.as_secs().to_string());
+            let duration = item
+                .duration
+                .map_or_else(|| "-".to_owned(), |d| d.as_millis().to_string());
+            let exit_status = item
+                .exit_status
+                .map_or_else(|| "-".to_owned(), |s| s.to_string());
+            let working_dir = item
+                .working_dir
+                .as_ref()
+                .map_or_else(|| "-".to_owned(), |d| d.to_string_lossy().to_string());
+
+            writeln!(
+                stdout,
+                "{}\t{start_time}\t{duration}\t{exit_status}\t{working_dir}\t{}",
+                item.id, item.command_line
+            )?;
         }
+
+        Ok(brush_core::builtins::ExitCode::Success)
     }
 }
 
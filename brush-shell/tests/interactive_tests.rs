@@ -116,6 +116,82 @@ fn run_pipeline_interactively() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn run_hook_functions() -> anyhow::Result<()> {
+    let mut session = start_shell_session()?;
+
+    session.expect_prompt()?;
+    session.send_line(r#"pre() { echo "pre:[$1]"; }; post() { echo "post:[$1]:$2"; }"#)?;
+    session.expect_prompt()?;
+    session.send_line("chpwd() { echo \"chpwd:$2\"; }")?;
+    session.expect_prompt()?;
+    session.send_line(
+        "BRUSH_PREEXEC_FUNCTIONS=(pre); BRUSH_POSTEXEC_FUNCTIONS=(post); BRUSH_CHPWD_FUNCTIONS=(chpwd)",
+    )?;
+    session.expect_prompt()?;
+
+    // Make sure the hooks are invoked, in order, with the expected arguments.
+    let output = session.exec_output("cd / && false")?;
+    let pre_index = output.find("pre:[cd / && false]");
+    let chpwd_index = output.find("chpwd:/");
+    let post_index = output.find("post:[cd / && false]:1");
+    assert!(pre_index.is_some());
+    assert!(pre_index < chpwd_index);
+    assert!(chpwd_index < post_index);
+
+    // Exit the shell.
+    session.exit()?;
+
+    Ok(())
+}
+
+#[test]
+fn survive_failing_hook_functions() -> anyhow::Result<()> {
+    let mut session = start_shell_session()?;
+
+    session.expect_prompt()?;
+    session.send_line("fail() { local x; : ${x:?hook failed}; }")?;
+    session.expect_prompt()?;
+    session.send_line(
+        "BRUSH_PREEXEC_FUNCTIONS=(fail); BRUSH_POSTEXEC_FUNCTIONS=(fail); BRUSH_CHPWD_FUNCTIONS=(fail)",
+    )?;
+    session.expect_prompt()?;
+
+    // Make sure the commands still run, and the shell keeps going.
+    let output = session.exec_output(r#"cd / && echo "pwd:$PWD""#)?;
+    assert!(output.contains("pwd:/"));
+    let output = session.exec_output("echo still-here")?;
+    assert!(output.contains("still-here"));
+
+    // Exit the shell.
+    session.exit()?;
+
+    Ok(())
+}
+
+#[test]
+fn run_autocd_and_cdspell() -> anyhow::Result<()> {
+    let mut session = start_shell_session()?;
+
+    session.expect_prompt()?;
+    session.send_line("shopt -s autocd cdspell; cd /")?;
+    session.expect_prompt()?;
+
+    // Make sure a bare directory name changes to that directory.
+    let output = session.exec_output(r#"usr; echo "pwd:$PWD""#)?;
+    assert!(output.contains("pwd:/usr"));
+
+    // Make sure a misspelled directory name is corrected.
+    let output = session.exec_output(r#"cd /usr/bni && echo "pwd:$PWD""#)?;
+    assert!(output.contains("/usr/bin"));
+    assert!(output.contains("pwd:/usr/bin"));
+
+    // Exit the shell.
+    session.exit()?;
+
+    Ok(())
+}
+
 //
 // Helpers
 //